@@ -0,0 +1,181 @@
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+use crate::keyframe::retime_input_keys;
+use crate::pxc::PxcFile;
+
+const DEFAULT_FRAMES_TOTAL: u32 = 30;
+const DEFAULT_FRAMERATE: f64 = 30.0;
+
+/// The project's top-level `animator` struct.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AnimationSettings {
+    pub frames_total: u32,
+    pub framerate: f64,
+    /// Playback range `[start, end]`, when one is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_range: Option<[i64; 2]>,
+}
+
+impl AnimationSettings {
+    fn check(&self) -> Result<()> {
+        if self.frames_total == 0 {
+            bail!("frame count must be at least 1");
+        }
+        if self.framerate.is_nan() || self.framerate <= 0.0 {
+            bail!("framerate must be positive");
+        }
+        if let Some([start, end]) = self.frame_range
+            && (start < 0 || start > end || end >= self.frames_total as i64)
+        {
+            bail!(
+                "frame range {}..{} is outside 0..{}",
+                start,
+                end,
+                self.frames_total - 1
+            );
+        }
+        Ok(())
+    }
+}
+
+pub fn animation_settings(pxc: &PxcFile) -> AnimationSettings {
+    let animator = pxc.json.get("animator");
+    let field = |key: &str| animator.and_then(|a| a.get(key));
+    let frame_range = field("frame_range")
+        .and_then(|v| v.as_array())
+        .filter(|r| r.len() == 2)
+        .and_then(|r| Some([r[0].as_f64()? as i64, r[1].as_f64()? as i64]));
+    AnimationSettings {
+        frames_total: field("frames_total")
+            .and_then(|v| v.as_f64())
+            .map_or(DEFAULT_FRAMES_TOTAL, |f| f as u32),
+        framerate: field("framerate")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(DEFAULT_FRAMERATE),
+        frame_range,
+    }
+}
+
+/// Writes `settings` into `animator`, keeping any other fields there.
+pub fn set_animation_settings(pxc: &mut PxcFile, settings: &AnimationSettings) -> Result<()> {
+    settings.check()?;
+    let root = pxc
+        .json
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("project JSON is not an object"))?;
+    let animator = root
+        .entry("animator")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if !animator.is_object() {
+        *animator = Value::Object(Map::new());
+    }
+    let animator = animator.as_object_mut().expect("animator is an object");
+    animator.insert("frames_total".to_string(), json!(settings.frames_total));
+    let framerate = settings.framerate;
+    let framerate = if framerate.fract() == 0.0 {
+        json!(framerate as i64)
+    } else {
+        json!(framerate)
+    };
+    animator.insert("framerate".to_string(), framerate);
+    match settings.frame_range {
+        Some(range) => animator.insert("frame_range".to_string(), json!(range)),
+        None => animator.remove("frame_range"),
+    };
+    Ok(())
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RetimeReport {
+    pub settings: AnimationSettings,
+    pub keys_moved: usize,
+    /// Keys dropped because an earlier key landed on the same frame.
+    pub keys_merged: usize,
+}
+
+/// Changes the frame count and/or framerate and scales every key time to match.
+/// A new frame count maps the first and last frames onto the new ones; a
+/// framerate alone keeps the duration in seconds, so the frame count and times
+/// scale by `new / old` fps. The playback range is scaled the same way.
+pub fn retime_pxc(
+    pxc: &mut PxcFile,
+    frames_total: Option<u32>,
+    framerate: Option<f64>,
+) -> Result<RetimeReport> {
+    let old = animation_settings(pxc);
+    let scale = match (frames_total, framerate) {
+        (Some(_), _) if old.frames_total <= 1 => 1.0,
+        (Some(frames), _) => (frames.max(1) - 1) as f64 / (old.frames_total - 1) as f64,
+        (None, Some(fps)) => fps / old.framerate,
+        (None, None) => bail!("retime needs a new frame count or framerate"),
+    };
+    let frames_total =
+        frames_total.unwrap_or_else(|| ((old.frames_total as f64 * scale).round() as u32).max(1));
+    let last = frames_total as i64 - 1;
+    let settings = AnimationSettings {
+        frames_total,
+        framerate: framerate.unwrap_or(old.framerate),
+        frame_range: old
+            .frame_range
+            .map(|range| range.map(|f| ((f as f64 * scale).round() as i64).clamp(0, last))),
+    };
+    settings.check()?;
+
+    let map = |frame: f64| frame * scale;
+    let (mut keys_moved, mut keys_merged) = (0, 0);
+    let nodes = pxc
+        .json
+        .get_mut("nodes")
+        .and_then(|v| v.as_array_mut())
+        .ok_or_else(|| anyhow!("no nodes array found"))?;
+    for node in nodes.iter_mut() {
+        for list in ["inputs", "inspectInputs"] {
+            let Some(inputs) = node.get_mut(list).and_then(|v| v.as_array_mut()) else {
+                continue;
+            };
+            for input in inputs.iter_mut() {
+                let (moved, merged) = retime_input_keys(input, &map);
+                keys_moved += moved;
+                keys_merged += merged;
+            }
+        }
+    }
+    set_animation_settings(pxc, &settings)?;
+    Ok(RetimeReport {
+        settings,
+        keys_moved,
+        keys_merged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+
+    #[test]
+    fn retime_scales_keys_and_merges_collisions() {
+        let mut pxc = empty_pxc();
+        pxc.json["animator"] = json!({"frames_total": 30, "framerate": 30, "frame_range": [6, 29]});
+        pxc.json["nodes"] = json!([{"id": "n", "type": "Node_T", "inputs": [
+            {"anim": true, "r": [[0, 1], [1, 2], [2, 3], [29, 4]]},
+            {"r": {"d": 5}},
+        ]}]);
+        let report = retime_pxc(&mut pxc, Some(10), None).unwrap();
+
+        // Frames scale by 9/29: 1 and 2 round to 0 and 1, and 0 is taken.
+        let keys = &pxc.json["nodes"][0]["inputs"][0]["r"];
+        assert_eq!(keys, &json!([[0, 1], [1, 3], [9, 4]]));
+        assert_eq!((report.keys_moved, report.keys_merged), (2, 1));
+        assert_eq!(
+            animation_settings(&pxc),
+            AnimationSettings {
+                frames_total: 10,
+                framerate: 30.0,
+                frame_range: Some([2, 9]),
+            }
+        );
+    }
+}
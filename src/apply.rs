@@ -0,0 +1,568 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{Result, anyhow, bail};
+use serde_json::{Map, Value, json};
+
+use crate::favorites::prune_input_refs_in_pxc;
+use crate::graph::collect_graph;
+use crate::group::{Link, input_link, set_input_link};
+use crate::ops::{build_node, resolve_input_slot, resolve_node_id, resolve_output_slot};
+use crate::pxc::PxcFile;
+use crate::registry::Registry;
+
+fn node_id(node: &Value) -> Option<&str> {
+    node.get("id").and_then(|v| v.as_str())
+}
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(|v| v.as_str()).unwrap_or("")
+}
+
+fn as_int(v: &Value) -> Option<i64> {
+    v.as_i64().or_else(|| v.as_f64().map(|f| f as i64))
+}
+
+/// Positions are stored as integers when they have no fractional part.
+fn coordinate(v: f64) -> Value {
+    if v.fract() == 0.0 {
+        Value::Number((v as i64).into())
+    } else {
+        json!(v)
+    }
+}
+
+fn stored_value(input: &Value) -> Option<&Value> {
+    let r = input.get("r")?;
+    match r.get("d") {
+        Some(d) => Some(d),
+        None if r.is_array() => Some(r),
+        None => None,
+    }
+}
+
+fn describe_link(link: &Link, keys: &HashMap<String, String>) -> String {
+    let from = keys.get(&link.0).unwrap_or(&link.0);
+    match link.2 {
+        Some(tag) => format!("{} out {} (tag {})", from, link.1, tag),
+        None => format!("{} out {}", from, link.1),
+    }
+}
+
+/// Resolves a node reference from the document: a key of `n` first, then a full
+/// or short id of an existing node.
+fn resolve_ref(
+    reference: &str,
+    keys: &BTreeMap<String, String>,
+    nodes: &[Value],
+) -> Result<String> {
+    if let Some(id) = keys.get(reference) {
+        return Ok(id.clone());
+    }
+    resolve_node_id(reference, nodes).ok_or_else(|| anyhow!("node not found: {}", reference))
+}
+
+fn find_node<'a>(nodes: &'a [Value], id: &str) -> Result<&'a Value> {
+    nodes
+        .iter()
+        .find(|n| node_id(n) == Some(id))
+        .ok_or_else(|| anyhow!("node not found: {}", id))
+}
+
+fn output_slot(conn: &Value, from: &Value, registry: &Registry, context: &str) -> Result<i64> {
+    match conn.get("fo") {
+        Some(Value::String(name)) => {
+            Ok(resolve_output_slot(from, None, Some(name), Some(registry))? as i64)
+        }
+        Some(v) => as_int(v).ok_or_else(|| anyhow!("{}: invalid \"fo\"", context)),
+        None => Ok(0),
+    }
+}
+
+/// Updates the project to match a compact graph document, the `{n, e, m}` shape
+/// produced by `graph_json_from_pxc`. Keys of `n` map to existing nodes through
+/// an `id` field or `m`; a `null` `m` means keys are full ids, and only a
+/// document without `m` falls back to the full/short id lookup. Unknown keys
+/// become new nodes of type `t`. Nodes in scope that the document leaves out are deleted.
+/// Connections (`c` on inputs and `e`) replace the existing ones whenever the
+/// document carries any. `group` limits the scope like `graph --group`. Returns
+/// one line per change; nothing is modified if any part of the document fails.
+pub fn apply_graph_json_to_pxc(
+    pxc: &mut PxcFile,
+    doc: &Value,
+    registry: &Registry,
+    group: Option<&str>,
+) -> Result<Vec<String>> {
+    let doc_nodes = doc
+        .get("n")
+        .and_then(|v| v.as_object())
+        .ok_or_else(|| anyhow!("graph JSON has no \"n\" object"))?;
+    let id_map = doc.get("m").and_then(|v| v.as_object());
+    // `"m": null` marks a full-id export, whose keys are never short ids.
+    let full_ids = doc.get("m").is_some_and(Value::is_null);
+    let original = pxc
+        .json
+        .get("nodes")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("no nodes array found"))?;
+    let graph = collect_graph(original, true, false, group)?;
+    let scope = match group {
+        Some(g) => resolve_node_id(g, original),
+        None => None,
+    };
+    let existing: HashSet<&str> = original.iter().filter_map(node_id).collect();
+
+    // Map document keys to node ids, creating ids for new nodes.
+    let mut keys: BTreeMap<String, String> = BTreeMap::new();
+    let mut claimed: HashSet<String> = HashSet::new();
+    let mut new_nodes: Vec<(String, Value)> = Vec::new();
+    for (key, dn) in doc_nodes {
+        let resolved = if let Some(id) = dn.get("id").and_then(|v| v.as_str()) {
+            Some(id.to_string())
+        } else if let Some(m) = id_map {
+            m.get(key).and_then(|v| v.as_str()).map(|s| s.to_string())
+        } else if full_ids {
+            existing.contains(key.as_str()).then(|| key.clone())
+        } else {
+            resolve_node_id(key, original)
+        };
+        let typ = dn.get("t").and_then(|v| v.as_str());
+        match resolved {
+            Some(id) => {
+                if !existing.contains(id.as_str()) {
+                    bail!("{}: node id not found: {}", key, id);
+                }
+                if !graph.node_map.contains_key(&id) {
+                    bail!("{}: node {} is outside the applied group", key, id);
+                }
+                if !claimed.insert(id.clone()) {
+                    bail!("{}: node {} is listed more than once", key, id);
+                }
+                let current = node_type(find_node(original, &id)?);
+                if let Some(t) = typ
+                    && t != current
+                {
+                    bail!(
+                        "{}: type change {} -> {} is not supported; use a new key instead",
+                        key,
+                        current,
+                        t
+                    );
+                }
+                keys.insert(key.clone(), id);
+            }
+            None => {
+                let t = typ.ok_or_else(|| anyhow!("{}: new node needs a type (\"t\")", key))?;
+                let reg_node = registry
+                    .nodes
+                    .get(t)
+                    .ok_or_else(|| anyhow!("{}: unknown node type: {}", key, t))?;
+                let (x, y) = match dn.get("p").and_then(|v| v.as_array()) {
+                    Some(p) => (
+                        p.first().and_then(|v| v.as_f64()).unwrap_or(0.0) as i32,
+                        p.get(1).and_then(|v| v.as_f64()).unwrap_or(0.0) as i32,
+                    ),
+                    None => (0, 0),
+                };
+                let name = dn.get("n").and_then(|v| v.as_str());
+                let (id, mut node) = build_node(t, x, y, name, Some(reg_node));
+                if let Some(g) = &scope {
+                    node["group"] = Value::String(g.clone());
+                }
+                keys.insert(key.clone(), id.clone());
+                new_nodes.push((key.clone(), node));
+            }
+        }
+    }
+    let labels: HashMap<String, String> =
+        keys.iter().map(|(k, id)| (id.clone(), k.clone())).collect();
+
+    let mut changes = Vec::new();
+    let removed: HashSet<String> = graph
+        .node_map
+        .keys()
+        .filter(|id| !claimed.contains(*id))
+        .cloned()
+        .collect();
+    let mut nodes: Vec<Value> = Vec::with_capacity(original.len() + new_nodes.len());
+    for node in original {
+        let id = node_id(node).unwrap_or("");
+        if removed.contains(id) {
+            changes.push(format!(
+                "removed node {} ({} \"{}\")",
+                id,
+                node_type(node),
+                node.get("name").and_then(|v| v.as_str()).unwrap_or("")
+            ));
+        } else {
+            nodes.push(node.clone());
+        }
+    }
+    for (key, node) in new_nodes {
+        changes.push(format!("added node {} ({})", key, node_type(&node)));
+        nodes.push(node);
+    }
+    for node in nodes.iter_mut() {
+        let label = node_id(node)
+            .map(|id| labels.get(id).cloned().unwrap_or_else(|| id.to_string()))
+            .unwrap_or_default();
+        let Some(inputs) = node.get_mut("inputs").and_then(|v| v.as_array_mut()) else {
+            continue;
+        };
+        for (slot, input) in inputs.iter_mut().enumerate() {
+            if let Some(link) = input_link(input)
+                && removed.contains(&link.0)
+            {
+                set_input_link(input, None);
+                changes.push(format!(
+                    "{} in {}: disconnected (source {} removed)",
+                    label, slot, link.0
+                ));
+            }
+        }
+    }
+
+    // Node fields and input values.
+    let mut connections: Vec<(String, usize, String, &Value)> = Vec::new();
+    for (key, dn) in doc_nodes {
+        let id = &keys[key];
+        let parent = match dn.get("g").and_then(|v| v.as_str()) {
+            Some(g) => Some(resolve_ref(g, &keys, &nodes)?),
+            None => scope.clone(),
+        };
+        let index = nodes
+            .iter()
+            .position(|n| node_id(n) == Some(id.as_str()))
+            .ok_or_else(|| anyhow!("node not found: {}", id))?;
+        let node = &mut nodes[index];
+
+        if let Some(name) = dn.get("n").and_then(|v| v.as_str()) {
+            let current = node.get("name").and_then(|v| v.as_str()).unwrap_or("");
+            if current != name {
+                changes.push(format!("{}: renamed \"{}\" -> \"{}\"", key, current, name));
+                node["name"] = Value::String(name.to_string());
+                node["renamed"] = Value::Bool(true);
+            }
+        }
+        if let Some(p) = dn.get("p").and_then(|v| v.as_array()) {
+            let x = p.first().and_then(|v| v.as_f64()).unwrap_or(0.0);
+            let y = p.get(1).and_then(|v| v.as_f64()).unwrap_or(0.0);
+            let cx = node.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0);
+            let cy = node.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0);
+            if (x, y) != (cx, cy) {
+                changes.push(format!("{}: moved to ({}, {})", key, x, y));
+                node["x"] = coordinate(x);
+                node["y"] = coordinate(y);
+            }
+        }
+        let current_parent = node.get("group").and_then(|v| v.as_str());
+        if current_parent != parent.as_deref() {
+            match &parent {
+                Some(g) => {
+                    let label = labels.get(g).unwrap_or(g);
+                    changes.push(format!("{}: moved into group {}", key, label));
+                    node["group"] = Value::String(g.clone());
+                }
+                None => {
+                    changes.push(format!("{}: moved out of its group", key));
+                    if let Some(obj) = node.as_object_mut() {
+                        obj.remove("group");
+                    }
+                }
+            }
+        }
+        if let Some(attri) = dn.get("a")
+            && node.get("attri") != Some(attri)
+        {
+            changes.push(format!("{}: attri updated", key));
+            node["attri"] = attri.clone();
+        }
+
+        let Some(entries) = dn.get("i").and_then(|v| v.as_array()) else {
+            continue;
+        };
+        for entry in entries {
+            let slot = match entry.get("s").and_then(|v| v.as_u64()) {
+                Some(s) => s as usize,
+                None => {
+                    let name = entry.get("n").and_then(|v| v.as_str());
+                    if name.is_none() {
+                        bail!("{}: input entry needs \"s\" or \"n\"", key);
+                    }
+                    resolve_input_slot(node, None, name, Some(registry))
+                        .map_err(|e| anyhow!("{}: {}", key, e))?
+                }
+            };
+            if let Some(v) = entry.get("v")
+                && let Some(port) = registry.input_port(node_type(node), slot)
+            {
+                port.check_value(v)
+                    .map_err(|e| anyhow!("{} in {}: {}", key, slot, e))?;
+            }
+            if !node.get("inputs").is_some_and(|v| v.is_array()) {
+                node["inputs"] = Value::Array(Vec::new());
+            }
+            let Some(inputs) = node.get_mut("inputs").and_then(|v| v.as_array_mut()) else {
+                continue;
+            };
+            while inputs.len() <= slot {
+                changes.push(format!("{} in {}: added input", key, inputs.len()));
+                inputs.push(Value::Object(Map::new()));
+            }
+            let input = &mut inputs[slot];
+            if !input.is_object() {
+                *input = Value::Object(Map::new());
+            }
+            if let Some(v) = entry.get("v")
+                && stored_value(input) != Some(v)
+            {
+                let before = stored_value(input)
+                    .map(|c| c.to_string())
+                    .unwrap_or_else(|| "none".to_string());
+                changes.push(format!("{} in {}: value {} -> {}", key, slot, before, v));
+                input["r"] = json!({ "d": v });
+                if let Some(obj) = input.as_object_mut() {
+                    obj.remove("anim");
+                }
+            }
+            if let Some(attri) = entry.get("a")
+                && input.get("attri") != Some(attri)
+            {
+                changes.push(format!("{} in {}: attri updated", key, slot));
+                input["attri"] = attri.clone();
+            }
+            if let Some(conn) = entry.get("c") {
+                connections.push((id.clone(), slot, format!("{} in {}", key, slot), conn));
+            }
+        }
+    }
+    let mut desired: HashMap<(String, usize), Link> = HashMap::new();
+    for (id, slot, context, conn) in connections {
+        let link = parse_link(conn, &keys, &nodes, registry, &context)?;
+        if desired.insert((id, slot), link).is_some() {
+            bail!("{}: connected more than once", context);
+        }
+    }
+
+    // Connections, when the document describes them.
+    let describes_links =
+        doc.get("e").is_some() || doc_nodes.values().any(|n| n.get("i").is_some());
+    if !describes_links {
+        store_nodes(pxc, nodes, &mut changes);
+        return Ok(changes);
+    }
+    if let Some(edges) = doc.get("e").and_then(|v| v.as_array()) {
+        for edge in edges {
+            let to = edge
+                .get("t")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("edge without \"t\": {}", edge))?;
+            let to_id = keys
+                .get(to)
+                .ok_or_else(|| anyhow!("edge target {} is not a node of the document", to))?;
+            let slot = edge
+                .get("ti")
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| anyhow!("edge without \"ti\": {}", edge))?
+                as usize;
+            let context = format!("{} in {}", to, slot);
+            let link = parse_link(edge, &keys, &nodes, registry, &context)?;
+            match desired.get(&(to_id.clone(), slot)) {
+                Some(existing) if *existing != link => {
+                    bail!("{}: \"c\" and \"e\" disagree", context)
+                }
+                Some(_) => {}
+                None => {
+                    desired.insert((to_id.clone(), slot), link);
+                }
+            }
+        }
+    }
+    for (key, id) in keys.iter() {
+        let Some(node) = nodes.iter_mut().find(|n| node_id(n) == Some(id.as_str())) else {
+            continue;
+        };
+        if !node.get("inputs").is_some_and(|v| v.is_array()) {
+            node["inputs"] = Value::Array(Vec::new());
+        }
+        let Some(inputs) = node.get_mut("inputs").and_then(|v| v.as_array_mut()) else {
+            continue;
+        };
+        let wanted_len = desired
+            .keys()
+            .filter(|(to, _)| to == id)
+            .map(|(_, slot)| slot + 1)
+            .max()
+            .unwrap_or(0);
+        while inputs.len() < wanted_len {
+            changes.push(format!("{} in {}: added input", key, inputs.len()));
+            inputs.push(Value::Object(Map::new()));
+        }
+        for (slot, input) in inputs.iter_mut().enumerate() {
+            let want = desired.get(&(id.clone(), slot));
+            let have = input_link(input);
+            if want == have.as_ref() {
+                continue;
+            }
+            match (want, &have) {
+                (Some(link), _) => changes.push(format!(
+                    "{} in {}: connected from {}",
+                    key,
+                    slot,
+                    describe_link(link, &labels)
+                )),
+                (None, Some(link)) => changes.push(format!(
+                    "{} in {}: disconnected (was {})",
+                    key,
+                    slot,
+                    describe_link(link, &labels)
+                )),
+                (None, None) => {}
+            }
+            set_input_link(input, want);
+        }
+    }
+
+    store_nodes(pxc, nodes, &mut changes);
+    Ok(changes)
+}
+
+/// Writes the new node list and drops favourites and panel entries of removed
+/// nodes.
+fn store_nodes(pxc: &mut PxcFile, nodes: Vec<Value>, changes: &mut Vec<String>) {
+    pxc.json["nodes"] = Value::Array(nodes);
+    let dropped = prune_input_refs_in_pxc(pxc);
+    if dropped > 0 {
+        changes.push(format!(
+            "dropped {} favourite or panel entries of removed inputs",
+            dropped
+        ));
+    }
+}
+
+/// Reads a connection source from a `c` entry or an edge (`f`, `fo`, `tg`).
+fn parse_link(
+    conn: &Value,
+    keys: &BTreeMap<String, String>,
+    nodes: &[Value],
+    registry: &Registry,
+    context: &str,
+) -> Result<Link> {
+    let from = conn
+        .get("f")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("{}: connection without \"f\"", context))?;
+    let from_id = resolve_ref(from, keys, nodes).map_err(|e| anyhow!("{}: {}", context, e))?;
+    let from_node = find_node(nodes, &from_id)?;
+    let from_index = output_slot(conn, from_node, registry, context)?;
+    let tag = conn.get("tg").and_then(as_int);
+    Ok((from_id, from_index, tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+    use crate::registry::{RegistryNode, RegistryPort};
+
+    fn registry() -> Registry {
+        let node = RegistryNode {
+            inputs: vec![RegistryPort::default(), RegistryPort::default()],
+            outputs: vec![RegistryPort::default()],
+            ..Default::default()
+        };
+        Registry {
+            nodes: HashMap::from([("Node_T".to_string(), node)]),
+        }
+    }
+
+    /// `a -> b -> c`, short ids `A`, `B`, `C`.
+    fn project() -> PxcFile {
+        let mut pxc = empty_pxc();
+        pxc.json["nodes"] = json!([
+            {"id": "a", "type": "Node_T", "inputs": [{}, {}], "outputs": [{}]},
+            {"id": "b", "type": "Node_T", "inputs": [{"from_node": "a", "from_index": 0}, {}],
+             "outputs": [{}]},
+            {"id": "c", "type": "Node_T", "inputs": [{"from_node": "b", "from_index": 0}, {}],
+             "outputs": [{}]},
+        ]);
+        pxc
+    }
+
+    fn ids(pxc: &PxcFile) -> Vec<&str> {
+        pxc.json["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(node_id)
+            .collect()
+    }
+
+    #[test]
+    fn omitted_nodes_are_deleted_and_their_links_dropped() {
+        let mut pxc = project();
+        let doc = json!({"n": {"A": {}, "C": {}}, "m": {"A": "a", "C": "c"}});
+        let changes = apply_graph_json_to_pxc(&mut pxc, &doc, &registry(), None).unwrap();
+        assert_eq!(ids(&pxc), ["a", "c"]);
+        assert!(changes.iter().any(|c| c.starts_with("removed node b")));
+        assert_eq!(input_link(&pxc.json["nodes"][1]["inputs"][0]), None);
+    }
+
+    #[test]
+    fn new_nodes_need_a_type() {
+        let mut pxc = project();
+        let doc = json!({"n": {"A": {}, "B": {}, "C": {}, "X": {}},
+                         "m": {"A": "a", "B": "b", "C": "c"}});
+        let err = apply_graph_json_to_pxc(&mut pxc, &doc, &registry(), None).unwrap_err();
+        assert_eq!(err.to_string(), "X: new node needs a type (\"t\")");
+        assert_eq!(pxc.json["nodes"], project().json["nodes"]);
+
+        let doc = json!({"n": {"A": {}, "B": {}, "C": {}, "X": {"t": "Node_T", "p": [64, 0]}},
+                         "m": {"A": "a", "B": "b", "C": "c"}});
+        let changes = apply_graph_json_to_pxc(&mut pxc, &doc, &registry(), None).unwrap();
+        assert_eq!(changes, ["added node X (Node_T)"]);
+        assert_eq!(pxc.json["nodes"][3]["x"], json!(64));
+    }
+
+    #[test]
+    fn connections_are_replaced_wholesale() {
+        let mut pxc = project();
+        // Only `a -> c` is described, so `a -> b` and `b -> c` go away.
+        let doc = json!({"n": {"A": {}, "B": {}, "C": {}},
+                         "m": {"A": "a", "B": "b", "C": "c"},
+                         "e": [{"f": "A", "t": "C", "ti": 1}]});
+        apply_graph_json_to_pxc(&mut pxc, &doc, &registry(), None).unwrap();
+        let links = |i: usize| {
+            let inputs = pxc.json["nodes"][i]["inputs"].as_array().unwrap();
+            inputs.iter().map(input_link).collect::<Vec<_>>()
+        };
+        assert_eq!(links(1), [None, None]);
+        assert_eq!(links(2), [None, Some(("a".to_string(), 0, None))]);
+    }
+
+    #[test]
+    fn keys_are_short_ids_only_without_m() {
+        let new_c = json!({"A": {}, "B": {}, "C": {"t": "Node_T"}});
+        // With `m`, an unmapped `C` is a new node and `c` is deleted.
+        let mut pxc = project();
+        let doc = json!({"n": new_c, "m": {"A": "a", "B": "b"}});
+        apply_graph_json_to_pxc(&mut pxc, &doc, &registry(), None).unwrap();
+        assert_eq!(ids(&pxc)[..2], ["a", "b"]);
+        assert_ne!(ids(&pxc)[2], "c");
+
+        // A full-id document (`"m": null`) never matches short ids.
+        let mut pxc = project();
+        let doc = json!({"n": {"a": {}, "b": {}, "c": {}, "A": {"t": "Node_T"}}, "m": null});
+        let changes = apply_graph_json_to_pxc(&mut pxc, &doc, &registry(), None).unwrap();
+        assert_eq!(changes, ["added node A (Node_T)"]);
+
+        // Without `m`, `C` is the third node.
+        let mut pxc = project();
+        let doc = json!({"n": new_c});
+        assert!(
+            apply_graph_json_to_pxc(&mut pxc, &doc, &registry(), None)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(ids(&pxc), ["a", "b", "c"]);
+    }
+}
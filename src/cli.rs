@@ -1,15 +1,50 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result, anyhow, bail};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use regex::Regex;
 use serde_json::{Map, Value};
 
+use crate::anim::{AnimationSettings, animation_settings, retime_pxc, set_animation_settings};
+use crate::apply::apply_graph_json_to_pxc;
+use crate::diff::{diff_registries, render_diff};
+use crate::dsl::build_graph_into_pxc;
+use crate::dynamic::{add_input_group_in_pxc, remove_input_group_in_pxc};
+use crate::favorites::{
+    InputRef, add_favorite_in_pxc, add_panel_to_pxc, favorites_in_pxc, panels_in_pxc,
+    remove_favorite_in_pxc, remove_panel_from_pxc, resolve_input,
+};
+use crate::globals::{
+    GlobalEdit, GlobalType, GlobalVar, add_global_to_pxc, get_global_in_pxc, globals_in_pxc,
+    remove_global_from_pxc, set_global_in_pxc,
+};
 use crate::graph::{GraphFormat, GraphMode, cmd_graph};
-use crate::ops::{remove_json_pointer, set_input_value_in_pxc, set_json_pointer};
+use crate::group::{group_nodes_in_pxc, ungroup_in_pxc};
+use crate::keyframe::{
+    Interpolation, KeyframeEase, delete_keyframe_in_pxc, get_input_value_at_in_pxc,
+    keyframes_in_pxc, move_keyframe_in_pxc, set_animated_in_pxc, set_keyframe_in_pxc,
+};
+use crate::layout::{DEFAULT_SPACING, auto_layout_pxc};
+use crate::lint::lint_pxc;
+use crate::metadata::{
+    MetaEdit, Metadata, metadata_in_pxc, project_files, set_metadata_in_dir, set_metadata_in_pxc,
+};
+use crate::migrate::migrate_pxc;
+use crate::notes::{
+    NoteEdit, add_note_to_pxc, edit_note_in_pxc, notes_in_pxc, parse_note_color,
+    remove_note_from_pxc,
+};
+use crate::ops::{
+    get_input_expression_in_pxc, get_input_value_in_pxc, remove_json_pointer,
+    set_input_expression_in_pxc, set_input_value_in_pxc, set_json_pointer,
+};
+use crate::palette::{
+    PaletteFormat, PaletteMap, palette_in_pxc, remap_palette_in_pxc, render_palette,
+};
 use crate::pxc::{
-    PxcFile, decode_preview, read_pxc, rgba_bytes_to_image, write_pxc, zlib_decompress,
+    PxcFile, decode_preview, empty_pxc, read_pxc, rgba_bytes_to_image, write_pxc, zlib_decompress,
 };
-use crate::registry::cmd_registry_build;
+use crate::registry::{Registry, RegistryPort, cmd_registry_build, load_registry, registry_choice};
 
 #[derive(Parser)]
 #[command(name = "pxc", version, about = "Pixel Composer .pxc project file tool")]
@@ -76,7 +111,36 @@ enum Command {
         edges: bool,
         #[arg(long)]
         registry: Option<PathBuf>,
+        #[arg(long)]
+        group: Option<String>,
+        #[arg(long)]
+        notes: bool,
+    },
+    GraphApply {
+        file: PathBuf,
+        graph: PathBuf,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        group: Option<String>,
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        in_place: bool,
+    },
+    Build {
+        source: PathBuf,
+        #[arg(short, long)]
+        out: PathBuf,
+        #[arg(long)]
+        base: Option<PathBuf>,
+        #[arg(long)]
+        registry: Option<PathBuf>,
     },
+    #[command(subcommand)]
+    Registry(RegistryCommand),
     RegistryBuild {
         #[arg(long)]
         scripts: PathBuf,
@@ -85,6 +149,12 @@ enum Command {
         #[arg(long)]
         out: PathBuf,
     },
+    GetInput {
+        #[command(flatten)]
+        target: InputTarget,
+        #[arg(long)]
+        frame: Option<f64>,
+    },
     SetInput {
         file: PathBuf,
         #[arg(long)]
@@ -97,6 +167,10 @@ enum Command {
         value: Option<String>,
         #[arg(long)]
         value_file: Option<PathBuf>,
+        #[arg(long, conflicts_with = "clear_expression")]
+        expression: Option<String>,
+        #[arg(long)]
+        clear_expression: bool,
         #[arg(long)]
         registry: Option<PathBuf>,
         #[arg(long)]
@@ -104,6 +178,13 @@ enum Command {
         #[arg(long)]
         in_place: bool,
     },
+    Lint {
+        file: PathBuf,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
     Connect {
         file: PathBuf,
         #[arg(long)]
@@ -121,6 +202,91 @@ enum Command {
         #[arg(long)]
         in_place: bool,
     },
+    Group {
+        file: PathBuf,
+        #[arg(long, value_delimiter = ',', required = true)]
+        nodes: Vec<String>,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        in_place: bool,
+    },
+    Ungroup {
+        file: PathBuf,
+        group: String,
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        in_place: bool,
+    },
+    #[command(subcommand)]
+    Keyframe(KeyframeCommand),
+    #[command(subcommand)]
+    Anim(AnimCommand),
+    #[command(subcommand)]
+    Globals(GlobalsCommand),
+    #[command(subcommand)]
+    Notes(NotesCommand),
+    #[command(subcommand)]
+    Meta(MetaCommand),
+    #[command(subcommand)]
+    Palette(PaletteCommand),
+    #[command(subcommand)]
+    Favorites(FavoritesCommand),
+    #[command(subcommand)]
+    Panels(PanelsCommand),
+    Migrate {
+        file: PathBuf,
+        #[arg(long)]
+        from_registry: PathBuf,
+        #[arg(long)]
+        to_registry: PathBuf,
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        in_place: bool,
+    },
+    AddInputGroup {
+        file: PathBuf,
+        #[arg(long)]
+        node: String,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        in_place: bool,
+    },
+    RemoveInputGroup {
+        file: PathBuf,
+        #[arg(long)]
+        node: String,
+        #[arg(long)]
+        index: usize,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        in_place: bool,
+    },
+    Layout {
+        file: PathBuf,
+        #[arg(long, value_delimiter = ',')]
+        nodes: Vec<String>,
+        #[arg(long)]
+        spacing: Option<String>,
+        #[arg(long)]
+        origin_only: bool,
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        in_place: bool,
+    },
     ExtractPreview {
         file: PathBuf,
         out: PathBuf,
@@ -131,6 +297,382 @@ enum Command {
     },
 }
 
+#[derive(Subcommand)]
+enum RegistryCommand {
+    Which {
+        file: PathBuf,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+    },
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        #[arg(long)]
+        json: bool,
+    },
+    List {
+        #[arg(long)]
+        filter: Option<String>,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+    Show {
+        node_type: String,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+    Search {
+        #[arg(long)]
+        input_type: String,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Args)]
+struct InputTarget {
+    file: PathBuf,
+    #[arg(long)]
+    node: String,
+    #[arg(long)]
+    input: Option<usize>,
+    #[arg(long)]
+    input_name: Option<String>,
+    #[arg(long)]
+    registry: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct WriteTarget {
+    #[arg(long)]
+    out: Option<PathBuf>,
+    #[arg(long)]
+    in_place: bool,
+}
+
+#[derive(Subcommand)]
+enum KeyframeCommand {
+    List {
+        #[command(flatten)]
+        target: InputTarget,
+        #[arg(long)]
+        json: bool,
+    },
+    Set {
+        #[command(flatten)]
+        target: InputTarget,
+        #[arg(long)]
+        frame: i64,
+        #[arg(long)]
+        value: Option<String>,
+        #[arg(long, value_enum)]
+        interp: Option<Interpolation>,
+        #[arg(long, value_enum)]
+        interp_in: Option<Interpolation>,
+        #[arg(long, value_enum)]
+        interp_out: Option<Interpolation>,
+        #[arg(long, value_delimiter = ',')]
+        ease_in: Option<Vec<f64>>,
+        #[arg(long, value_delimiter = ',')]
+        ease_out: Option<Vec<f64>>,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+    Move {
+        #[command(flatten)]
+        target: InputTarget,
+        #[arg(long)]
+        frame: i64,
+        #[arg(long)]
+        to: i64,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+    Delete {
+        #[command(flatten)]
+        target: InputTarget,
+        #[arg(long)]
+        frame: i64,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+    Animate {
+        #[command(flatten)]
+        target: InputTarget,
+        #[arg(long)]
+        off: bool,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+}
+
+#[derive(Subcommand)]
+enum AnimCommand {
+    Get {
+        file: PathBuf,
+        #[arg(long)]
+        json: bool,
+    },
+    Set {
+        file: PathBuf,
+        #[arg(long)]
+        frames: Option<u32>,
+        #[arg(long)]
+        fps: Option<f64>,
+        #[arg(long, value_delimiter = ',', conflicts_with = "clear_range")]
+        range: Option<Vec<i64>>,
+        #[arg(long)]
+        clear_range: bool,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+    Retime {
+        file: PathBuf,
+        #[arg(long)]
+        frames: Option<u32>,
+        #[arg(long)]
+        fps: Option<f64>,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+}
+
+#[derive(Subcommand)]
+enum GlobalsCommand {
+    List {
+        file: PathBuf,
+        #[arg(long)]
+        json: bool,
+    },
+    Get {
+        file: PathBuf,
+        name: String,
+        #[arg(long)]
+        json: bool,
+    },
+    Set {
+        file: PathBuf,
+        name: String,
+        #[arg(long)]
+        value: Option<String>,
+        #[arg(long = "type", value_enum)]
+        ty: Option<GlobalType>,
+        #[arg(long)]
+        rename: Option<String>,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+    Add {
+        file: PathBuf,
+        name: String,
+        #[arg(long = "type", value_enum, default_value = "float")]
+        ty: GlobalType,
+        #[arg(long)]
+        value: Option<String>,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+    Remove {
+        file: PathBuf,
+        name: String,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+}
+
+#[derive(Args)]
+struct NoteArgs {
+    #[arg(long, allow_negative_numbers = true)]
+    x: Option<f64>,
+    #[arg(long, allow_negative_numbers = true)]
+    y: Option<f64>,
+    #[arg(long)]
+    width: Option<f64>,
+    #[arg(long)]
+    height: Option<f64>,
+    #[arg(long)]
+    color: Option<String>,
+}
+
+impl NoteArgs {
+    fn edit(&self, text: Option<String>) -> Result<NoteEdit> {
+        Ok(NoteEdit {
+            x: self.x,
+            y: self.y,
+            w: self.width,
+            h: self.height,
+            text,
+            color: self.color.as_deref().map(parse_note_color).transpose()?,
+        })
+    }
+}
+
+#[derive(Subcommand)]
+enum NotesCommand {
+    List {
+        file: PathBuf,
+        #[arg(long)]
+        json: bool,
+    },
+    Add {
+        file: PathBuf,
+        #[arg(long)]
+        text: String,
+        #[command(flatten)]
+        note: NoteArgs,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+    Edit {
+        file: PathBuf,
+        index: usize,
+        #[arg(long)]
+        text: Option<String>,
+        #[command(flatten)]
+        note: NoteArgs,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+    Remove {
+        file: PathBuf,
+        index: usize,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+}
+
+#[derive(Args)]
+struct MetaArgs {
+    #[arg(long)]
+    author: Option<String>,
+    #[arg(long)]
+    description: Option<String>,
+    #[arg(long)]
+    contact: Option<String>,
+    #[arg(long)]
+    alias: Option<String>,
+    #[arg(long, value_delimiter = ',')]
+    tags: Option<Vec<String>>,
+    #[arg(long = "add-tag")]
+    add_tags: Vec<String>,
+    #[arg(long = "remove-tag")]
+    remove_tags: Vec<String>,
+}
+
+impl MetaArgs {
+    fn edit(self) -> MetaEdit {
+        MetaEdit {
+            author: self.author,
+            description: self.description,
+            contact: self.contact,
+            alias: self.alias,
+            tags: self.tags,
+            add_tags: self.add_tags,
+            remove_tags: self.remove_tags,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum MetaCommand {
+    Show {
+        path: PathBuf,
+        #[arg(long)]
+        json: bool,
+    },
+    Set {
+        path: PathBuf,
+        #[command(flatten)]
+        meta: MetaArgs,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+}
+
+#[derive(Subcommand)]
+enum PaletteCommand {
+    Extract {
+        file: PathBuf,
+        #[arg(long, value_enum)]
+        format: Option<PaletteFormat>,
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long, default_value_t = 16)]
+        swatch: u32,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+    },
+    Remap {
+        file: PathBuf,
+        map: PathBuf,
+        #[arg(long)]
+        nearest: bool,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+}
+
+#[derive(Subcommand)]
+enum FavoritesCommand {
+    List {
+        file: PathBuf,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+    Add {
+        #[command(flatten)]
+        target: InputTarget,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+    Remove {
+        #[command(flatten)]
+        target: InputTarget,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+}
+
+#[derive(Subcommand)]
+enum PanelsCommand {
+    List {
+        file: PathBuf,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[arg(long)]
+        json: bool,
+    },
+    Add {
+        file: PathBuf,
+        #[arg(long)]
+        name: String,
+        #[arg(long = "input", required = true)]
+        inputs: Vec<String>,
+        #[arg(long)]
+        registry: Option<PathBuf>,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+    Remove {
+        file: PathBuf,
+        index: usize,
+        #[command(flatten)]
+        write: WriteTarget,
+    },
+}
+
 pub fn run() -> Result<()> {
     let cli = Cli::parse();
     match cli.command {
@@ -163,6 +705,8 @@ pub fn run() -> Result<()> {
             full_ids,
             edges,
             registry,
+            group,
+            notes,
         } => cmd_graph(
             &file,
             format,
@@ -175,43 +719,165 @@ pub fn run() -> Result<()> {
             full_ids,
             edges,
             registry.as_deref(),
+            group.as_deref(),
+            notes,
+        ),
+        Command::GraphApply {
+            file,
+            graph,
+            registry,
+            group,
+            dry_run,
+            out,
+            in_place,
+        } => cmd_graph_apply(
+            &file,
+            &graph,
+            registry.as_deref(),
+            group.as_deref(),
+            dry_run,
+            out,
+            in_place,
         ),
+        Command::Build {
+            source,
+            out,
+            base,
+            registry,
+        } => cmd_build(&source, &out, base.as_deref(), registry.as_deref()),
+        Command::Registry(RegistryCommand::Which { file, registry }) => {
+            cmd_registry_which(&file, registry.as_deref())
+        }
+        Command::Registry(RegistryCommand::Diff { old, new, json }) => {
+            cmd_registry_diff(&old, &new, json)
+        }
+        Command::Registry(RegistryCommand::List {
+            filter,
+            registry,
+            json,
+        }) => cmd_registry_list(filter.as_deref(), registry.as_deref(), json),
+        Command::Registry(RegistryCommand::Show {
+            node_type,
+            registry,
+            json,
+        }) => cmd_registry_show(&node_type, registry.as_deref(), json),
+        Command::Registry(RegistryCommand::Search {
+            input_type,
+            registry,
+            json,
+        }) => cmd_registry_search(&input_type, registry.as_deref(), json),
         Command::RegistryBuild {
             scripts,
             locale,
             out,
         } => cmd_registry_build(&scripts, locale.as_deref(), &out),
+        Command::GetInput { target, frame } => cmd_get_input(&target, frame),
         Command::SetInput {
             file,
             node,
-            input,
-            input_name,
-            value,
-            value_file,
+            input,
+            input_name,
+            value,
+            value_file,
+            expression,
+            clear_expression,
+            registry,
+            out,
+            in_place,
+        } => cmd_set_input(
+            &file,
+            &node,
+            input,
+            input_name.as_deref(),
+            value.as_deref(),
+            value_file.as_deref(),
+            expression.as_deref(),
+            clear_expression,
+            registry.as_deref(),
+            out,
+            in_place,
+        ),
+        Command::Lint {
+            file,
+            registry,
+            json,
+        } => cmd_lint(&file, registry.as_deref(), json),
+        Command::Connect {
+            file,
+            from,
+            from_index,
+            to,
+            to_input,
+            tag,
+            out,
+            in_place,
+        } => cmd_connect(&file, &from, from_index, &to, to_input, tag, out, in_place),
+        Command::Group {
+            file,
+            nodes,
+            name,
+            registry,
+            out,
+            in_place,
+        } => cmd_group(
+            &file,
+            &nodes,
+            name.as_deref(),
+            registry.as_deref(),
+            out,
+            in_place,
+        ),
+        Command::Ungroup {
+            file,
+            group,
+            out,
+            in_place,
+        } => cmd_ungroup(&file, &group, out, in_place),
+        Command::Keyframe(command) => cmd_keyframe(command),
+        Command::Anim(command) => cmd_anim(command),
+        Command::Globals(command) => cmd_globals(command),
+        Command::Notes(command) => cmd_notes(command),
+        Command::Meta(command) => cmd_meta(command),
+        Command::Palette(command) => cmd_palette(command),
+        Command::Favorites(command) => cmd_favorites(command),
+        Command::Panels(command) => cmd_panels(command),
+        Command::Migrate {
+            file,
+            from_registry,
+            to_registry,
+            out,
+            in_place,
+        } => cmd_migrate(&file, &from_registry, &to_registry, out, in_place),
+        Command::AddInputGroup {
+            file,
+            node,
             registry,
             out,
             in_place,
-        } => cmd_set_input(
-            &file,
-            &node,
-            input,
-            input_name.as_deref(),
-            value.as_deref(),
-            value_file.as_deref(),
-            registry.as_deref(),
+        } => cmd_add_input_group(&file, &node, registry.as_deref(), out, in_place),
+        Command::RemoveInputGroup {
+            file,
+            node,
+            index,
+            registry,
             out,
             in_place,
-        ),
-        Command::Connect {
+        } => cmd_remove_input_group(&file, &node, index, registry.as_deref(), out, in_place),
+        Command::Layout {
             file,
-            from,
-            from_index,
-            to,
-            to_input,
-            tag,
+            nodes,
+            spacing,
+            origin_only,
             out,
             in_place,
-        } => cmd_connect(&file, &from, from_index, &to, to_input, tag, out, in_place),
+        } => cmd_layout(
+            &file,
+            &nodes,
+            spacing.as_deref(),
+            origin_only,
+            out,
+            in_place,
+        ),
         Command::ExtractPreview { file, out } => cmd_extract_preview(&file, &out),
         Command::ExtractThumbnail { file, out } => cmd_extract_thumbnail(&file, &out),
     }
@@ -333,6 +999,189 @@ fn cmd_list_nodes(path: &Path) -> Result<()> {
     Ok(())
 }
 
+fn cmd_registry_which(path: &Path, registry_path: Option<&Path>) -> Result<()> {
+    let pxc = read_pxc(path)?;
+    let version = pxc.save_version();
+    let choice = registry_choice(registry_path, version)?;
+    let version_text = |v: Option<u32>| v.map_or("unknown".to_string(), |v| v.to_string());
+    println!("project.save_version: {}", version_text(version));
+    println!("registry: {}", choice.source);
+    println!("registry.save_version: {}", version_text(choice.version));
+    if !choice.available.is_empty() {
+        let available: Vec<String> = choice.available.iter().map(|v| v.to_string()).collect();
+        println!("available: {}", available.join(", "));
+    }
+    Ok(())
+}
+
+fn load_registry_file(path: &Path) -> Result<Registry> {
+    load_registry(Some(path), None)?.ok_or_else(|| anyhow!("no registry at {}", path.display()))
+}
+
+fn cmd_registry_diff(old: &Path, new: &Path, json: bool) -> Result<()> {
+    let diff = diff_registries(&load_registry_file(old)?, &load_registry_file(new)?);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print!("{}", render_diff(&diff));
+    }
+    Ok(())
+}
+
+fn query_registry(registry_path: Option<&Path>) -> Result<Registry> {
+    load_registry(registry_path, None)?.ok_or_else(|| anyhow!("registry required"))
+}
+
+fn cmd_registry_list(filter: Option<&str>, registry_path: Option<&Path>, json: bool) -> Result<()> {
+    let registry = query_registry(registry_path)?;
+    let filter = filter
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| anyhow!("invalid --filter: {}", e))?;
+    let mut names: Vec<&String> = registry
+        .nodes
+        .keys()
+        .filter(|name| filter.as_ref().is_none_or(|re| re.is_match(name)))
+        .collect();
+    names.sort();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&names)?);
+    } else {
+        for name in names {
+            println!("{}", name);
+        }
+    }
+    Ok(())
+}
+
+fn print_ports(title: &str, ports: &[RegistryPort]) {
+    if ports.is_empty() {
+        return;
+    }
+    println!("{}:", title);
+    for (slot, port) in ports.iter().enumerate() {
+        println!(
+            "  {}\t{}\t{}\t{}",
+            slot,
+            port.name.as_deref().unwrap_or(""),
+            port.ty.as_deref().unwrap_or(""),
+            port.tooltip.as_deref().unwrap_or("").replace('\n', " ")
+        );
+    }
+}
+
+fn cmd_registry_show(node_type: &str, registry_path: Option<&Path>, json: bool) -> Result<()> {
+    let registry = query_registry(registry_path)?;
+    let node = registry
+        .nodes
+        .get(node_type)
+        .ok_or_else(|| anyhow!("unknown node type: {}", node_type))?;
+    if json {
+        let mut out = serde_json::json!({
+            "type": node_type,
+            "inputs": node.inputs,
+            "outputs": node.outputs,
+        });
+        if !node.dynamic_inputs.is_empty() {
+            out["dynamic_inputs"] = serde_json::to_value(&node.dynamic_inputs)?;
+        }
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+    println!("{}", node_type);
+    print_ports("inputs", &node.inputs);
+    print_ports("outputs", &node.outputs);
+    let title = format!("dynamic inputs (repeated from slot {})", node.inputs.len());
+    print_ports(&title, &node.dynamic_inputs);
+    Ok(())
+}
+
+/// Slots and names of the ports whose value type is `wanted`.
+fn ports_of_type(ports: &[RegistryPort], wanted: &str) -> Vec<Value> {
+    ports
+        .iter()
+        .enumerate()
+        .filter(|(_, port)| {
+            port.value_type()
+                .is_some_and(|ty| ty.eq_ignore_ascii_case(wanted))
+                || port
+                    .ty
+                    .as_deref()
+                    .is_some_and(|ty| ty.eq_ignore_ascii_case(wanted))
+        })
+        .map(|(slot, port)| serde_json::json!({"slot": slot, "name": port.name}))
+        .collect()
+}
+
+fn cmd_registry_search(input_type: &str, registry_path: Option<&Path>, json: bool) -> Result<()> {
+    let registry = query_registry(registry_path)?;
+    let mut names: Vec<&String> = registry.nodes.keys().collect();
+    names.sort();
+    let mut found = Vec::new();
+    for name in names {
+        let node = &registry.nodes[name];
+        let inputs = ports_of_type(&node.inputs, input_type);
+        let dynamic = ports_of_type(&node.dynamic_inputs, input_type);
+        if inputs.is_empty() && dynamic.is_empty() {
+            continue;
+        }
+        let mut entry = serde_json::json!({"type": name, "inputs": inputs});
+        if !dynamic.is_empty() {
+            entry["dynamic_inputs"] = Value::Array(dynamic);
+        }
+        found.push(entry);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&found)?);
+        return Ok(());
+    }
+    for entry in found.iter() {
+        let mut ports = Vec::new();
+        for (key, label) in [("inputs", "in"), ("dynamic_inputs", "dyn")] {
+            for port in entry
+                .get(key)
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+            {
+                ports.push(format!(
+                    "{} {} {}",
+                    label,
+                    port["slot"],
+                    port["name"].as_str().unwrap_or("")
+                ));
+            }
+        }
+        println!(
+            "{}\t{}",
+            entry["type"].as_str().unwrap_or(""),
+            ports.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn cmd_get_input(target: &InputTarget, frame: Option<f64>) -> Result<()> {
+    let pxc = read_pxc(&target.file)?;
+    let registry = load_registry(target.registry.as_deref(), pxc.save_version())?;
+    let node = target.node.as_str();
+    let input_name = target.input_name.as_deref();
+    let value = match frame {
+        Some(frame) => get_input_value_at_in_pxc(
+            &pxc,
+            node,
+            target.input,
+            input_name,
+            frame,
+            registry.as_ref(),
+        )?,
+        None => get_input_value_in_pxc(&pxc, node, target.input, input_name, registry.as_ref())?,
+    };
+    println!("{}", serde_json::to_string_pretty(&value)?);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn cmd_set_input(
     path: &Path,
     node_arg: &str,
@@ -340,35 +1189,75 @@ fn cmd_set_input(
     input_name: Option<&str>,
     value_json: Option<&str>,
     value_file: Option<&Path>,
+    expression: Option<&str>,
+    clear_expression: bool,
     registry_path: Option<&Path>,
     out: Option<PathBuf>,
     in_place: bool,
 ) -> Result<()> {
     let mut pxc = read_pxc(path)?;
-    let registry = crate::registry::load_registry(registry_path)?;
+    let registry = crate::registry::load_registry(registry_path, pxc.save_version())?;
 
     let value_str = if let Some(s) = value_json {
-        s.to_string()
+        Some(s.to_string())
     } else if let Some(p) = value_file {
-        std::fs::read_to_string(p)?
+        Some(std::fs::read_to_string(p)?)
+    } else if expression.is_none() && !clear_expression {
+        return Err(anyhow!(
+            "--value, --value-file, --expression or --clear-expression required"
+        ));
     } else {
-        return Err(anyhow!("--value or --value-file required"));
+        None
     };
-    let value: Value =
-        serde_json::from_str(&value_str).map_err(|e| anyhow!("invalid JSON value: {}", e))?;
-
-    set_input_value_in_pxc(
-        &mut pxc,
-        node_arg,
-        input_slot,
-        input_name,
-        value,
-        registry.as_ref(),
-    )?;
+    if let Some(value_str) = value_str {
+        let value: Value =
+            serde_json::from_str(&value_str).map_err(|e| anyhow!("invalid JSON value: {}", e))?;
+        set_input_value_in_pxc(
+            &mut pxc,
+            node_arg,
+            input_slot,
+            input_name,
+            value,
+            registry.as_ref(),
+        )?;
+    }
+    if expression.is_some() || clear_expression {
+        set_input_expression_in_pxc(
+            &mut pxc,
+            node_arg,
+            input_slot,
+            input_name,
+            expression,
+            registry.as_ref(),
+        )?;
+    } else if let Some(expression) =
+        get_input_expression_in_pxc(&pxc, node_arg, input_slot, input_name, registry.as_ref())?
+    {
+        eprintln!(
+            "warning: expression {:?} still drives this input; use --clear-expression to apply the value",
+            expression
+        );
+    }
 
     write_with_target(path, out, in_place, &pxc)
 }
 
+fn cmd_lint(path: &Path, registry_path: Option<&Path>, json: bool) -> Result<()> {
+    let pxc = read_pxc(path)?;
+    let registry = load_registry(registry_path, pxc.save_version())?;
+    let issues = lint_pxc(&pxc, registry.as_ref());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&issues)?);
+        return Ok(());
+    }
+    for issue in issues.iter() {
+        println!("warning: {}", issue);
+    }
+    println!("{} warnings", issues.len());
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn cmd_connect(
     path: &Path,
     from: &str,
@@ -420,6 +1309,689 @@ fn cmd_connect(
     write_with_target(path, out, in_place, &pxc)
 }
 
+fn cmd_graph_apply(
+    path: &Path,
+    graph_path: &Path,
+    registry_path: Option<&Path>,
+    group: Option<&str>,
+    dry_run: bool,
+    out: Option<PathBuf>,
+    in_place: bool,
+) -> Result<()> {
+    let mut pxc = read_pxc(path)?;
+    let registry = crate::registry::load_registry(registry_path, pxc.save_version())?
+        .ok_or_else(|| anyhow!("registry required"))?;
+    let data = std::fs::read_to_string(graph_path)?;
+    let doc: Value =
+        serde_json::from_str(&data).map_err(|e| anyhow!("invalid graph JSON: {}", e))?;
+    let changes = apply_graph_json_to_pxc(&mut pxc, &doc, &registry, group)?;
+    for change in changes.iter() {
+        println!("{}", change);
+    }
+    println!("{} changes", changes.len());
+    if dry_run {
+        return Ok(());
+    }
+    write_with_target(path, out, in_place, &pxc)
+}
+
+fn cmd_build(
+    source: &Path,
+    out: &Path,
+    base: Option<&Path>,
+    registry_path: Option<&Path>,
+) -> Result<()> {
+    let mut pxc = match base {
+        Some(p) => read_pxc(p)?,
+        None => empty_pxc(),
+    };
+    let registry = crate::registry::load_registry(registry_path, pxc.save_version())?
+        .ok_or_else(|| anyhow!("registry required"))?;
+    let built = build_graph_into_pxc(&mut pxc, source, &registry)?;
+    write_pxc(out, &pxc, true)?;
+    for (name, id) in built.iter() {
+        println!("{}\t{}", name, id);
+    }
+    Ok(())
+}
+
+fn cmd_group(
+    path: &Path,
+    nodes: &[String],
+    name: Option<&str>,
+    registry_path: Option<&Path>,
+    out: Option<PathBuf>,
+    in_place: bool,
+) -> Result<()> {
+    let mut pxc = read_pxc(path)?;
+    let registry = crate::registry::load_registry(registry_path, pxc.save_version())?;
+    let members: Vec<&str> = nodes.iter().map(|s| s.as_str()).collect();
+    let group_id = group_nodes_in_pxc(&mut pxc, &members, name, registry.as_ref())?;
+    write_with_target(path, out, in_place, &pxc)?;
+    println!("{}", group_id);
+    Ok(())
+}
+
+fn cmd_ungroup(path: &Path, group: &str, out: Option<PathBuf>, in_place: bool) -> Result<()> {
+    let mut pxc = read_pxc(path)?;
+    let moved = ungroup_in_pxc(&mut pxc, group)?;
+    write_with_target(path, out, in_place, &pxc)?;
+    println!("ungrouped {} nodes", moved);
+    Ok(())
+}
+
+fn ease_arg(values: Option<&Vec<f64>>) -> Result<Option<[f64; 2]>> {
+    match values.map(|v| v.as_slice()) {
+        None => Ok(None),
+        Some(&[x, y]) => Ok(Some([x, y])),
+        Some(_) => bail!("ease handles take two numbers, e.g. 0.3,1"),
+    }
+}
+
+fn cmd_keyframe(command: KeyframeCommand) -> Result<()> {
+    let target = match &command {
+        KeyframeCommand::List { target, .. }
+        | KeyframeCommand::Set { target, .. }
+        | KeyframeCommand::Move { target, .. }
+        | KeyframeCommand::Delete { target, .. }
+        | KeyframeCommand::Animate { target, .. } => target,
+    };
+    let mut pxc = read_pxc(&target.file)?;
+    let registry = load_registry(target.registry.as_deref(), pxc.save_version())?;
+    let node = target.node.as_str();
+    let input = target.input;
+    let input_name = target.input_name.as_deref();
+    let registry = registry.as_ref();
+    let write = match &command {
+        KeyframeCommand::List { json, .. } => {
+            let keys = keyframes_in_pxc(&pxc, node, input, input_name, registry)?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&keys)?);
+            } else {
+                for key in keys.iter() {
+                    println!(
+                        "{}\t{}\t{}\t{}",
+                        key.frame,
+                        key.value,
+                        serde_json::to_value(key.interp_in)?.as_str().unwrap_or(""),
+                        serde_json::to_value(key.interp_out)?.as_str().unwrap_or("")
+                    );
+                }
+            }
+            return Ok(());
+        }
+        KeyframeCommand::Set {
+            frame,
+            value,
+            interp,
+            interp_in,
+            interp_out,
+            ease_in,
+            ease_out,
+            write,
+            ..
+        } => {
+            let value = json_arg(value.as_deref())?;
+            let ease = KeyframeEase {
+                ease_in: ease_arg(ease_in.as_ref())?,
+                ease_out: ease_arg(ease_out.as_ref())?,
+                interp_in: interp_in.or(*interp),
+                interp_out: interp_out.or(*interp),
+            };
+            set_keyframe_in_pxc(
+                &mut pxc, node, input, input_name, *frame, value, &ease, registry,
+            )?;
+            write
+        }
+        KeyframeCommand::Move {
+            frame, to, write, ..
+        } => {
+            move_keyframe_in_pxc(&mut pxc, node, input, input_name, *frame, *to, registry)?;
+            write
+        }
+        KeyframeCommand::Delete { frame, write, .. } => {
+            delete_keyframe_in_pxc(&mut pxc, node, input, input_name, *frame, registry)?;
+            write
+        }
+        KeyframeCommand::Animate { off, write, .. } => {
+            set_animated_in_pxc(&mut pxc, node, input, input_name, !*off, registry)?;
+            write
+        }
+    };
+    write_with_target(&target.file, write.out.clone(), write.in_place, &pxc)
+}
+
+fn print_animation(settings: &AnimationSettings) {
+    println!("frames: {}", settings.frames_total);
+    println!("fps: {}", settings.framerate);
+    if let Some([start, end]) = settings.frame_range {
+        println!("range: {}..{}", start, end);
+    }
+}
+
+fn cmd_anim(command: AnimCommand) -> Result<()> {
+    match command {
+        AnimCommand::Get { file, json } => {
+            let settings = animation_settings(&read_pxc(&file)?);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&settings)?);
+            } else {
+                print_animation(&settings);
+            }
+            Ok(())
+        }
+        AnimCommand::Set {
+            file,
+            frames,
+            fps,
+            range,
+            clear_range,
+            write,
+        } => {
+            let mut pxc = read_pxc(&file)?;
+            let mut settings = animation_settings(&pxc);
+            if let Some(frames) = frames {
+                settings.frames_total = frames;
+            }
+            if let Some(fps) = fps {
+                settings.framerate = fps;
+            }
+            match range.as_deref() {
+                Some(&[start, end]) => settings.frame_range = Some([start, end]),
+                Some(_) => bail!("--range takes two frames, e.g. 0,59"),
+                None if clear_range => settings.frame_range = None,
+                None => {}
+            }
+            set_animation_settings(&mut pxc, &settings)?;
+            write_with_target(&file, write.out, write.in_place, &pxc)?;
+            print_animation(&settings);
+            Ok(())
+        }
+        AnimCommand::Retime {
+            file,
+            frames,
+            fps,
+            write,
+        } => {
+            let mut pxc = read_pxc(&file)?;
+            let report = retime_pxc(&mut pxc, frames, fps)?;
+            write_with_target(&file, write.out, write.in_place, &pxc)?;
+            print_animation(&report.settings);
+            println!(
+                "{} keys moved, {} merged",
+                report.keys_moved, report.keys_merged
+            );
+            Ok(())
+        }
+    }
+}
+
+fn json_arg(value: Option<&str>) -> Result<Option<Value>> {
+    value
+        .map(serde_json::from_str)
+        .transpose()
+        .map_err(|e| anyhow!("invalid JSON value: {}", e))
+}
+
+fn global_type_name(global: &GlobalVar) -> String {
+    match global.ty {
+        Some(ty) => serde_json::to_value(ty)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default(),
+        None => format!("type {}", global.type_index),
+    }
+}
+
+fn cmd_globals(command: GlobalsCommand) -> Result<()> {
+    match command {
+        GlobalsCommand::List { file, json } => {
+            let globals = globals_in_pxc(&read_pxc(&file)?);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&globals)?);
+            } else {
+                for global in globals.iter() {
+                    println!(
+                        "{}\t{}\t{}",
+                        global.name,
+                        global_type_name(global),
+                        global.value
+                    );
+                }
+            }
+            Ok(())
+        }
+        GlobalsCommand::Get { file, name, json } => {
+            let global = get_global_in_pxc(&read_pxc(&file)?, &name)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&global)?);
+            } else {
+                println!("{}", global.value);
+            }
+            Ok(())
+        }
+        GlobalsCommand::Set {
+            file,
+            name,
+            value,
+            ty,
+            rename,
+            write,
+        } => {
+            let mut pxc = read_pxc(&file)?;
+            let edit = GlobalEdit {
+                value: json_arg(value.as_deref())?,
+                ty,
+                rename,
+            };
+            let rewritten = set_global_in_pxc(&mut pxc, &name, &edit)?;
+            write_with_target(&file, write.out, write.in_place, &pxc)?;
+            if edit.rename.is_some() {
+                println!("{} expressions updated", rewritten);
+            }
+            Ok(())
+        }
+        GlobalsCommand::Add {
+            file,
+            name,
+            ty,
+            value,
+            write,
+        } => {
+            let mut pxc = read_pxc(&file)?;
+            add_global_to_pxc(&mut pxc, &name, ty, json_arg(value.as_deref())?)?;
+            write_with_target(&file, write.out, write.in_place, &pxc)
+        }
+        GlobalsCommand::Remove { file, name, write } => {
+            let mut pxc = read_pxc(&file)?;
+            let still_used = remove_global_from_pxc(&mut pxc, &name)?;
+            write_with_target(&file, write.out, write.in_place, &pxc)?;
+            if still_used > 0 {
+                eprintln!("warning: {} expressions still read {}", still_used, name);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn cmd_notes(command: NotesCommand) -> Result<()> {
+    match command {
+        NotesCommand::List { file, json } => {
+            let notes = notes_in_pxc(&read_pxc(&file)?);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&notes)?);
+            } else {
+                for note in notes.iter() {
+                    println!(
+                        "{}\t({}, {})\t{}x{}\t{}\t{}",
+                        note.index,
+                        note.x,
+                        note.y,
+                        note.w,
+                        note.h,
+                        note.color_hex(),
+                        note.text.replace('\n', " ")
+                    );
+                }
+            }
+            Ok(())
+        }
+        NotesCommand::Add {
+            file,
+            text,
+            note,
+            write,
+        } => {
+            let mut pxc = read_pxc(&file)?;
+            let index = add_note_to_pxc(&mut pxc, &text, &note.edit(None)?)?;
+            write_with_target(&file, write.out, write.in_place, &pxc)?;
+            println!("{}", index);
+            Ok(())
+        }
+        NotesCommand::Edit {
+            file,
+            index,
+            text,
+            note,
+            write,
+        } => {
+            let mut pxc = read_pxc(&file)?;
+            edit_note_in_pxc(&mut pxc, index, &note.edit(text)?)?;
+            write_with_target(&file, write.out, write.in_place, &pxc)
+        }
+        NotesCommand::Remove { file, index, write } => {
+            let mut pxc = read_pxc(&file)?;
+            remove_note_from_pxc(&mut pxc, index)?;
+            write_with_target(&file, write.out, write.in_place, &pxc)
+        }
+    }
+}
+
+fn print_metadata(meta: &Metadata) {
+    println!("author: {}", meta.author);
+    println!("description: {}", meta.description.replace('\n', " "));
+    println!("contact: {}", meta.contact);
+    println!("alias: {}", meta.alias);
+    println!("tags: {}", meta.tags.join(", "));
+    for (key, value) in meta.other.iter() {
+        println!("{}: {}", key, value);
+    }
+}
+
+/// `meta` commands take a project or a directory of projects.
+fn cmd_meta(command: MetaCommand) -> Result<()> {
+    match command {
+        MetaCommand::Show { path, json } => {
+            let files = if path.is_dir() {
+                project_files(&path)
+            } else {
+                vec![path.clone()]
+            };
+            let mut all = Vec::new();
+            for file in files.iter() {
+                let meta = metadata_in_pxc(&read_pxc(file)?);
+                if json {
+                    all.push(serde_json::json!({"file": file, "metadata": meta}));
+                } else {
+                    if path.is_dir() {
+                        println!("== {}", file.display());
+                    }
+                    print_metadata(&meta);
+                }
+            }
+            if json {
+                let out = if path.is_dir() {
+                    Value::Array(all)
+                } else {
+                    all.remove(0)["metadata"].take()
+                };
+                println!("{}", serde_json::to_string_pretty(&out)?);
+            }
+            Ok(())
+        }
+        MetaCommand::Set { path, meta, write } => {
+            let edit = meta.edit();
+            if !path.is_dir() {
+                let mut pxc = read_pxc(&path)?;
+                set_metadata_in_pxc(&mut pxc, &edit)?;
+                return write_with_target(&path, write.out, write.in_place, &pxc);
+            }
+            if write.out.is_none() && !write.in_place {
+                bail!("use --out DIR or --in-place for write operations");
+            }
+            let results = set_metadata_in_dir(&path, &edit, write.out.as_deref())?;
+            let changed = results.iter().filter(|(_, changed)| *changed).count();
+            for (file, changed) in results.iter() {
+                let state = if *changed { "updated" } else { "unchanged" };
+                println!("{}\t{}", state, file.display());
+            }
+            println!("{} of {} projects updated", changed, results.len());
+            Ok(())
+        }
+    }
+}
+
+fn cmd_palette(command: PaletteCommand) -> Result<()> {
+    match command {
+        PaletteCommand::Extract {
+            file,
+            format,
+            out,
+            swatch,
+            registry,
+        } => {
+            let pxc = read_pxc(&file)?;
+            let registry = load_registry(registry.as_deref(), pxc.save_version())?;
+            let colors = palette_in_pxc(&pxc, registry.as_ref());
+            let from_ext = out
+                .as_deref()
+                .and_then(|p| p.extension())
+                .and_then(|e| e.to_str())
+                .and_then(PaletteFormat::from_extension);
+            let format = format.or(from_ext).unwrap_or(PaletteFormat::Json);
+            let name = file
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("palette");
+            let bytes = render_palette(&colors, format, name, swatch)?;
+            match out {
+                Some(out) => {
+                    std::fs::write(&out, bytes)
+                        .with_context(|| format!("writing {}", out.display()))?;
+                    eprintln!("{} colours", colors.len());
+                }
+                None if format == PaletteFormat::Png => bail!("use --out for PNG output"),
+                None => print!("{}", String::from_utf8_lossy(&bytes)),
+            }
+            Ok(())
+        }
+        PaletteCommand::Remap {
+            file,
+            map,
+            nearest,
+            registry,
+            json,
+            write,
+        } => {
+            let text = std::fs::read_to_string(&map)
+                .with_context(|| format!("reading {}", map.display()))?;
+            let value: Value = serde_json::from_str(&text)
+                .with_context(|| format!("parsing {}", map.display()))?;
+            let map = PaletteMap::from_json(&value)?;
+            let mut pxc = read_pxc(&file)?;
+            let registry = load_registry(registry.as_deref(), pxc.save_version())?;
+            let report = remap_palette_in_pxc(&mut pxc, &map, nearest, registry.as_ref());
+            write_with_target(&file, write.out, write.in_place, &pxc)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                for node in report.iter() {
+                    let name = node.node_name.as_deref().unwrap_or("");
+                    println!("{}\t{}\t{}", node.node, name, node.substitutions);
+                }
+                let total: usize = report.iter().map(|n| n.substitutions).sum();
+                println!("{} substitutions in {} nodes", total, report.len());
+            }
+            Ok(())
+        }
+    }
+}
+
+fn input_ref_line(input: &InputRef) -> String {
+    let mut line = format!("{}\t{}", input.node, input.input);
+    if let Some(name) = &input.input_name {
+        line.push_str(&format!("\t{}", name));
+    }
+    if let Some(name) = &input.node_name {
+        line.push_str(&format!("\t({})", name));
+    }
+    line
+}
+
+fn cmd_favorites(command: FavoritesCommand) -> Result<()> {
+    let (target, write, add) = match command {
+        FavoritesCommand::List {
+            file,
+            registry,
+            json,
+        } => {
+            let pxc = read_pxc(&file)?;
+            let registry = load_registry(registry.as_deref(), pxc.save_version())?;
+            let favorites = favorites_in_pxc(&pxc, registry.as_ref());
+            if json {
+                println!("{}", serde_json::to_string_pretty(&favorites)?);
+            } else {
+                for favorite in favorites.iter() {
+                    println!("{}", input_ref_line(favorite));
+                }
+            }
+            return Ok(());
+        }
+        FavoritesCommand::Add { target, write } => (target, write, true),
+        FavoritesCommand::Remove { target, write } => (target, write, false),
+    };
+    let mut pxc = read_pxc(&target.file)?;
+    let registry = load_registry(target.registry.as_deref(), pxc.save_version())?;
+    let node = target.node.as_str();
+    let input_name = target.input_name.as_deref();
+    if add {
+        if !add_favorite_in_pxc(&mut pxc, node, target.input, input_name, registry.as_ref())? {
+            eprintln!("already a favourite");
+        }
+    } else {
+        remove_favorite_in_pxc(&mut pxc, node, target.input, input_name, registry.as_ref())?;
+    }
+    write_with_target(&target.file, write.out, write.in_place, &pxc)
+}
+
+fn cmd_panels(command: PanelsCommand) -> Result<()> {
+    match command {
+        PanelsCommand::List {
+            file,
+            registry,
+            json,
+        } => {
+            let pxc = read_pxc(&file)?;
+            let registry = load_registry(registry.as_deref(), pxc.save_version())?;
+            let panels = panels_in_pxc(&pxc, registry.as_ref());
+            if json {
+                println!("{}", serde_json::to_string_pretty(&panels)?);
+            } else {
+                for panel in panels.iter() {
+                    println!("{}\t{}", panel.index, panel.name);
+                    for input in panel.inputs.iter() {
+                        println!("  {}", input_ref_line(input));
+                    }
+                }
+            }
+            Ok(())
+        }
+        PanelsCommand::Add {
+            file,
+            name,
+            inputs,
+            registry,
+            write,
+        } => {
+            let mut pxc = read_pxc(&file)?;
+            let registry = load_registry(registry.as_deref(), pxc.save_version())?;
+            let mut refs = Vec::new();
+            for spec in inputs.iter() {
+                let (node, input) = spec
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("expected NODE:INPUT, got {:?}", spec))?;
+                let (slot, input_name) = match input.parse::<usize>() {
+                    Ok(slot) => (Some(slot), None),
+                    Err(_) => (None, Some(input)),
+                };
+                refs.push(resolve_input(
+                    &pxc,
+                    node,
+                    slot,
+                    input_name,
+                    registry.as_ref(),
+                )?);
+            }
+            let index = add_panel_to_pxc(&mut pxc, &name, &refs)?;
+            write_with_target(&file, write.out, write.in_place, &pxc)?;
+            println!("{}", index);
+            Ok(())
+        }
+        PanelsCommand::Remove { file, index, write } => {
+            let mut pxc = read_pxc(&file)?;
+            remove_panel_from_pxc(&mut pxc, index)?;
+            write_with_target(&file, write.out, write.in_place, &pxc)
+        }
+    }
+}
+
+fn cmd_migrate(
+    path: &Path,
+    from_registry: &Path,
+    to_registry: &Path,
+    out: Option<PathBuf>,
+    in_place: bool,
+) -> Result<()> {
+    let mut pxc = read_pxc(path)?;
+    let from = load_registry_file(from_registry)?;
+    let to = load_registry_file(to_registry)?;
+    let report = migrate_pxc(&mut pxc, &from, &to)?;
+    write_with_target(path, out, in_place, &pxc)?;
+    for issue in report.issues.iter() {
+        println!("{}", issue);
+    }
+    println!(
+        "{} nodes, {} links migrated, {} issues",
+        report.nodes,
+        report.links,
+        report.issues.len()
+    );
+    Ok(())
+}
+
+fn cmd_add_input_group(
+    path: &Path,
+    node: &str,
+    registry_path: Option<&Path>,
+    out: Option<PathBuf>,
+    in_place: bool,
+) -> Result<()> {
+    let mut pxc = read_pxc(path)?;
+    let registry = crate::registry::load_registry(registry_path, pxc.save_version())?
+        .ok_or_else(|| anyhow!("registry required"))?;
+    let index = add_input_group_in_pxc(&mut pxc, node, &registry)?;
+    write_with_target(path, out, in_place, &pxc)?;
+    println!("{}", index);
+    Ok(())
+}
+
+fn cmd_remove_input_group(
+    path: &Path,
+    node: &str,
+    index: usize,
+    registry_path: Option<&Path>,
+    out: Option<PathBuf>,
+    in_place: bool,
+) -> Result<()> {
+    let mut pxc = read_pxc(path)?;
+    let registry = crate::registry::load_registry(registry_path, pxc.save_version())?
+        .ok_or_else(|| anyhow!("registry required"))?;
+    remove_input_group_in_pxc(&mut pxc, node, index, &registry)?;
+    write_with_target(path, out, in_place, &pxc)
+}
+
+fn cmd_layout(
+    path: &Path,
+    nodes: &[String],
+    spacing: Option<&str>,
+    origin_only: bool,
+    out: Option<PathBuf>,
+    in_place: bool,
+) -> Result<()> {
+    let mut pxc = read_pxc(path)?;
+    let spacing = match spacing {
+        Some(s) => {
+            let (x, y) = s
+                .split_once(',')
+                .ok_or_else(|| anyhow!("--spacing must be X,Y"))?;
+            let x: f64 = x.trim().parse().context("invalid spacing X")?;
+            let y: f64 = y.trim().parse().context("invalid spacing Y")?;
+            (x, y)
+        }
+        None => DEFAULT_SPACING,
+    };
+    let only: Vec<&str> = nodes.iter().map(|s| s.as_str()).collect();
+    let only = if only.is_empty() {
+        None
+    } else {
+        Some(only.as_slice())
+    };
+    let moved = auto_layout_pxc(&mut pxc, only, spacing, origin_only)?;
+    write_with_target(path, out, in_place, &pxc)?;
+    println!("positioned {} nodes", moved);
+    Ok(())
+}
+
 fn cmd_extract_preview(path: &Path, out: &Path) -> Result<()> {
     let pxc = read_pxc(path)?;
     let preview = decode_preview(&pxc.json)?;
@@ -81,14 +81,14 @@ pub(crate) fn color_from_value(v: &Value) -> Option<u32> {
                     return Some(c);
                 }
             }
-        } else if let Some(f) = n.as_f64() {
-            if f.is_finite() {
-                let r = f.round();
-                if (r - f).abs() < 0.0001 && r >= 0.0 && r <= u32::MAX as f64 {
-                    let c = r as u32;
-                    if c & 0xFF00_0000 != 0 {
-                        return Some(c);
-                    }
+        } else if let Some(f) = n.as_f64()
+            && f.is_finite()
+        {
+            let r = f.round();
+            if (r - f).abs() < 0.0001 && r >= 0.0 && r <= u32::MAX as f64 {
+                let c = r as u32;
+                if c & 0xFF00_0000 != 0 {
+                    return Some(c);
                 }
             }
         }
@@ -105,6 +105,7 @@ pub(crate) fn default_gradient_value() -> Value {
     Value::String(s.to_string())
 }
 
+#[cfg_attr(not(feature = "python"), allow(dead_code))]
 pub(crate) fn gradient_value_from_keys(keys: &[(f64, u32)], interp: i32) -> Value {
     let mut arr = Vec::with_capacity(keys.len());
     for (t, c) in keys {
@@ -132,7 +133,7 @@ fn hue_set_color(color: u32, hue_deg: f64) -> u32 {
     (a as u32) << 24 | (b8 << 16) | (g8 << 8) | r8
 }
 
-fn looks_like_color_array(arr: &[Value]) -> bool {
+pub(crate) fn looks_like_color_array(arr: &[Value]) -> bool {
     if arr.is_empty() {
         return false;
     }
@@ -190,32 +191,31 @@ fn hue_set_value(value: &mut Value, key_name: Option<&str>, hue_deg: f64) -> usi
             let mut changed = 0usize;
             if let Some(keys) = v.get_mut("keys").and_then(|v| v.as_array_mut()) {
                 for k in keys.iter_mut() {
-                    if let Some(obj) = k.as_object_mut() {
-                        if let Some(val) = obj.get_mut("value") {
-                            if let Some(c) = color_from_value(val) {
-                                let out = hue_set_color(c, hue_deg);
-                                *val = Value::Number(out.into());
-                                changed += 1;
-                            }
-                        }
+                    if let Some(obj) = k.as_object_mut()
+                        && let Some(val) = obj.get_mut("value")
+                        && let Some(c) = color_from_value(val)
+                    {
+                        let out = hue_set_color(c, hue_deg);
+                        *val = Value::Number(out.into());
+                        changed += 1;
                     }
                 }
             }
-            if changed > 0 {
-                if let Ok(ns) = serde_json::to_string(&v) {
-                    *s = ns;
-                }
+            if changed > 0
+                && let Ok(ns) = serde_json::to_string(&v)
+            {
+                *s = ns;
             }
             changed
         }
         Value::Number(_) => {
             if let Some(k) = key_name {
-                if k == "value" || k == "d" || key_is_colorish(k) {
-                    if let Some(c) = color_from_value(value) {
-                        let out = hue_set_color(c, hue_deg);
-                        *value = Value::Number(out.into());
-                        return 1;
-                    }
+                if (k == "value" || k == "d" || key_is_colorish(k))
+                    && let Some(c) = color_from_value(value)
+                {
+                    let out = hue_set_color(c, hue_deg);
+                    *value = Value::Number(out.into());
+                    return 1;
                 }
             } else if let Some(c) = color_from_value(value) {
                 let out = hue_set_color(c, hue_deg);
@@ -235,12 +235,11 @@ pub fn hue_set_pxc(pxc: &mut PxcFile, hue_deg: f64) -> usize {
             if let Some(inputs) = node.get_mut("inputs").and_then(|v| v.as_array_mut()) {
                 for input in inputs.iter_mut() {
                     if let Some(obj) = input.as_object_mut() {
-                        if let Some(r) = obj.get_mut("r") {
-                            if let Some(r_obj) = r.as_object_mut() {
-                                if let Some(d) = r_obj.get_mut("d") {
-                                    changed += hue_set_value(d, Some("d"), hue_deg);
-                                }
-                            }
+                        if let Some(r) = obj.get_mut("r")
+                            && let Some(r_obj) = r.as_object_mut()
+                            && let Some(d) = r_obj.get_mut("d")
+                        {
+                            changed += hue_set_value(d, Some("d"), hue_deg);
                         }
                         if let Some(a) = obj.get_mut("animators") {
                             changed += hue_set_value(a, None, hue_deg);
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value, json};
+
+use crate::graph::{GraphData, edge_details};
+use crate::registry::Registry;
+
+/// Cytoscape.js `elements` JSON. Groups become compound nodes via `data.parent`.
+pub(crate) fn render_cytoscape(
+    graph: &GraphData,
+    nodes: &[Value],
+    registry: Option<&Registry>,
+) -> Value {
+    let by_id: HashMap<&str, &Value> = nodes
+        .iter()
+        .filter_map(|n| n.get("id").and_then(|v| v.as_str()).map(|id| (id, n)))
+        .collect();
+
+    let mut out_nodes = Vec::new();
+    for (id, meta) in graph.node_map.iter() {
+        let node = by_id.get(id.as_str());
+        let mut data = Map::new();
+        data.insert("id".to_string(), Value::String(graph.key(id)));
+        data.insert("full_id".to_string(), Value::String(id.clone()));
+        data.insert(
+            "name".to_string(),
+            meta.get("name").cloned().unwrap_or(Value::Null),
+        );
+        data.insert(
+            "type".to_string(),
+            meta.get("type").cloned().unwrap_or(Value::Null),
+        );
+        if let Some(parent) = graph.parent(id) {
+            data.insert("parent".to_string(), Value::String(graph.key(parent)));
+        }
+        let x = node
+            .and_then(|n| n.get("x"))
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0);
+        let y = node
+            .and_then(|n| n.get("y"))
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0);
+        out_nodes.push(json!({ "data": data, "position": { "x": x, "y": y } }));
+    }
+
+    let mut out_edges = Vec::new();
+    for (i, edge) in edge_details(graph, registry).iter().enumerate() {
+        let mut data = Map::new();
+        data.insert("id".to_string(), Value::String(format!("e{}", i)));
+        data.insert("source".to_string(), Value::String(graph.key(edge.from)));
+        data.insert("target".to_string(), Value::String(graph.key(edge.to)));
+        data.insert(
+            "from_slot".to_string(),
+            Value::Number(edge.from_slot.into()),
+        );
+        data.insert(
+            "to_slot".to_string(),
+            Value::Number((edge.to_slot as u64).into()),
+        );
+        data.insert(
+            "from_port".to_string(),
+            json!(edge.from_port.and_then(|p| p.name.clone())),
+        );
+        data.insert(
+            "from_type".to_string(),
+            json!(edge.from_port.and_then(|p| p.ty.clone())),
+        );
+        data.insert(
+            "to_port".to_string(),
+            json!(edge.to_port.and_then(|p| p.name.clone())),
+        );
+        data.insert(
+            "to_type".to_string(),
+            json!(edge.to_port.and_then(|p| p.ty.clone())),
+        );
+        data.insert("tag".to_string(), json!(edge.tag));
+        data.insert("bypass".to_string(), Value::Bool(edge.bypass));
+        out_edges.push(json!({ "data": data }));
+    }
+
+    json!({ "elements": { "nodes": out_nodes, "edges": out_edges } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::collect_graph;
+
+    #[test]
+    fn groups_become_compound_parents() {
+        // `g` (A) holds `a` (B), which feeds `b` (C) outside the group.
+        let nodes = json!([
+            {"id": "g", "type": "Node_Group", "x": 0, "y": 0},
+            {"id": "a", "type": "Node_T", "group": "g", "x": 10, "y": 20, "outputs": [{}]},
+            {"id": "b", "type": "Node_T", "x": 300, "y": 0,
+             "inputs": [{}, {"from_node": "a", "from_index": 0, "from_tag": 1}]},
+        ]);
+        let nodes = nodes.as_array().unwrap();
+        let graph = collect_graph(nodes, false, false, None).unwrap();
+        let out = render_cytoscape(&graph, nodes, None);
+        let node = |key: &str| {
+            out["elements"]["nodes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|n| n["data"]["id"] == key)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(node("B")["data"]["parent"], "A");
+        assert_eq!(node("B")["position"], json!({"x": 10.0, "y": 20.0}));
+        assert!(node("C")["data"].get("parent").is_none());
+        let edges = out["elements"]["edges"].as_array().unwrap();
+        assert_eq!(edges.len(), 1);
+        let edge = &edges[0]["data"];
+        assert_eq!(
+            (&edge["source"], &edge["target"]),
+            (&json!("B"), &json!("C"))
+        );
+        assert_eq!((&edge["to_slot"], &edge["tag"]), (&json!(1), &json!(1)));
+        assert_eq!(edge["bypass"], false);
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt::Write as _;
+
+use serde::Serialize;
+
+use crate::registry::{Registry, RegistryPort};
+
+/// How one port differs between two registries.
+#[derive(Clone, Debug, Serialize)]
+pub struct PortDiff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_slot: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_slot: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_type: Option<String>,
+    /// Any of `added`, `removed`, `renamed`, `retyped` and `moved`.
+    pub changes: Vec<&'static str>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NodeDiff {
+    #[serde(rename = "type")]
+    pub node_type: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<PortDiff>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<PortDiff>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dynamic_inputs: Vec<PortDiff>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RegistryDiff {
+    /// Node types only in the new registry.
+    pub added: Vec<String>,
+    /// Node types only in the old registry.
+    pub removed: Vec<String>,
+    /// Node types in both whose ports differ.
+    pub changed: Vec<NodeDiff>,
+}
+
+fn is_gap(port: &RegistryPort) -> bool {
+    port.name.is_none() && port.ty.is_none()
+}
+
+fn same_name(a: &RegistryPort, b: &RegistryPort) -> bool {
+    match (&a.name, &b.name) {
+        (Some(a), Some(b)) => a.trim().eq_ignore_ascii_case(b.trim()),
+        _ => false,
+    }
+}
+
+/// Pairs the ports of two versions of a node: by name first (in order when a
+/// name repeats), then leftovers that share a slot. Unpaired ports come back
+/// with `None` on the other side; empty slots are skipped.
+pub(crate) fn match_ports(
+    old: &[RegistryPort],
+    new: &[RegistryPort],
+) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut new_used = vec![false; new.len()];
+    let mut pairs: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+    let mut unmatched = Vec::new();
+    for (i, port) in old.iter().enumerate() {
+        if is_gap(port) {
+            continue;
+        }
+        let found = (0..new.len()).find(|&j| !new_used[j] && same_name(port, &new[j]));
+        match found {
+            Some(j) => {
+                new_used[j] = true;
+                pairs.push((Some(i), Some(j)));
+            }
+            None => unmatched.push(i),
+        }
+    }
+    for i in unmatched {
+        if i < new.len() && !new_used[i] && !is_gap(&new[i]) {
+            new_used[i] = true;
+            pairs.push((Some(i), Some(i)));
+        } else {
+            pairs.push((Some(i), None));
+        }
+    }
+    for (j, port) in new.iter().enumerate() {
+        if !new_used[j] && !is_gap(port) {
+            pairs.push((None, Some(j)));
+        }
+    }
+    pairs.sort_by_key(|&(o, n)| (o.or(n), n));
+    pairs
+}
+
+pub(crate) fn diff_ports(old: &[RegistryPort], new: &[RegistryPort]) -> Vec<PortDiff> {
+    let mut out = Vec::new();
+    for (o, n) in match_ports(old, new) {
+        let op = o.map(|i| &old[i]);
+        let np = n.map(|j| &new[j]);
+        let mut changes = Vec::new();
+        match (op, np) {
+            (Some(_), None) => changes.push("removed"),
+            (None, Some(_)) => changes.push("added"),
+            (Some(a), Some(b)) => {
+                if a.name != b.name {
+                    changes.push("renamed");
+                }
+                if a.ty.is_some() && b.ty.is_some() && a.ty != b.ty {
+                    changes.push("retyped");
+                }
+                if o != n {
+                    changes.push("moved");
+                }
+            }
+            (None, None) => {}
+        }
+        if changes.is_empty() {
+            continue;
+        }
+        out.push(PortDiff {
+            old_slot: o,
+            new_slot: n,
+            old_name: op.and_then(|p| p.name.clone()),
+            new_name: np.and_then(|p| p.name.clone()),
+            old_type: op.and_then(|p| p.ty.clone()),
+            new_type: np.and_then(|p| p.ty.clone()),
+            changes,
+        });
+    }
+    out
+}
+
+/// Node types and ports that differ between two registries.
+pub fn diff_registries(old: &Registry, new: &Registry) -> RegistryDiff {
+    let mut diff = RegistryDiff::default();
+    for name in new.nodes.keys() {
+        if !old.nodes.contains_key(name) {
+            diff.added.push(name.clone());
+        }
+    }
+    for (name, old_node) in old.nodes.iter() {
+        let Some(new_node) = new.nodes.get(name) else {
+            diff.removed.push(name.clone());
+            continue;
+        };
+        let node = NodeDiff {
+            node_type: name.clone(),
+            inputs: diff_ports(&old_node.inputs, &new_node.inputs),
+            outputs: diff_ports(&old_node.outputs, &new_node.outputs),
+            dynamic_inputs: diff_ports(&old_node.dynamic_inputs, &new_node.dynamic_inputs),
+        };
+        if !node.inputs.is_empty() || !node.outputs.is_empty() || !node.dynamic_inputs.is_empty() {
+            diff.changed.push(node);
+        }
+    }
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed.sort_by(|a, b| a.node_type.cmp(&b.node_type));
+    diff
+}
+
+fn slot_text(slot: Option<usize>) -> String {
+    slot.map_or("-".to_string(), |s| s.to_string())
+}
+
+fn quoted(name: Option<&str>) -> String {
+    name.map_or("(unnamed)".to_string(), |n| format!("\"{}\"", n))
+}
+
+/// One line per change, e.g. `  in 2 -> 3 moved "Opacity"`.
+pub(crate) fn render_diff(diff: &RegistryDiff) -> String {
+    let mut s = String::new();
+    for name in diff.added.iter() {
+        let _ = writeln!(s, "+ {}", name);
+    }
+    for name in diff.removed.iter() {
+        let _ = writeln!(s, "- {}", name);
+    }
+    for node in diff.changed.iter() {
+        let _ = writeln!(s, "~ {}", node.node_type);
+        let lists = [
+            ("in", &node.inputs),
+            ("out", &node.outputs),
+            ("dyn", &node.dynamic_inputs),
+        ];
+        for (kind, ports) in lists {
+            for p in ports.iter() {
+                let slots = match (p.old_slot, p.new_slot) {
+                    (Some(o), Some(n)) if o != n => format!("{} -> {}", o, n),
+                    (o, n) => slot_text(o.or(n)),
+                };
+                let mut line = format!("  {} {} {}", kind, slots, p.changes.join(", "));
+                let old_name = quoted(p.old_name.as_deref());
+                let new_name = quoted(p.new_name.as_deref());
+                if p.changes.contains(&"renamed") {
+                    let _ = write!(line, " {} -> {}", old_name, new_name);
+                } else if p.new_slot.is_some() {
+                    let _ = write!(line, " {}", new_name);
+                } else {
+                    let _ = write!(line, " {}", old_name);
+                }
+                if p.changes.contains(&"retyped") {
+                    let _ = write!(
+                        line,
+                        " ({} -> {})",
+                        p.old_type.as_deref().unwrap_or("?"),
+                        p.new_type.as_deref().unwrap_or("?")
+                    );
+                } else if let Some(ty) = p.new_type.as_deref().or(p.old_type.as_deref()) {
+                    let _ = write!(line, " ({})", ty);
+                }
+                let _ = writeln!(s, "{}", line);
+            }
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(name: &str, ty: &str) -> RegistryPort {
+        RegistryPort {
+            name: Some(name.to_string()),
+            ty: Some(ty.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn inserted_input_shifts_later_slots() {
+        let old = [port("Surface", "s"), port("Size", "i"), port("Mask", "s")];
+        let new = [
+            port("Surface", "s"),
+            port("Strength", "f"),
+            port("Size", "i"),
+            port("Mask", "s"),
+        ];
+        let pairs = match_ports(&old, &new);
+        assert_eq!(
+            pairs,
+            vec![
+                (Some(0), Some(0)),
+                (None, Some(1)),
+                (Some(1), Some(2)),
+                (Some(2), Some(3)),
+            ]
+        );
+        let changes: Vec<_> = diff_ports(&old, &new)
+            .into_iter()
+            .map(|p| p.changes)
+            .collect();
+        assert_eq!(changes, vec![vec!["added"], vec!["moved"], vec!["moved"]]);
+    }
+
+    #[test]
+    fn same_slot_leftovers_are_renames() {
+        let old = [port("Surface", "s"), port("Size", "i")];
+        let new = [port("Surface", "s"), port("Radius", "f")];
+        let diff = diff_ports(&old, &new);
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].changes, vec!["renamed", "retyped"]);
+        assert_eq!((diff[0].old_slot, diff[0].new_slot), (Some(1), Some(1)));
+    }
+}
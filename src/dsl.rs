@@ -0,0 +1,756 @@
+//! `.pxg` graph source: a small declarative language compiled into project nodes.
+//!
+//! ```text
+//! // comments run to the end of the line
+//! include "common.pxg"
+//! noise = Node_Noise(Seed: 7)
+//! blur = Node_Blur(Size: 4) <- noise.out0
+//! mix = Node_Blend(Opacity: 0.5, Color: #ff8000) <- blur, noise
+//! mix.Background <- blur."Surface Out"
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Error, Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::color::color_from_rgba;
+use crate::group::set_input_link;
+use crate::layout::{DEFAULT_SPACING, auto_layout_pxc};
+use crate::ops::build_node;
+use crate::pxc::PxcFile;
+use crate::registry::{Registry, RegistryPort};
+
+#[derive(Clone, Copy, Debug)]
+struct Loc {
+    file: usize,
+    line: usize,
+    col: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Ident(String),
+    Num(String),
+    Str(String),
+    Color(u32),
+    Sym(&'static str),
+    Newline,
+    Eof,
+}
+
+#[derive(Clone)]
+struct Token {
+    tok: Tok,
+    loc: Loc,
+}
+
+/// An input or output named by slot number or by port name.
+#[derive(Clone, Debug)]
+enum Key {
+    Slot(usize),
+    Name(String),
+}
+
+struct Ref {
+    node: String,
+    output: Option<Key>,
+    loc: Loc,
+}
+
+enum ArgValue {
+    Value(Value),
+    Link(Ref),
+}
+
+struct Arg {
+    key: Key,
+    loc: Loc,
+    value: ArgValue,
+}
+
+struct Decl {
+    name: String,
+    loc: Loc,
+    ty: String,
+    ty_loc: Loc,
+    args: Vec<Arg>,
+    sources: Vec<Ref>,
+}
+
+struct Assign {
+    node: String,
+    loc: Loc,
+    arg: Arg,
+}
+
+#[derive(Default)]
+struct Program {
+    files: Vec<PathBuf>,
+    decls: Vec<Decl>,
+    assigns: Vec<Assign>,
+}
+
+impl Program {
+    fn error(&self, loc: Loc, msg: impl std::fmt::Display) -> Error {
+        anyhow!(
+            "{}:{}:{}: {}",
+            self.files[loc.file].display(),
+            loc.line,
+            loc.col,
+            msg
+        )
+    }
+}
+
+fn lex(program: &Program, file: usize, src: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line, mut col) = (0usize, 1usize, 1usize);
+    let mut depth = 0usize;
+    while i < chars.len() {
+        let c = chars[i];
+        let loc = Loc { file, line, col };
+        let start = i;
+        let tok = match c {
+            ' ' | '\t' | '\r' => {
+                i += 1;
+                col += 1;
+                continue;
+            }
+            '\n' => {
+                i += 1;
+                line += 1;
+                col = 1;
+                if depth > 0 {
+                    continue;
+                }
+                Tok::Newline
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None | Some('\n') => {
+                            return Err(program.error(loc, "unterminated string"));
+                        }
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\\') => {
+                            let esc = chars.get(i + 1).copied().unwrap_or('\\');
+                            s.push(match esc {
+                                'n' => '\n',
+                                't' => '\t',
+                                other => other,
+                            });
+                            i += 2;
+                        }
+                        Some(&ch) => {
+                            s.push(ch);
+                            i += 1;
+                        }
+                    }
+                }
+                Tok::Str(s)
+            }
+            '#' => {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_hexdigit() {
+                    i += 1;
+                }
+                let hex: String = chars[start + 1..i].iter().collect();
+                let channel = |k: usize| u8::from_str_radix(&hex[k..k + 2], 16).unwrap_or(0);
+                match hex.len() {
+                    6 => Tok::Color(color_from_rgba(channel(0), channel(2), channel(4), 255)),
+                    8 => Tok::Color(color_from_rgba(
+                        channel(0),
+                        channel(2),
+                        channel(4),
+                        channel(6),
+                    )),
+                    _ => return Err(program.error(loc, "colors are written #RRGGBB or #RRGGBBAA")),
+                }
+            }
+            '<' if chars.get(i + 1) == Some(&'-') => {
+                i += 2;
+                Tok::Sym("<-")
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())) =>
+            {
+                i += 1;
+                while i < chars.len() {
+                    let ch = chars[i];
+                    let exp_sign = (ch == '-' || ch == '+') && matches!(chars[i - 1], 'e' | 'E');
+                    if ch.is_ascii_digit() || ch == '.' || ch == 'e' || ch == 'E' || exp_sign {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                Tok::Num(chars[start..i].iter().collect())
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                Tok::Ident(chars[start..i].iter().collect())
+            }
+            '(' | '[' => {
+                depth += 1;
+                i += 1;
+                Tok::Sym(if c == '(' { "(" } else { "[" })
+            }
+            ')' | ']' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+                Tok::Sym(if c == ')' { ")" } else { "]" })
+            }
+            '=' | ',' | ':' | '.' => {
+                i += 1;
+                Tok::Sym(match c {
+                    '=' => "=",
+                    ',' => ",",
+                    ':' => ":",
+                    _ => ".",
+                })
+            }
+            other => return Err(program.error(loc, format!("unexpected character '{}'", other))),
+        };
+        if !matches!(tok, Tok::Newline) {
+            col += i - start;
+        }
+        tokens.push(Token { tok, loc });
+    }
+    tokens.push(Token {
+        tok: Tok::Eof,
+        loc: Loc { file, line, col },
+    });
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    program: &'a Program,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let t = self.tokens[self.pos].clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        t
+    }
+
+    fn is_sym(&self, sym: &str) -> bool {
+        matches!(&self.peek().tok, Tok::Sym(s) if *s == sym)
+    }
+
+    fn expect_sym(&mut self, sym: &str) -> Result<Loc> {
+        let t = self.next();
+        match &t.tok {
+            Tok::Sym(s) if *s == sym => Ok(t.loc),
+            other => Err(self.program.error(
+                t.loc,
+                format!("expected '{}', found {}", sym, describe(other)),
+            )),
+        }
+    }
+
+    fn expect_ident(&mut self, what: &str) -> Result<(String, Loc)> {
+        let t = self.next();
+        match &t.tok {
+            Tok::Ident(s) => Ok((s.clone(), t.loc)),
+            other => Err(self.program.error(
+                t.loc,
+                format!("expected {}, found {}", what, describe(other)),
+            )),
+        }
+    }
+
+    fn expect_end(&mut self) -> Result<()> {
+        let t = self.next();
+        match &t.tok {
+            Tok::Newline | Tok::Eof => Ok(()),
+            other => Err(self.program.error(
+                t.loc,
+                format!("expected end of line, found {}", describe(other)),
+            )),
+        }
+    }
+
+    /// A port key: a slot number, a quoted name, or words such as `Surface In`.
+    fn key(&mut self) -> Result<(Key, Loc)> {
+        let t = self.next();
+        let loc = t.loc;
+        match &t.tok {
+            Tok::Num(n) => n
+                .parse::<usize>()
+                .map(|s| (Key::Slot(s), loc))
+                .map_err(|_| {
+                    self.program
+                        .error(loc, format!("invalid slot number {}", n))
+                }),
+            Tok::Str(s) => Ok((Key::Name(s.clone()), loc)),
+            Tok::Ident(s) => {
+                let mut words = vec![s.clone()];
+                while let Tok::Ident(w) = &self.peek().tok {
+                    words.push(w.clone());
+                    self.next();
+                }
+                Ok((Key::Name(words.join(" ")), loc))
+            }
+            other => Err(self.program.error(
+                loc,
+                format!("expected a port name, found {}", describe(other)),
+            )),
+        }
+    }
+
+    fn reference(&mut self) -> Result<Ref> {
+        let (node, loc) = self.expect_ident("a node name")?;
+        let output = if self.is_sym(".") {
+            self.next();
+            Some(self.key()?.0)
+        } else {
+            None
+        };
+        Ok(Ref { node, output, loc })
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        let t = self.next();
+        let loc = t.loc;
+        match &t.tok {
+            Tok::Num(n) => {
+                let v = if let Ok(i) = n.parse::<i64>() {
+                    json!(i)
+                } else {
+                    let f: f64 = n
+                        .parse()
+                        .map_err(|_| self.program.error(loc, format!("invalid number {}", n)))?;
+                    json!(f)
+                };
+                Ok(v)
+            }
+            Tok::Str(s) => Ok(Value::String(s.clone())),
+            Tok::Color(c) => Ok(json!(c)),
+            Tok::Ident(s) if s == "true" => Ok(Value::Bool(true)),
+            Tok::Ident(s) if s == "false" => Ok(Value::Bool(false)),
+            Tok::Sym("[") => {
+                let mut items = Vec::new();
+                while !self.is_sym("]") {
+                    items.push(self.value()?);
+                    if !self.is_sym(",") {
+                        break;
+                    }
+                    self.next();
+                }
+                self.expect_sym("]")?;
+                Ok(Value::Array(items))
+            }
+            other => Err(self
+                .program
+                .error(loc, format!("expected a value, found {}", describe(other)))),
+        }
+    }
+
+    /// `key: value` or `key <- ref`.
+    fn arg(&mut self, assign: &str) -> Result<Arg> {
+        let (key, loc) = self.key()?;
+        let value = if self.is_sym("<-") {
+            self.next();
+            ArgValue::Link(self.reference()?)
+        } else {
+            self.expect_sym(assign)?;
+            ArgValue::Value(self.value()?)
+        };
+        Ok(Arg { key, loc, value })
+    }
+}
+
+fn describe(tok: &Tok) -> String {
+    match tok {
+        Tok::Ident(s) => format!("'{}'", s),
+        Tok::Num(n) => n.clone(),
+        Tok::Str(s) => format!("\"{}\"", s),
+        Tok::Color(_) => "a color".to_string(),
+        Tok::Sym(s) => format!("'{}'", s),
+        Tok::Newline => "end of line".to_string(),
+        Tok::Eof => "end of file".to_string(),
+    }
+}
+
+fn parse_file(
+    program: &mut Program,
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    from: Option<Loc>,
+) -> Result<()> {
+    let canonical = fs::canonicalize(path).map_err(|e| {
+        let msg = format!("cannot read {}: {}", path.display(), e);
+        match from {
+            Some(loc) => program.error(loc, msg),
+            None => anyhow!(msg),
+        }
+    })?;
+    if stack.contains(&canonical) {
+        let msg = format!("include cycle through {}", path.display());
+        return Err(match from {
+            Some(loc) => program.error(loc, msg),
+            None => anyhow!(msg),
+        });
+    }
+    if program
+        .files
+        .iter()
+        .any(|f| fs::canonicalize(f).map(|c| c == canonical).unwrap_or(false))
+    {
+        return Ok(());
+    }
+    let src = fs::read_to_string(path)?;
+    let file = program.files.len();
+    program.files.push(path.to_path_buf());
+    let tokens = lex(program, file, &src)?;
+    stack.push(canonical);
+
+    let mut includes = Vec::new();
+    let mut decls = Vec::new();
+    let mut assigns = Vec::new();
+    {
+        let mut p = Parser {
+            program,
+            tokens,
+            pos: 0,
+        };
+        loop {
+            let t = p.next();
+            let loc = t.loc;
+            match t.tok.clone() {
+                Tok::Eof => break,
+                Tok::Newline => continue,
+                Tok::Ident(word) if word == "include" && matches!(p.peek().tok, Tok::Str(_)) => {
+                    if let Tok::Str(target) = p.next().tok.clone() {
+                        includes.push((target, loc));
+                    }
+                    p.expect_end()?;
+                }
+                Tok::Ident(name) if p.is_sym("=") => {
+                    p.next();
+                    let (ty, ty_loc) = p.expect_ident("a node type")?;
+                    p.expect_sym("(")?;
+                    let mut args = Vec::new();
+                    while !p.is_sym(")") {
+                        args.push(p.arg(":")?);
+                        if !p.is_sym(",") {
+                            break;
+                        }
+                        p.next();
+                    }
+                    p.expect_sym(")")?;
+                    let mut sources = Vec::new();
+                    if p.is_sym("<-") {
+                        p.next();
+                        loop {
+                            sources.push(p.reference()?);
+                            if !p.is_sym(",") {
+                                break;
+                            }
+                            p.next();
+                        }
+                    }
+                    p.expect_end()?;
+                    decls.push(Decl {
+                        name,
+                        loc,
+                        ty,
+                        ty_loc,
+                        args,
+                        sources,
+                    });
+                }
+                Tok::Ident(node) if p.is_sym(".") => {
+                    p.next();
+                    let arg = p.arg("=")?;
+                    p.expect_end()?;
+                    assigns.push(Assign { node, loc, arg });
+                }
+                other => {
+                    return Err(p.program.error(
+                        loc,
+                        format!("expected a statement, found {}", describe(&other)),
+                    ));
+                }
+            }
+        }
+    }
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    for (target, loc) in includes {
+        parse_file(program, &dir.join(target), stack, Some(loc))?;
+    }
+    program.decls.extend(decls);
+    program.assigns.extend(assigns);
+    stack.pop();
+    Ok(())
+}
+
+/// Finds a port by slot, exact name, loose name (case and `_` vs space), or `in3`/`out3`.
+fn port_slot(ports: &[RegistryPort], key: &Key, prefix: &str) -> Option<usize> {
+    match key {
+        Key::Slot(s) => (*s < ports.len()).then_some(*s),
+        Key::Name(name) => {
+            let loose = |s: &str| s.to_lowercase().replace('_', " ");
+            ports
+                .iter()
+                .position(|p| p.name.as_deref() == Some(name.as_str()))
+                .or_else(|| {
+                    ports
+                        .iter()
+                        .position(|p| p.name.as_deref().map(loose) == Some(loose(name)))
+                })
+                .or_else(|| {
+                    name.strip_prefix(prefix)
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .filter(|s| *s < ports.len())
+                })
+        }
+    }
+}
+
+fn key_label(key: &Key) -> String {
+    match key {
+        Key::Slot(s) => s.to_string(),
+        Key::Name(n) => format!("\"{}\"", n),
+    }
+}
+
+/// Compiles the `.pxg` file at `path` (and its includes) into new nodes in `pxc`.
+/// Inputs not mentioned keep their registry defaults; `<- a, b` after a node
+/// connects the sources to its free input slots in order. New nodes are placed
+/// with the layered auto-layout. Returns `(name, node id)` in declaration order.
+pub fn build_graph_into_pxc(
+    pxc: &mut PxcFile,
+    path: &Path,
+    registry: &Registry,
+) -> Result<Vec<(String, String)>> {
+    let mut program = Program::default();
+    parse_file(&mut program, path, &mut Vec::new(), None)?;
+
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (i, decl) in program.decls.iter().enumerate() {
+        if let Some(&prev) = index.get(decl.name.as_str()) {
+            let p = program.decls[prev].loc;
+            return Err(program.error(
+                decl.loc,
+                format!(
+                    "node `{}` is already declared at {}:{}:{}",
+                    decl.name,
+                    program.files[p.file].display(),
+                    p.line,
+                    p.col
+                ),
+            ));
+        }
+        index.insert(&decl.name, i);
+        if !registry.nodes.contains_key(&decl.ty) {
+            return Err(program.error(decl.ty_loc, format!("unknown node type `{}`", decl.ty)));
+        }
+    }
+
+    let mut built: Vec<(String, Value)> = Vec::new();
+    let mut links: Vec<(usize, usize, &Ref)> = Vec::new();
+    for decl in program.decls.iter() {
+        let reg_node = &registry.nodes[&decl.ty];
+        let (id, node) = build_node(&decl.ty, 0, 0, Some(&decl.name), Some(reg_node));
+        built.push((id, node));
+    }
+
+    let mut assigned: HashSet<(usize, usize)> = HashSet::new();
+    let all_args = program
+        .decls
+        .iter()
+        .enumerate()
+        .flat_map(|(i, d)| d.args.iter().map(move |a| (i, a)))
+        .map(Ok)
+        .chain(program.assigns.iter().map(|a| {
+            index
+                .get(a.node.as_str())
+                .map(|&i| (i, &a.arg))
+                .ok_or_else(|| program.error(a.loc, format!("unknown node `{}`", a.node)))
+        }));
+    for item in all_args {
+        let (target, arg) = item?;
+        let decl = &program.decls[target];
+        let ports = &registry.nodes[&decl.ty].inputs;
+        let slot = port_slot(ports, &arg.key, "in").ok_or_else(|| {
+            program.error(
+                arg.loc,
+                format!("{} has no input {}", decl.ty, key_label(&arg.key)),
+            )
+        })?;
+        if !assigned.insert((target, slot)) {
+            return Err(program.error(
+                arg.loc,
+                format!(
+                    "input {} of `{}` is set twice",
+                    key_label(&arg.key),
+                    decl.name
+                ),
+            ));
+        }
+        match &arg.value {
+            ArgValue::Value(v) => {
+                ports[slot]
+                    .check_value(v)
+                    .map_err(|e| program.error(arg.loc, e))?;
+                built[target].1["inputs"][slot]["r"] = json!({ "d": v });
+            }
+            ArgValue::Link(r) => links.push((target, slot, r)),
+        }
+    }
+    for (target, decl) in program.decls.iter().enumerate() {
+        let inputs = registry.nodes[&decl.ty].inputs.len();
+        let mut free = (0..inputs).filter(|s| !assigned.contains(&(target, *s)));
+        for source in decl.sources.iter() {
+            let slot = free.next().ok_or_else(|| {
+                program.error(
+                    source.loc,
+                    format!("`{}` ({}) has no free input left", decl.name, decl.ty),
+                )
+            })?;
+            links.push((target, slot, source));
+        }
+    }
+
+    for (target, slot, r) in links {
+        let &from = index
+            .get(r.node.as_str())
+            .ok_or_else(|| program.error(r.loc, format!("unknown node `{}`", r.node)))?;
+        let from_ty = &program.decls[from].ty;
+        let out = match &r.output {
+            None => 0,
+            Some(key) => {
+                port_slot(&registry.nodes[from_ty].outputs, key, "out").ok_or_else(|| {
+                    program.error(
+                        r.loc,
+                        format!("{} has no output {}", from_ty, key_label(key)),
+                    )
+                })?
+            }
+        };
+        let link = (built[from].0.clone(), out as i64, None);
+        set_input_link(&mut built[target].1["inputs"][slot], Some(&link));
+    }
+
+    let nodes = pxc
+        .json
+        .get_mut("nodes")
+        .and_then(|v| v.as_array_mut())
+        .ok_or_else(|| anyhow!("no nodes array found"))?;
+    let mut out = Vec::with_capacity(built.len());
+    for (decl, (id, node)) in program.decls.iter().zip(built) {
+        out.push((decl.name.clone(), id));
+        nodes.push(node);
+    }
+    let ids: Vec<&str> = out.iter().map(|(_, id)| id.as_str()).collect();
+    auto_layout_pxc(pxc, Some(&ids), DEFAULT_SPACING, true)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::input_link;
+    use crate::pxc::empty_pxc;
+    use crate::registry::RegistryNode;
+
+    fn registry() -> Registry {
+        let port = |name: &str| RegistryPort {
+            name: Some(name.to_string()),
+            ..Default::default()
+        };
+        let node = RegistryNode {
+            inputs: vec![port("Surface In"), port("Size")],
+            outputs: vec![port("Surface Out")],
+            ..Default::default()
+        };
+        Registry {
+            nodes: HashMap::from([("Node_T".to_string(), node)]),
+        }
+    }
+
+    /// Writes `files` into a fresh directory and builds the first one.
+    fn build(tag: &str, files: &[(&str, &str)]) -> Result<(PxcFile, Vec<(String, String)>)> {
+        let dir = std::env::temp_dir().join(format!("pxc-dsl-{}-{}", std::process::id(), tag));
+        fs::create_dir_all(&dir)?;
+        for (name, src) in files {
+            fs::write(dir.join(name), src)?;
+        }
+        let mut pxc = empty_pxc();
+        let result = build_graph_into_pxc(&mut pxc, &dir.join(files[0].0), &registry());
+        fs::remove_dir_all(&dir)?;
+        result.map(|built| (pxc, built))
+    }
+
+    #[test]
+    fn builds_values_and_links() {
+        let src = "// two nodes\na = Node_T(Size: 4)\nb = Node_T() <- a.\"Surface Out\"\n";
+        let (pxc, built) = build("ok", &[("g.pxg", src)]).unwrap();
+        let names: Vec<&str> = built.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+        let nodes = pxc.json["nodes"].as_array().unwrap();
+        assert_eq!(nodes[0]["inputs"][1]["r"]["d"], json!(4));
+        let link = input_link(&nodes[1]["inputs"][0]).unwrap();
+        assert_eq!((link.0.as_str(), link.1), (built[0].1.as_str(), 0));
+    }
+
+    #[test]
+    fn errors_carry_file_line_and_column() {
+        let err = build("type", &[("e.pxg", "a = Node_Blr(Size: 1)\n")]).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("e.pxg:1:5: unknown node type `Node_Blr`"),
+            "{}",
+            err
+        );
+        let err = build("input", &[("e.pxg", "a = Node_T()\na.Strength = 1\n")]).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("e.pxg:2:3: Node_T has no input \"Strength\"")
+        );
+        let err = build("syntax", &[("e.pxg", "a = Node_T(\n")]).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("e.pxg:2:1: expected a port name, found end of file")
+        );
+    }
+
+    #[test]
+    fn include_cycles_are_reported() {
+        let files = [
+            ("a.pxg", "include \"b.pxg\"\na = Node_T()\n"),
+            ("b.pxg", "include \"a.pxg\"\nb = Node_T()\n"),
+        ];
+        let err = build("cycle", &files).unwrap_err().to_string();
+        assert!(err.contains("b.pxg:1:1: include cycle through"), "{}", err);
+        assert!(err.ends_with("a.pxg"), "{}", err);
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{Result, anyhow, bail};
+use serde_json::{Map, Value, json};
+
+use crate::favorites::update_input_refs;
+use crate::ops::{default_input, resolve_node_id};
+use crate::pxc::PxcFile;
+use crate::registry::{Registry, RegistryNode};
+
+/// A variable-length node's fixed input count and the size of one input group.
+struct DynamicLayout {
+    fix_len: usize,
+    data_len: usize,
+}
+
+impl DynamicLayout {
+    fn of(node: &Map<String, Value>, reg_node: &RegistryNode) -> Result<Self> {
+        let node_type = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        let data_len = reg_node.dynamic_inputs.len();
+        if data_len == 0 {
+            bail!(
+                "{} has no dynamic inputs in the registry; use a registry built by \
+                 `registry-build`",
+                node_type
+            );
+        }
+        let fix_len = reg_node.inputs.len();
+        // The save's own layout must agree with the registry, or groups would be
+        // cut at the wrong slots.
+        if let Some(saved) = node.get("input_fix_len").and_then(|v| v.as_u64())
+            && saved as usize != fix_len
+        {
+            bail!(
+                "node has input_fix_len {} but the registry lists {} fixed inputs for {}",
+                saved,
+                fix_len,
+                node_type
+            );
+        }
+        Ok(Self { fix_len, data_len })
+    }
+
+    fn group_count(&self, inputs: usize) -> Result<usize> {
+        let dynamic = inputs.saturating_sub(self.fix_len);
+        if !dynamic.is_multiple_of(self.data_len) {
+            bail!(
+                "node has {} dynamic inputs, not a multiple of the group size {}",
+                dynamic,
+                self.data_len
+            );
+        }
+        Ok(dynamic / self.data_len)
+    }
+}
+
+fn dynamic_node<'a>(
+    pxc: &'a mut PxcFile,
+    node_arg: &str,
+    registry: &'a Registry,
+) -> Result<(&'a mut Map<String, Value>, &'a RegistryNode)> {
+    let nodes = pxc
+        .json
+        .get_mut("nodes")
+        .and_then(|v| v.as_array_mut())
+        .ok_or_else(|| anyhow!("no nodes array found"))?;
+    let node_id = resolve_node_id(node_arg, nodes)
+        .ok_or_else(|| anyhow!("node id not found: {}", node_arg))?;
+    let node = nodes
+        .iter_mut()
+        .find(|n| n.get("id").and_then(|v| v.as_str()) == Some(node_id.as_str()))
+        .and_then(|n| n.as_object_mut())
+        .ok_or_else(|| anyhow!("node not found after resolve: {}", node_id))?;
+    let node_type = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let reg_node = registry
+        .nodes
+        .get(node_type)
+        .ok_or_else(|| anyhow!("unknown node type: {}", node_type))?;
+    Ok((node, reg_node))
+}
+
+/// Appends one dynamic input group (e.g. another layer) filled with the registry
+/// defaults. Returns the new group's index.
+pub fn add_input_group_in_pxc(
+    pxc: &mut PxcFile,
+    node_arg: &str,
+    registry: &Registry,
+) -> Result<usize> {
+    let (node, reg_node) = dynamic_node(pxc, node_arg, registry)?;
+    let layout = DynamicLayout::of(node, reg_node)?;
+    let inputs = node
+        .entry("inputs")
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("node inputs is not an array"))?;
+    if inputs.len() < layout.fix_len {
+        bail!(
+            "node has {} inputs, fewer than its {} fixed inputs",
+            inputs.len(),
+            layout.fix_len
+        );
+    }
+    let index = layout.group_count(inputs.len())?;
+    inputs.extend(reg_node.dynamic_inputs.iter().map(default_input));
+    node.insert("input_fix_len".to_string(), json!(layout.fix_len));
+    node.insert("data_length".to_string(), json!(layout.data_len));
+    Ok(index)
+}
+
+/// Removes dynamic input group `index`; later groups move down one place.
+pub fn remove_input_group_in_pxc(
+    pxc: &mut PxcFile,
+    node_arg: &str,
+    index: usize,
+    registry: &Registry,
+) -> Result<()> {
+    let (node, reg_node) = dynamic_node(pxc, node_arg, registry)?;
+    let layout = DynamicLayout::of(node, reg_node)?;
+    let inputs = node
+        .get_mut("inputs")
+        .and_then(|v| v.as_array_mut())
+        .ok_or_else(|| anyhow!("node has no inputs array"))?;
+    let groups = layout.group_count(inputs.len())?;
+    if index >= groups {
+        bail!("input group {} out of range (node has {})", index, groups);
+    }
+    let start = layout.fix_len + index * layout.data_len;
+    let end = start + layout.data_len;
+    inputs.drain(start..end);
+
+    let id = node
+        .get("id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    let data_len = layout.data_len;
+    update_input_refs(pxc, &|ref_id, slot| {
+        if ref_id != id || slot < start {
+            Some(slot)
+        } else if slot < end {
+            None
+        } else {
+            Some(slot - data_len)
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,316 @@
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::ops::{resolve_input_slot, resolve_node_id};
+use crate::pxc::PxcFile;
+use crate::registry::Registry;
+
+/// An input referenced by `favVal` or a custom panel.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct InputRef {
+    pub node: String,
+    pub input: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_name: Option<String>,
+}
+
+/// One entry of `cPanels`: a named list of inputs.
+#[derive(Clone, Debug, Serialize)]
+pub struct CustomPanel {
+    pub index: usize,
+    pub name: String,
+    pub inputs: Vec<InputRef>,
+}
+
+fn nodes(pxc: &PxcFile) -> &[Value] {
+    pxc.json
+        .get("nodes")
+        .and_then(|v| v.as_array())
+        .map_or(&[], |a| a.as_slice())
+}
+
+fn find_node<'a>(nodes: &'a [Value], id: &str) -> Option<&'a Value> {
+    nodes
+        .iter()
+        .find(|n| n.get("id").and_then(|v| v.as_str()) == Some(id))
+}
+
+/// `[node_id, input_index]`, as `favVal` and panels store inputs.
+fn as_pair(value: &Value) -> Option<(&str, usize)> {
+    match value.as_array()?.as_slice() {
+        [id, index] => Some((id.as_str()?, index.as_f64()? as usize)),
+        _ => None,
+    }
+}
+
+fn input_ref(pxc: &PxcFile, id: &str, slot: usize, registry: Option<&Registry>) -> InputRef {
+    let node = find_node(nodes(pxc), id);
+    let node_type = node
+        .and_then(|n| n.get("type"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    InputRef {
+        node: id.to_string(),
+        input: slot,
+        node_name: node
+            .and_then(|n| n.get("name"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        input_name: registry
+            .and_then(|r| r.nodes.get(node_type))
+            .and_then(|n| n.input_name(slot)),
+    }
+}
+
+/// Full node id and slot of an input given by short or full id and slot or name.
+pub(crate) fn resolve_input(
+    pxc: &PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    registry: Option<&Registry>,
+) -> Result<(String, usize)> {
+    let nodes = nodes(pxc);
+    let id = resolve_node_id(node_arg, nodes)
+        .ok_or_else(|| anyhow!("node id not found: {}", node_arg))?;
+    let node = find_node(nodes, &id).ok_or_else(|| anyhow!("node not found: {}", id))?;
+    let slot = resolve_input_slot(node, input_slot, input_name, registry)?;
+    let count = node
+        .get("inputs")
+        .and_then(|v| v.as_array())
+        .map_or(0, |a| a.len());
+    if slot >= count {
+        bail!("{} has no input {} ({} inputs)", node_arg, slot, count);
+    }
+    Ok((id, slot))
+}
+
+fn set_favorited(pxc: &mut PxcFile, id: &str, slot: usize, on: bool) {
+    let input = pxc
+        .json
+        .get_mut("nodes")
+        .and_then(|v| v.as_array_mut())
+        .and_then(|nodes| {
+            nodes
+                .iter_mut()
+                .find(|n| n.get("id").and_then(|v| v.as_str()) == Some(id))
+        })
+        .and_then(|n| n.get_mut("inputs"))
+        .and_then(|v| v.as_array_mut())
+        .and_then(|inputs| inputs.get_mut(slot))
+        .and_then(|v| v.as_object_mut());
+    if let Some(input) = input {
+        if on {
+            input.insert("favorited".to_string(), Value::Bool(true));
+        } else {
+            input.remove("favorited");
+        }
+    }
+}
+
+fn list_mut<'a>(pxc: &'a mut PxcFile, key: &str) -> Result<&'a mut Vec<Value>> {
+    let root = pxc
+        .json
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("project JSON is not an object"))?;
+    let list = root.entry(key).or_insert_with(|| json!([]));
+    if !list.is_array() {
+        *list = json!([]);
+    }
+    Ok(list.as_array_mut().expect("list is an array"))
+}
+
+pub fn favorites_in_pxc(pxc: &PxcFile, registry: Option<&Registry>) -> Vec<InputRef> {
+    let favorites = pxc.json.get("favVal").and_then(|v| v.as_array());
+    favorites
+        .into_iter()
+        .flatten()
+        .filter_map(as_pair)
+        .map(|(id, slot)| input_ref(pxc, id, slot, registry))
+        .collect()
+}
+
+/// Marks an input as a favourite. Returns false when it already was one.
+pub fn add_favorite_in_pxc(
+    pxc: &mut PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    registry: Option<&Registry>,
+) -> Result<bool> {
+    let (id, slot) = resolve_input(pxc, node_arg, input_slot, input_name, registry)?;
+    set_favorited(pxc, &id, slot, true);
+    let favorites = list_mut(pxc, "favVal")?;
+    if favorites
+        .iter()
+        .any(|f| as_pair(f) == Some((id.as_str(), slot)))
+    {
+        return Ok(false);
+    }
+    favorites.push(json!([id, slot]));
+    Ok(true)
+}
+
+pub fn remove_favorite_in_pxc(
+    pxc: &mut PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    registry: Option<&Registry>,
+) -> Result<()> {
+    let (id, slot) = resolve_input(pxc, node_arg, input_slot, input_name, registry)?;
+    set_favorited(pxc, &id, slot, false);
+    let favorites = list_mut(pxc, "favVal")?;
+    let before = favorites.len();
+    favorites.retain(|f| as_pair(f) != Some((id.as_str(), slot)));
+    if favorites.len() == before {
+        bail!("{} input {} is not a favourite", node_arg, slot);
+    }
+    Ok(())
+}
+
+pub fn panels_in_pxc(pxc: &PxcFile, registry: Option<&Registry>) -> Vec<CustomPanel> {
+    let panels = pxc.json.get("cPanels").and_then(|v| v.as_array());
+    panels
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, panel)| CustomPanel {
+            index,
+            name: panel
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string(),
+            inputs: panel
+                .get("inputs")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(as_pair)
+                .map(|(id, slot)| input_ref(pxc, id, slot, registry))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Appends a panel showing `inputs` (node, slot) in order and returns its index.
+pub fn add_panel_to_pxc(
+    pxc: &mut PxcFile,
+    name: &str,
+    inputs: &[(String, usize)],
+) -> Result<usize> {
+    if inputs.is_empty() {
+        bail!("a panel needs at least one input");
+    }
+    for (id, slot) in inputs {
+        let count = find_node(nodes(pxc), id)
+            .ok_or_else(|| anyhow!("node not found: {}", id))?
+            .get("inputs")
+            .and_then(|v| v.as_array())
+            .map_or(0, |a| a.len());
+        if *slot >= count {
+            bail!("{} has no input {} ({} inputs)", id, slot, count);
+        }
+    }
+    let refs: Vec<Value> = inputs.iter().map(|(id, slot)| json!([id, slot])).collect();
+    let panels = list_mut(pxc, "cPanels")?;
+    panels.push(json!({"name": name, "inputs": refs}));
+    Ok(panels.len() - 1)
+}
+
+pub fn remove_panel_from_pxc(pxc: &mut PxcFile, index: usize) -> Result<()> {
+    let panels = list_mut(pxc, "cPanels")?;
+    if index >= panels.len() {
+        bail!("panel {} not found ({} panels)", index, panels.len());
+    }
+    panels.remove(index);
+    Ok(())
+}
+
+fn update_pairs(list: &mut Vec<Value>, map: &dyn Fn(&str, usize) -> Option<usize>) -> usize {
+    let before = list.len();
+    list.retain_mut(|entry| {
+        let Some((id, slot)) = as_pair(entry) else {
+            return true;
+        };
+        match map(id, slot) {
+            Some(new_slot) if new_slot == slot => true,
+            Some(new_slot) => {
+                entry[1] = json!(new_slot);
+                true
+            }
+            None => false,
+        }
+    });
+    before - list.len()
+}
+
+/// Rewrites every `favVal` and panel entry through `map` (node id, slot) -> new
+/// slot; entries mapped to `None` are dropped. Returns the number dropped.
+/// Called by operations that delete nodes or move input slots.
+pub(crate) fn update_input_refs(
+    pxc: &mut PxcFile,
+    map: &dyn Fn(&str, usize) -> Option<usize>,
+) -> usize {
+    let mut dropped = 0;
+    if let Some(favorites) = pxc.json.get_mut("favVal").and_then(|v| v.as_array_mut()) {
+        dropped += update_pairs(favorites, map);
+    }
+    if let Some(panels) = pxc.json.get_mut("cPanels").and_then(|v| v.as_array_mut()) {
+        for panel in panels.iter_mut() {
+            if let Some(inputs) = panel.get_mut("inputs").and_then(|v| v.as_array_mut()) {
+                dropped += update_pairs(inputs, map);
+            }
+        }
+    }
+    dropped
+}
+
+/// Drops favourites and panel entries whose node or input no longer exists.
+pub fn prune_input_refs_in_pxc(pxc: &mut PxcFile) -> usize {
+    let counts: std::collections::HashMap<String, usize> = nodes(pxc)
+        .iter()
+        .filter_map(|n| {
+            let id = n.get("id")?.as_str()?.to_string();
+            let count = n
+                .get("inputs")
+                .and_then(|v| v.as_array())
+                .map_or(0, |a| a.len());
+            Some((id, count))
+        })
+        .collect();
+    update_input_refs(pxc, &|id, slot| {
+        counts.get(id).filter(|count| slot < **count).map(|_| slot)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+
+    #[test]
+    fn refs_follow_slots_and_drop_with_their_node() {
+        let mut pxc = empty_pxc();
+        pxc.json["nodes"] = json!([
+            {"id": "a", "type": "Node_T", "inputs": [{}, {}, {}]},
+            {"id": "b", "type": "Node_T", "inputs": [{}]},
+        ]);
+        assert!(add_favorite_in_pxc(&mut pxc, "a", Some(2), None, None).unwrap());
+        assert!(!add_favorite_in_pxc(&mut pxc, "A", Some(2), None, None).unwrap());
+        add_favorite_in_pxc(&mut pxc, "b", Some(0), None, None).unwrap();
+        let panel = [("a".to_string(), 1), ("b".to_string(), 0)];
+        add_panel_to_pxc(&mut pxc, "Knobs", &panel).unwrap();
+        assert_eq!(pxc.json["nodes"][0]["inputs"][2]["favorited"], true);
+
+        pxc.json["nodes"].as_array_mut().unwrap().remove(1);
+        assert_eq!(prune_input_refs_in_pxc(&mut pxc), 2);
+        update_input_refs(&mut pxc, &|_, slot| Some(slot + 1));
+        assert_eq!(pxc.json["favVal"], json!([["a", 3]]));
+        assert_eq!(pxc.json["cPanels"][0]["inputs"], json!([["a", 2]]));
+    }
+}
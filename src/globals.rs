@@ -0,0 +1,465 @@
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+use crate::pxc::PxcFile;
+
+/// Value type of a global; Pixel Composer's `VALUE_TYPE`, saved as its index
+/// in `global_type`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GlobalType {
+    #[value(alias = "int")]
+    Integer,
+    Float,
+    #[value(alias = "bool")]
+    Boolean,
+    Color,
+    Path,
+    Text,
+}
+
+impl GlobalType {
+    fn from_index(index: i64) -> Option<Self> {
+        match index {
+            0 => Some(Self::Integer),
+            1 => Some(Self::Float),
+            2 => Some(Self::Boolean),
+            3 => Some(Self::Color),
+            5 => Some(Self::Path),
+            7 => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> i64 {
+        match self {
+            Self::Integer => 0,
+            Self::Float => 1,
+            Self::Boolean => 2,
+            Self::Color => 3,
+            Self::Path => 5,
+            Self::Text => 7,
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        <Self as ValueEnum>::from_str(name, true).map_err(|_| {
+            anyhow!(
+                "unknown global type {:?} (expected integer, float, boolean, color, path or text)",
+                name
+            )
+        })
+    }
+
+    fn default_value(self) -> Value {
+        match self {
+            Self::Integer | Self::Float => json!(0),
+            Self::Boolean => json!(false),
+            Self::Color => json!(0xFFFF_FFFFu32),
+            Self::Path | Self::Text => json!(""),
+        }
+    }
+
+    /// The value as it is saved for this type, or an error when it does not fit.
+    fn check(self, value: &Value) -> Result<Value> {
+        let checked = match self {
+            Self::Integer => value
+                .as_f64()
+                .filter(|f| f.fract() == 0.0)
+                .map(|f| json!(f as i64)),
+            Self::Float => value.is_number().then(|| value.clone()),
+            Self::Boolean => value.is_boolean().then(|| value.clone()),
+            Self::Color => value
+                .as_f64()
+                .filter(|f| f.fract() == 0.0 && *f >= 0.0 && *f <= u32::MAX as f64)
+                .map(|f| json!(f as u32)),
+            Self::Path | Self::Text => value.is_string().then(|| value.clone()),
+        };
+        checked.ok_or_else(|| {
+            let expected = serde_json::to_value(self)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            anyhow!(
+                "{} global expects {}, got {}",
+                expected,
+                type_hint(self),
+                value
+            )
+        })
+    }
+}
+
+fn type_hint(ty: GlobalType) -> &'static str {
+    match ty {
+        GlobalType::Integer => "a whole number",
+        GlobalType::Float => "a number",
+        GlobalType::Boolean => "true or false",
+        GlobalType::Color => "a 0xAABBGGRR integer",
+        GlobalType::Path | GlobalType::Text => "a string",
+    }
+}
+
+/// One project-wide variable of the `global_node`.
+#[derive(Clone, Debug, Serialize)]
+pub struct GlobalVar {
+    pub name: String,
+    /// `None` for types this tool does not edit; see `type_index`.
+    #[serde(rename = "type")]
+    pub ty: Option<GlobalType>,
+    pub type_index: i64,
+    pub value: Value,
+}
+
+/// Changes to one global; fields left as `None` are kept.
+#[derive(Clone, Debug, Default)]
+pub struct GlobalEdit {
+    pub value: Option<Value>,
+    pub ty: Option<GlobalType>,
+    pub rename: Option<String>,
+}
+
+fn global_name(input: &Value) -> Option<&str> {
+    input.get("name").and_then(|v| v.as_str())
+}
+
+fn global_var(input: &Value) -> GlobalVar {
+    let type_index = input
+        .get("global_type")
+        .and_then(|v| v.as_f64())
+        .map_or(GlobalType::Float.index(), |f| f as i64);
+    GlobalVar {
+        name: global_name(input).unwrap_or("").to_string(),
+        ty: GlobalType::from_index(type_index),
+        type_index,
+        value: input
+            .get("r")
+            .and_then(|r| r.get("d"))
+            .cloned()
+            .unwrap_or(Value::Null),
+    }
+}
+
+fn globals(pxc: &PxcFile) -> &[Value] {
+    pxc.json
+        .get("global_node")
+        .and_then(|g| g.get("inputs"))
+        .and_then(|v| v.as_array())
+        .map_or(&[], |a| a.as_slice())
+}
+
+fn globals_mut(pxc: &mut PxcFile) -> Result<&mut Vec<Value>> {
+    let root = pxc
+        .json
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("project JSON is not an object"))?;
+    let node = root
+        .entry("global_node")
+        .or_insert_with(|| json!({"inputs": [], "attri": {}}));
+    if !node.is_object() {
+        *node = json!({"inputs": [], "attri": {}});
+    }
+    let node = node.as_object_mut().expect("global_node is an object");
+    let inputs = node.entry("inputs").or_insert_with(|| json!([]));
+    if !inputs.is_array() {
+        *inputs = json!([]);
+    }
+    Ok(inputs.as_array_mut().expect("inputs is an array"))
+}
+
+fn find_global<'a>(inputs: &'a mut [Value], name: &str) -> Result<&'a mut Map<String, Value>> {
+    inputs
+        .iter_mut()
+        .find(|g| global_name(g) == Some(name))
+        .and_then(|g| g.as_object_mut())
+        .ok_or_else(|| anyhow!("global not found: {}", name))
+}
+
+/// Globals are referenced by name from expressions, so names must be identifiers.
+fn check_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!(
+            "invalid global name {:?}: use letters, digits and _, not starting with a digit",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Calls `f` on every identifier of an expression that is not inside a string
+/// literal or after a `.`, and returns the rewritten expression.
+fn map_identifiers(expr: &str, f: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(expr.len());
+    let mut quote: Option<char> = None;
+    let mut after_dot = false;
+    let mut chars = expr.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if let Some(q) = quote {
+            out.push(c);
+            if c == '\\' {
+                if let Some((_, escaped)) = chars.next() {
+                    out.push(escaped);
+                }
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            quote = Some(c);
+            out.push(c);
+            after_dot = false;
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, n)) = chars.peek() {
+                if !(n.is_ascii_alphanumeric() || n == '_') {
+                    break;
+                }
+                end = i + n.len_utf8();
+                chars.next();
+            }
+            let ident = &expr[start..end];
+            match (!after_dot).then(|| f(ident)).flatten() {
+                Some(replacement) => out.push_str(&replacement),
+                None => out.push_str(ident),
+            }
+            after_dot = false;
+            continue;
+        }
+        if c.is_ascii_digit() {
+            // Skip number literals whole so `1e5` is not read as an identifier.
+            out.push(c);
+            while let Some(&(_, n)) = chars.peek() {
+                if !(n.is_ascii_alphanumeric() || n == '.' || n == '_') {
+                    break;
+                }
+                out.push(n);
+                chars.next();
+            }
+            after_dot = false;
+            continue;
+        }
+        if !c.is_whitespace() {
+            after_dot = c == '.';
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// True when the expression reads the global `name`.
+pub(crate) fn expression_uses(expr: &str, name: &str) -> bool {
+    let found = std::cell::Cell::new(false);
+    map_identifiers(expr, |ident| {
+        if ident == name {
+            found.set(true);
+        }
+        None
+    });
+    found.get()
+}
+
+/// Every input with an expression (`global_key`), in the graph and in the
+/// `global_node` itself.
+fn expression_inputs(pxc: &mut PxcFile) -> Vec<&mut Map<String, Value>> {
+    let root = &mut pxc.json;
+    let mut lists: Vec<&mut Vec<Value>> = Vec::new();
+    if let Some(obj) = root.as_object_mut() {
+        for (key, value) in obj.iter_mut() {
+            match key.as_str() {
+                "nodes" => {
+                    for node in value.as_array_mut().into_iter().flatten() {
+                        for (field, list) in node.as_object_mut().into_iter().flatten() {
+                            if (field == "inputs" || field == "inspectInputs")
+                                && let Some(list) = list.as_array_mut()
+                            {
+                                lists.push(list);
+                            }
+                        }
+                    }
+                }
+                "global_node" => {
+                    if let Some(list) = value.get_mut("inputs").and_then(|v| v.as_array_mut()) {
+                        lists.push(list);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    lists
+        .into_iter()
+        .flat_map(|list| list.iter_mut())
+        .filter_map(|input| input.as_object_mut())
+        .filter(|input| input.get("global_key").is_some_and(|k| k.is_string()))
+        .collect()
+}
+
+pub fn globals_in_pxc(pxc: &PxcFile) -> Vec<GlobalVar> {
+    globals(pxc).iter().map(global_var).collect()
+}
+
+pub fn get_global_in_pxc(pxc: &PxcFile, name: &str) -> Result<GlobalVar> {
+    globals(pxc)
+        .iter()
+        .find(|g| global_name(g) == Some(name))
+        .map(global_var)
+        .ok_or_else(|| anyhow!("global not found: {}", name))
+}
+
+/// Adds a global; without a value it starts at the type's default.
+pub fn add_global_to_pxc(
+    pxc: &mut PxcFile,
+    name: &str,
+    ty: GlobalType,
+    value: Option<Value>,
+) -> Result<()> {
+    check_name(name)?;
+    let value = match value {
+        Some(value) => ty.check(&value)?,
+        None => ty.default_value(),
+    };
+    let inputs = globals_mut(pxc)?;
+    if inputs.iter().any(|g| global_name(g) == Some(name)) {
+        bail!("global already exists: {}", name);
+    }
+    inputs.push(json!({
+        "name": name,
+        "global_type": ty.index(),
+        "global_disp": 0,
+        "m": 1,
+        "r": {"d": value},
+    }));
+    Ok(())
+}
+
+/// Applies `edit` to a global. A rename also rewrites every expression that
+/// reads the global; the number of inputs rewritten is returned.
+pub fn set_global_in_pxc(pxc: &mut PxcFile, name: &str, edit: &GlobalEdit) -> Result<usize> {
+    if let Some(new_name) = edit.rename.as_deref()
+        && new_name != name
+    {
+        check_name(new_name)?;
+        if globals(pxc)
+            .iter()
+            .any(|g| global_name(g) == Some(new_name))
+        {
+            bail!("global already exists: {}", new_name);
+        }
+    }
+    let inputs = globals_mut(pxc)?;
+    let global = find_global(inputs, name)?;
+    let current = global_var(&Value::Object(global.clone()));
+    let ty = edit.ty.or(current.ty);
+    let value = match (&edit.value, ty) {
+        (Some(value), Some(ty)) => Some(ty.check(value)?),
+        (Some(_), None) => bail!(
+            "global {} has type {}, which cannot be edited; pass a type",
+            name,
+            current.type_index
+        ),
+        // A new type keeps the value when it still fits.
+        (None, Some(ty)) if edit.ty.is_some() => Some(
+            ty.check(&current.value)
+                .unwrap_or_else(|_| ty.default_value()),
+        ),
+        (None, _) => None,
+    };
+    if let Some(ty) = edit.ty
+        && current.ty != Some(ty)
+    {
+        global.insert("global_type".to_string(), json!(ty.index()));
+        // Display widgets depend on the type.
+        global.insert("global_disp".to_string(), json!(0));
+    }
+    if let Some(value) = value {
+        global.insert("r".to_string(), json!({"d": value}));
+    }
+    let Some(new_name) = edit.rename.as_deref().filter(|n| *n != name) else {
+        return Ok(0);
+    };
+    global.insert("name".to_string(), json!(new_name));
+
+    let mut rewritten = 0;
+    for input in expression_inputs(pxc) {
+        let expr = input["global_key"].as_str().unwrap_or("");
+        let renamed = map_identifiers(expr, |ident| (ident == name).then(|| new_name.to_string()));
+        if renamed != expr {
+            input.insert("global_key".to_string(), Value::String(renamed));
+            rewritten += 1;
+        }
+    }
+    Ok(rewritten)
+}
+
+/// Removes a global and returns the number of expressions that still read it.
+pub fn remove_global_from_pxc(pxc: &mut PxcFile, name: &str) -> Result<usize> {
+    let inputs = globals_mut(pxc)?;
+    let before = inputs.len();
+    inputs.retain(|g| global_name(g) != Some(name));
+    if inputs.len() == before {
+        bail!("global not found: {}", name);
+    }
+    Ok(expression_inputs(pxc)
+        .into_iter()
+        .filter(|input| expression_uses(input["global_key"].as_str().unwrap_or(""), name))
+        .count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+
+    #[test]
+    fn rename_rewrites_expressions() {
+        let mut pxc = empty_pxc();
+        pxc.json["nodes"] = json!([{"id": "n", "type": "Node_T", "inputs": [
+            {"global_use": true, "global_key": "size * 2 + sizes + a.size"},
+            {"global_use": true, "global_key": "\"size\" + size"},
+            {"r": {"d": 1}},
+        ]}]);
+        add_global_to_pxc(&mut pxc, "size", GlobalType::Integer, Some(json!(4))).unwrap();
+        let edit = GlobalEdit {
+            rename: Some("scale".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(set_global_in_pxc(&mut pxc, "size", &edit).unwrap(), 2);
+
+        let inputs = &pxc.json["nodes"][0]["inputs"];
+        assert_eq!(inputs[0]["global_key"], "scale * 2 + sizes + a.size");
+        assert_eq!(inputs[1]["global_key"], "\"size\" + scale");
+        let global = get_global_in_pxc(&pxc, "scale").unwrap();
+        assert_eq!(
+            (global.ty, global.value),
+            (Some(GlobalType::Integer), json!(4))
+        );
+        assert_eq!(remove_global_from_pxc(&mut pxc, "scale").unwrap(), 2);
+    }
+
+    #[test]
+    fn values_follow_the_type() {
+        let mut pxc = empty_pxc();
+        add_global_to_pxc(&mut pxc, "flag", GlobalType::Float, Some(json!(2.0))).unwrap();
+        let to_int = GlobalEdit {
+            ty: Some(GlobalType::Integer),
+            ..Default::default()
+        };
+        set_global_in_pxc(&mut pxc, "flag", &to_int).unwrap();
+        assert_eq!(get_global_in_pxc(&pxc, "flag").unwrap().value, json!(2));
+        let text = GlobalEdit {
+            value: Some(json!("x")),
+            ..Default::default()
+        };
+        assert!(set_global_in_pxc(&mut pxc, "flag", &text).is_err());
+    }
+}
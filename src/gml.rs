@@ -0,0 +1,728 @@
+//! Minimal GML lexer and call-expression parser for `registry-build`.
+//!
+//! It understands enough of GML to find node constructors and the
+//! `newInput`/`newOutput` calls inside them: comments, the string forms,
+//! preprocessor lines, nested brackets and chained `.method(...)` calls.
+//! Anything it cannot model as a literal, call or member access is kept as
+//! [`Expr::Other`] with its source text.
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Tok {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Punct(char),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Token {
+    pub(crate) tok: Tok,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+pub(crate) fn tokenize(src: &str) -> Vec<Token> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    while i < bytes.len() {
+        let b = bytes[i];
+        let start = i;
+        let tok = match b {
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i = (i + 2).min(bytes.len());
+                continue;
+            }
+            // `#region`, `#macro NAME value` and friends run to the end of the line
+            // (macros may continue with a trailing backslash).
+            b'#' if bytes.get(i + 1).is_some_and(|c| c.is_ascii_alphabetic())
+                && !is_hex_color(&bytes[i + 1..]) =>
+            {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    if bytes[i] == b'\\' && bytes.get(i + 1) == Some(&b'\n') {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                continue;
+            }
+            b'#' | b'$' if bytes.get(i + 1).is_some_and(|c| c.is_ascii_hexdigit()) => {
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_hexdigit() {
+                    i += 1;
+                }
+                Tok::Number(src[start..i].to_string())
+            }
+            b'@' | b'$' if matches!(bytes.get(i + 1), Some(b'"') | Some(b'\'')) => {
+                // `@"..."` is verbatim; `$"..."` is a template string. Both may span lines.
+                let verbatim = b == b'@';
+                let quote = bytes[i + 1];
+                i += 2;
+                let (text, end) = read_string(src, i, quote, !verbatim);
+                i = end;
+                Tok::Str(text)
+            }
+            b'"' | b'\'' => {
+                let (text, end) = read_string(src, i + 1, b, true);
+                i = end;
+                Tok::Str(text)
+            }
+            b if b.is_ascii_digit()
+                || (b == b'.' && bytes.get(i + 1).is_some_and(|c| c.is_ascii_digit())) =>
+            {
+                if b == b'0' && matches!(bytes.get(i + 1), Some(b'x') | Some(b'b')) {
+                    i += 2;
+                }
+                while i < bytes.len() && (is_ident(bytes[i]) || bytes[i] == b'.') {
+                    i += 1;
+                }
+                Tok::Number(src[start..i].to_string())
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' => {
+                while i < bytes.len() && is_ident(bytes[i]) {
+                    i += 1;
+                }
+                Tok::Ident(src[start..i].to_string())
+            }
+            _ => {
+                let c = src[i..].chars().next().unwrap_or('\0');
+                i += c.len_utf8().max(1);
+                Tok::Punct(c)
+            }
+        };
+        tokens.push(Token { tok, start, end: i });
+    }
+    tokens
+}
+
+fn is_hex_color(rest: &[u8]) -> bool {
+    let n = rest.iter().take_while(|c| c.is_ascii_hexdigit()).count();
+    (n == 6 || n == 8)
+        && !rest
+            .get(n)
+            .is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'_')
+}
+
+/// Reads a string body starting after the opening quote. Returns the text and
+/// the offset past the closing quote.
+fn read_string(src: &str, mut i: usize, quote: u8, escapes: bool) -> (String, usize) {
+    let bytes = src.as_bytes();
+    let start = i;
+    let mut out = String::new();
+    let mut run = start;
+    while i < bytes.len() && bytes[i] != quote {
+        if escapes && bytes[i] == b'\\' && i + 1 < bytes.len() {
+            out.push_str(&src[run..i]);
+            let c = src[i + 1..].chars().next().unwrap_or('\\');
+            match c {
+                'n' => out.push('\n'),
+                't' => out.push('\t'),
+                'r' => out.push('\r'),
+                other => out.push(other),
+            }
+            i += 1 + c.len_utf8();
+            run = i;
+            continue;
+        }
+        i += 1;
+    }
+    out.push_str(&src[run..i.min(bytes.len())]);
+    (out, (i + 1).min(bytes.len()))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Expr {
+    Number(String),
+    Str(String),
+    Ident(String),
+    Neg(Box<Expr>),
+    New(Box<Expr>),
+    Member(Box<Expr>, String),
+    Call(Box<Expr>, Vec<Expr>),
+    Array(Vec<Expr>),
+    Struct(Vec<(String, Expr)>),
+    Other(String),
+}
+
+impl Expr {
+    /// Dotted name of an identifier or member chain, e.g. `VALUE_TYPE.surface`.
+    pub(crate) fn path(&self) -> Option<String> {
+        match self {
+            Expr::Ident(s) => Some(s.clone()),
+            Expr::Member(base, name) => Some(format!("{}.{}", base.path()?, name)),
+            _ => None,
+        }
+    }
+
+    /// Name of the function called by a (possibly `new`) call expression.
+    pub(crate) fn callee(&self) -> Option<(String, &[Expr])> {
+        match self {
+            Expr::New(inner) => inner.callee(),
+            Expr::Call(f, args) => Some((f.path()?, args)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Expr::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn field(&self, key: &str) -> Option<&Expr> {
+        match self {
+            Expr::Struct(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+
+    fn at_punct(&self, c: char) -> bool {
+        self.peek() == Some(&Tok::Punct(c))
+    }
+
+    fn at_delim(&self) -> bool {
+        match self.peek() {
+            None => true,
+            Some(Tok::Punct(c)) => matches!(c, ',' | ')' | ']' | '}' | ';' | ':'),
+            _ => false,
+        }
+    }
+
+    /// Index past the bracket that closes the one at `open`.
+    fn skip_group(&self, open: usize) -> usize {
+        let mut depth = 0usize;
+        let mut i = open;
+        while i < self.tokens.len() {
+            match self.tokens[i].tok {
+                Tok::Punct('(' | '[' | '{') => depth += 1,
+                Tok::Punct(')' | ']' | '}') => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return i + 1;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        i
+    }
+
+    fn expr(&mut self) -> Expr {
+        let start = self.pos;
+        if let Some(e) = self.unary()
+            && self.at_delim()
+        {
+            return e;
+        }
+        // Operators, ternaries, function literals...: keep the source text.
+        self.pos = start;
+        while !self.at_delim() {
+            if matches!(self.peek(), Some(Tok::Punct('(' | '[' | '{'))) {
+                self.pos = self.skip_group(self.pos);
+            } else {
+                self.pos += 1;
+            }
+        }
+        let end = self.tokens[start..self.pos]
+            .last()
+            .map(|t| t.end)
+            .unwrap_or_else(|| self.tokens.get(start).map(|t| t.start).unwrap_or(0));
+        let begin = self.tokens.get(start).map(|t| t.start).unwrap_or(end);
+        Expr::Other(self.src[begin..end.max(begin)].to_string())
+    }
+
+    fn unary(&mut self) -> Option<Expr> {
+        match self.peek()? {
+            Tok::Punct('-') => {
+                self.pos += 1;
+                Some(Expr::Neg(Box::new(self.unary()?)))
+            }
+            Tok::Ident(s) if s == "new" => {
+                self.pos += 1;
+                Some(Expr::New(Box::new(self.postfix()?)))
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    fn postfix(&mut self) -> Option<Expr> {
+        let mut e = self.primary()?;
+        loop {
+            if self.at_punct('.') {
+                self.pos += 1;
+                let Some(Tok::Ident(name)) = self.peek().cloned() else {
+                    return None;
+                };
+                self.pos += 1;
+                e = Expr::Member(Box::new(e), name);
+            } else if self.at_punct('(') {
+                let args = self.args()?;
+                e = Expr::Call(Box::new(e), args);
+            } else {
+                return Some(e);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Option<Expr> {
+        let tok = self.peek()?.clone();
+        match tok {
+            Tok::Number(n) => {
+                self.pos += 1;
+                Some(Expr::Number(n))
+            }
+            Tok::Str(s) => {
+                self.pos += 1;
+                Some(Expr::Str(s))
+            }
+            Tok::Ident(s) if s == "function" => None,
+            Tok::Ident(s) => {
+                self.pos += 1;
+                Some(Expr::Ident(s))
+            }
+            Tok::Punct('(') => {
+                self.pos += 1;
+                let e = self.expr();
+                if !self.at_punct(')') {
+                    return None;
+                }
+                self.pos += 1;
+                Some(e)
+            }
+            Tok::Punct('[') => {
+                self.pos += 1;
+                let items = self.list(']')?;
+                Some(Expr::Array(items))
+            }
+            Tok::Punct('{') => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                while !self.at_punct('}') {
+                    let key = match self.peek()? {
+                        Tok::Ident(k) | Tok::Str(k) => k.clone(),
+                        _ => return None,
+                    };
+                    self.pos += 1;
+                    if !self.at_punct(':') {
+                        return None;
+                    }
+                    self.pos += 1;
+                    fields.push((key, self.expr()));
+                    if self.at_punct(',') {
+                        self.pos += 1;
+                    } else if !self.at_punct('}') {
+                        return None;
+                    }
+                }
+                self.pos += 1;
+                Some(Expr::Struct(fields))
+            }
+            _ => None,
+        }
+    }
+
+    /// Comma-separated expressions up to `close` (already past the opener).
+    fn list(&mut self, close: char) -> Option<Vec<Expr>> {
+        let mut items = Vec::new();
+        while !self.at_punct(close) {
+            self.peek()?;
+            items.push(self.expr());
+            if self.at_punct(',') {
+                self.pos += 1;
+            } else if !self.at_punct(close) {
+                return None;
+            }
+        }
+        self.pos += 1;
+        Some(items)
+    }
+
+    fn args(&mut self) -> Option<Vec<Expr>> {
+        if !self.at_punct('(') {
+            return None;
+        }
+        self.pos += 1;
+        self.list(')')
+    }
+}
+
+/// One `newInput(slot, ctor(...))` or `newOutput(...)` call.
+#[derive(Clone, Debug)]
+pub(crate) struct PortCall {
+    /// Literal slot index; `None` when computed (dynamic inputs).
+    pub(crate) slot: Option<usize>,
+    /// `N` of a computed `index + N` slot: the port's place in a dynamic group.
+    pub(crate) offset: Option<usize>,
+    /// Name of the `nodeValue_*` constructor.
+    pub(crate) ctor: Option<String>,
+    pub(crate) args: Vec<Expr>,
+    /// Chained setters, from the constructor and from the `newInput` call.
+    pub(crate) chain: Vec<(String, Vec<Expr>)>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct NodeDef {
+    pub(crate) name: String,
+    pub(crate) inputs: Vec<PortCall>,
+    pub(crate) outputs: Vec<PortCall>,
+    /// Group size from `setDynamicInput(N, ...)`.
+    pub(crate) dynamic_len: Option<usize>,
+}
+
+/// Peels `.setX(...)` calls off a constructor expression.
+fn split_chain(mut e: Expr) -> (Expr, Vec<(String, Vec<Expr>)>) {
+    let mut chain = Vec::new();
+    loop {
+        match e {
+            Expr::Call(f, args) if matches!(*f, Expr::Member(ref base, _) if matches!(**base, Expr::Call(..) | Expr::New(_))) =>
+            {
+                let Expr::Member(base, name) = *f else {
+                    unreachable!()
+                };
+                chain.push((name, args));
+                e = *base;
+            }
+            other => {
+                chain.reverse();
+                return (other, chain);
+            }
+        }
+    }
+}
+
+fn port_call(p: &mut Parser) -> Option<PortCall> {
+    let mut args = p.args()?;
+    let mut chain = Vec::new();
+    while p.at_punct('.') {
+        let Some(Tok::Ident(name)) = p.tokens.get(p.pos + 1).map(|t| t.tok.clone()) else {
+            break;
+        };
+        p.pos += 2;
+        let Some(margs) = p.args() else {
+            break;
+        };
+        chain.push((name, margs));
+    }
+    let slot = match args.first() {
+        Some(Expr::Number(n)) => n.parse::<usize>().ok(),
+        _ => None,
+    };
+    let offset = match args.first() {
+        Some(Expr::Ident(_)) => Some(0),
+        Some(Expr::Other(text)) => text
+            .split_once('+')
+            .filter(|(base, _)| base.trim().chars().all(|c| c.is_alphanumeric() || c == '_'))
+            .and_then(|(_, n)| n.trim().parse().ok()),
+        _ => None,
+    };
+    let value = if args.len() > 1 {
+        args.swap_remove(1)
+    } else {
+        Expr::Other(String::new())
+    };
+    let (ctor, mut inner_chain) = split_chain(value);
+    inner_chain.extend(chain);
+    let (name, ctor_args) = match ctor.callee() {
+        Some((name, a)) => (Some(name), a.to_vec()),
+        None => (None, Vec::new()),
+    };
+    Some(PortCall {
+        slot,
+        offset,
+        ctor: name,
+        args: ctor_args,
+        chain: inner_chain,
+    })
+}
+
+/// Every `function Node_*(...) [: Parent(...)] constructor { ... }` in `src`
+/// with the port calls found in its body.
+pub(crate) fn node_definitions(src: &str) -> Vec<NodeDef> {
+    let tokens = tokenize(src);
+    let mut p = Parser {
+        src,
+        tokens: &tokens,
+        pos: 0,
+    };
+    let ident = |i: usize| match tokens.get(i).map(|t| &t.tok) {
+        Some(Tok::Ident(s)) => Some(s.as_str()),
+        _ => None,
+    };
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let name = match (ident(i), ident(i + 1)) {
+            (Some("function"), Some(name)) if name.starts_with("Node_") => name.to_string(),
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let mut j = i + 2;
+        if tokens.get(j).map(|t| &t.tok) != Some(&Tok::Punct('(')) {
+            i += 1;
+            continue;
+        }
+        j = p.skip_group(j);
+        if tokens.get(j).map(|t| &t.tok) == Some(&Tok::Punct(':')) {
+            j += 2;
+            if tokens.get(j).map(|t| &t.tok) == Some(&Tok::Punct('(')) {
+                j = p.skip_group(j);
+            }
+        }
+        if ident(j) != Some("constructor")
+            || tokens.get(j + 1).map(|t| &t.tok) != Some(&Tok::Punct('{'))
+        {
+            i += 1;
+            continue;
+        }
+        let body_end = p.skip_group(j + 1);
+        let mut def = NodeDef {
+            name,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            dynamic_len: None,
+        };
+        let mut k = j + 2;
+        while k < body_end {
+            let kind = ident(k);
+            if kind == Some("setDynamicInput")
+                && let Some(Tok::Number(n)) = tokens.get(k + 2).map(|t| &t.tok)
+                && tokens.get(k + 1).map(|t| &t.tok) == Some(&Tok::Punct('('))
+            {
+                def.dynamic_len = n.parse().ok();
+            }
+            let is_port = matches!(kind, Some("newInput" | "newOutput"))
+                && tokens.get(k + 1).map(|t| &t.tok) == Some(&Tok::Punct('('))
+                && (k == 0 || tokens[k - 1].tok != Tok::Punct('.'));
+            if !is_port {
+                k += 1;
+                continue;
+            }
+            p.pos = k + 1;
+            match port_call(&mut p) {
+                Some(call) => {
+                    if kind == Some("newInput") {
+                        def.inputs.push(call);
+                    } else {
+                        def.outputs.push(call);
+                    }
+                    k = p.pos.max(k + 1);
+                }
+                None => k += 1,
+            }
+        }
+        out.push(def);
+        i = body_end;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single(src: &str) -> NodeDef {
+        let mut defs = node_definitions(src);
+        assert_eq!(defs.len(), 1, "expected one node in {:?}", defs);
+        defs.remove(0)
+    }
+
+    fn chain_names(call: &PortCall) -> Vec<&str> {
+        call.chain.iter().map(|(n, _)| n.as_str()).collect()
+    }
+
+    #[test]
+    fn multi_line_call_with_nested_parentheses() {
+        let def = single(
+            r#"function Node_A(_x, _y, _group = noone) : Node_Processor(_x, _y, _group) constructor {
+                newInput(1,
+                    nodeValue_Int(
+                        "Size",
+                        self,
+                        max(1, min(3, 4))
+                    )
+                );
+            }"#,
+        );
+        assert_eq!(def.name, "Node_A");
+        let call = &def.inputs[0];
+        assert_eq!(call.slot, Some(1));
+        assert_eq!(call.ctor.as_deref(), Some("nodeValue_Int"));
+        assert_eq!(call.args.len(), 3);
+        assert_eq!(call.args[0], Expr::Str("Size".to_string()));
+        assert!(matches!(&call.args[2], Expr::Call(..)));
+    }
+
+    #[test]
+    fn comments_are_ignored() {
+        let def = single(
+            r#"function Node_A() constructor {
+                // newInput(5, nodeValue_Float("Commented", self, 0));
+                /* newInput(6, nodeValue_Float("Block", self, 0));
+                   still a comment ) ( */
+                newInput(0, nodeValue_Float("Real", /* inline */ self, 1)); // trailing
+            }"#,
+        );
+        assert_eq!(def.inputs.len(), 1);
+        assert_eq!(def.inputs[0].args[0].as_str(), Some("Real"));
+        assert_eq!(def.inputs[0].args[2], Expr::Number("1".to_string()));
+    }
+
+    #[test]
+    fn strings_with_commas_quotes_and_parentheses() {
+        let def = single(
+            r#"function Node_A() constructor {
+                newInput(0, nodeValue_Text("a, b (c", self, "say \"hi\", ok)"));
+                newInput(1, nodeValue_Text('single, quoted', self, @"verbatim \n
+line, two"));
+            }"#,
+        );
+        let a = &def.inputs[0];
+        assert_eq!(a.args.len(), 3);
+        assert_eq!(a.args[0].as_str(), Some("a, b (c"));
+        assert_eq!(a.args[2].as_str(), Some("say \"hi\", ok)"));
+        let b = &def.inputs[1];
+        assert_eq!(b.args[0].as_str(), Some("single, quoted"));
+        assert_eq!(b.args[2].as_str(), Some("verbatim \\n\nline, two"));
+    }
+
+    #[test]
+    fn every_constructor_in_a_file() {
+        let defs = node_definitions(
+            r#"function Node_create_A(_x, _y, _group = noone) {
+                var n = new Node_A(_x, _y, _group);
+                return n;
+            }
+            function Node_A(_x, _y, _group = noone) : Node(_x, _y, _group) constructor {
+                newInput(0, nodeValue_Surface("In", self));
+            }
+            function helper() { newInput(9, nodeValue_Float("Stray", self, 0)); }
+            function Node_B(_x, _y, _group = noone) : Node_A(_x, _y, _group) constructor {
+                newOutput(0, nodeValue_Output("Out", self, VALUE_TYPE.surface, noone));
+            }"#,
+        );
+        let names: Vec<&str> = defs.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["Node_A", "Node_B"]);
+        assert_eq!(defs[0].inputs.len(), 1);
+        assert_eq!(defs[1].outputs.len(), 1);
+        assert_eq!(
+            defs[1].outputs[0].args[2].path().as_deref(),
+            Some("VALUE_TYPE.surface")
+        );
+    }
+
+    #[test]
+    fn chained_setters_inside_and_outside_the_call() {
+        let def = single(
+            r#"function Node_A() constructor {
+                newInput(1, nodeValue_Int("Size", self, 3)
+                    .setDisplay(VALUE_DISPLAY.slider, { range: [1, 32, 0.1] }))
+                    .setTooltip("Blur, in pixels")
+                    .setUnitRef(function(index) { return getDimension(index); }, VALUE_UNIT.reference);
+                newInput(2, nodeValue_Bool("Next", self, false));
+            }"#,
+        );
+        assert_eq!(def.inputs.len(), 2);
+        let call = &def.inputs[0];
+        assert_eq!(
+            chain_names(call),
+            ["setDisplay", "setTooltip", "setUnitRef"]
+        );
+        let range = call.chain[0].1[1].field("range");
+        assert!(matches!(range, Some(Expr::Array(items)) if items.len() == 3));
+        assert!(matches!(&call.chain[2].1[0], Expr::Other(s) if s.starts_with("function")));
+        assert_eq!(def.inputs[1].slot, Some(2));
+    }
+
+    #[test]
+    fn preprocessor_lines_and_color_literals() {
+        let def = single(
+            "#region setup\n#macro LIMIT \\\n  newInput(7, nodeValue_Float(\"Macro\", self, 0))\n\
+             function Node_A() constructor {\n\
+             #region inputs\n\
+             newInput(0, nodeValue_Color(\"Tint\", self, #FF8000));\n\
+             newInput(1, nodeValue_Int(\"Mask\", self, $FF));\n\
+             #endregion\n\
+             }",
+        );
+        assert_eq!(def.inputs.len(), 2);
+        assert_eq!(def.inputs[0].args[2], Expr::Number("#FF8000".to_string()));
+        assert_eq!(def.inputs[1].args[2], Expr::Number("$FF".to_string()));
+    }
+
+    #[test]
+    fn computed_slots_and_expressions() {
+        let def = single(
+            r#"function Node_A() constructor {
+                setDynamicInput(2, true, VALUE_TYPE.surface);
+                static createNewInput = function(index = array_length(inputs)) {
+                    newInput(index + 0, nodeValue_Surface("Layer", self));
+                    return inputs[index];
+                }
+                newInput(0, new nodeValue_Float("Scale", self, 1 / 3));
+                newInput(1, nodeValue_Vec2("Offset", self, [ -0.5, .5 ]));
+            }"#,
+        );
+        assert_eq!(def.inputs.len(), 3);
+        assert_eq!(def.inputs[0].slot, None);
+        assert_eq!(def.inputs[0].offset, Some(0));
+        assert_eq!(def.inputs[1].offset, None);
+        assert_eq!(def.dynamic_len, Some(2));
+        assert_eq!(def.inputs[1].ctor.as_deref(), Some("nodeValue_Float"));
+        assert_eq!(def.inputs[1].args[2], Expr::Other("1 / 3".to_string()));
+        assert_eq!(
+            def.inputs[2].args[2],
+            Expr::Array(vec![
+                Expr::Neg(Box::new(Expr::Number("0.5".to_string()))),
+                Expr::Number(".5".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn enum_items_built_with_new() {
+        let def = single(
+            r#"function Node_A() constructor {
+                newInput(0, nodeValue_Enum_Button("Mode", self, 1, [ new scrollItem("Linear", s_a), new scrollItem("Radial", s_b, 1) ]));
+            }"#,
+        );
+        let Expr::Array(items) = &def.inputs[0].args[3] else {
+            panic!("expected array, got {:?}", def.inputs[0].args[3]);
+        };
+        let labels: Vec<&str> = items
+            .iter()
+            .filter_map(|i| i.callee().and_then(|(_, a)| a.first()?.as_str()))
+            .collect();
+        assert_eq!(labels, ["Linear", "Radial"]);
+    }
+}
@@ -1,12 +1,18 @@
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 use anyhow::{Result, anyhow};
 use clap::ValueEnum;
 use serde_json::{Map, Value, json};
 
+use crate::cytoscape::render_cytoscape;
+use crate::graphml::render_graphml;
 use crate::ids::{short_for_id, short_id};
+use crate::notes::{Note, notes_in_pxc};
+use crate::ops::{input_expression, resolve_node_id};
 use crate::pxc::{PxcFile, read_pxc};
-use crate::registry::{Registry, load_registry};
+use crate::registry::{Registry, RegistryPort, load_registry};
+use crate::svg::render_svg;
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
 pub enum GraphFormat {
@@ -14,6 +20,9 @@ pub enum GraphFormat {
     Dot,
     Json,
     Summary,
+    Svg,
+    Graphml,
+    Cytoscape,
 }
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
@@ -23,37 +32,198 @@ pub enum GraphMode {
     Full,
 }
 
-pub(crate) fn cmd_graph(
-    path: &Path,
-    format: GraphFormat,
-    mode: GraphMode,
-    pretty: bool,
-    include_id_map: bool,
-    include_ids: bool,
-    include_pos: bool,
-    json_inputs: bool,
-    full_ids: bool,
-    include_edges: bool,
-    registry_path: Option<&Path>,
-) -> Result<()> {
-    let pxc = read_pxc(path)?;
-    let nodes = pxc
-        .json
-        .get("nodes")
-        .and_then(|v| v.as_array())
-        .ok_or_else(|| anyhow!("no nodes array found"))?;
+pub(crate) struct GraphData {
+    pub(crate) node_map: Map<String, Value>,
+    pub(crate) id_map: Map<String, Value>,
+    pub(crate) edges: Vec<Value>,
+    pub(crate) outputs_used: HashMap<String, HashSet<usize>>,
+    pub(crate) parents: HashMap<String, String>,
+    pub(crate) full_ids: bool,
+}
+
+impl GraphData {
+    pub(crate) fn key(&self, id: &str) -> String {
+        if self.full_ids {
+            id.to_string()
+        } else {
+            short_for_id(&self.id_map, id).unwrap_or_else(|| id.to_string())
+        }
+    }
+
+    /// Nodes in scope whose parent group is not exported, in output order.
+    pub(crate) fn roots(&self) -> Vec<String> {
+        self.node_map
+            .keys()
+            .filter(|id| self.parent(id).is_none())
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn children(&self, group_id: &str) -> Vec<String> {
+        self.node_map
+            .keys()
+            .filter(|id| self.parent(id) == Some(group_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Parent group of `id`, only if that group is part of the export.
+    pub(crate) fn parent(&self, id: &str) -> Option<&str> {
+        self.parents
+            .get(id)
+            .map(|p| p.as_str())
+            .filter(|p| self.node_map.contains_key(*p))
+    }
+
+    pub(crate) fn is_group(&self, id: &str) -> bool {
+        self.parents
+            .values()
+            .any(|p| p == id && self.node_map.contains_key(p.as_str()))
+    }
+
+    pub(crate) fn hierarchy(&self, ids: &[String]) -> Value {
+        let mut out = Vec::new();
+        for id in ids {
+            let mut entry = Map::new();
+            entry.insert("k".to_string(), Value::String(self.key(id)));
+            let children = self.children(id);
+            if !children.is_empty() {
+                entry.insert("ch".to_string(), self.hierarchy(&children));
+            }
+            out.push(Value::Object(entry));
+        }
+        Value::Array(out)
+    }
+}
+
+/// Bypass connections are stored with `from_index` offset by this amount.
+/// Pixel Composer saves a link from a junction's bypass output (`bypass_junc`)
+/// with 1000 added to the index and strips it again when it reconnects on load
+/// (`NodeValue` in `scripts/node_value/node_value.gml`); see "Connections" in
+/// the README's file format notes.
+pub(crate) const BYPASS_INDEX_OFFSET: i64 = 1000;
+
+/// One connection with the registry ports on both ends resolved.
+pub(crate) struct EdgeDetail<'a> {
+    pub(crate) from: &'a str,
+    pub(crate) to: &'a str,
+    pub(crate) from_slot: i64,
+    pub(crate) to_slot: usize,
+    pub(crate) tag: Option<i64>,
+    pub(crate) bypass: bool,
+    pub(crate) from_port: Option<&'a RegistryPort>,
+    pub(crate) to_port: Option<&'a RegistryPort>,
+}
+
+pub(crate) fn edge_details<'a>(
+    graph: &'a GraphData,
+    registry: Option<&'a Registry>,
+) -> Vec<EdgeDetail<'a>> {
+    let type_of = |id: &str| {
+        graph
+            .node_map
+            .get(id)
+            .and_then(|n| n.get("type"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+    };
+    let mut out = Vec::new();
+    for edge in graph.edges.iter() {
+        let from = edge.get("f").and_then(|v| v.as_str()).unwrap_or("");
+        let to = edge.get("t").and_then(|v| v.as_str()).unwrap_or("");
+        let from_slot = edge.get("fo").and_then(|v| v.as_i64()).unwrap_or(-1);
+        let to_slot = edge.get("ti").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+        let bypass = from_slot >= BYPASS_INDEX_OFFSET;
+        let out_slot = if bypass {
+            from_slot - BYPASS_INDEX_OFFSET
+        } else {
+            from_slot
+        };
+        let from_port = usize::try_from(out_slot)
+            .ok()
+            .and_then(|slot| registry?.output_port(type_of(from), slot));
+        let to_port = registry.and_then(|r| r.input_port(type_of(to), to_slot));
+        out.push(EdgeDetail {
+            from,
+            to,
+            from_slot,
+            to_slot,
+            tag: edge.get("tg").and_then(|v| v.as_i64()),
+            bypass,
+            from_port,
+            to_port,
+        });
+    }
+    out
+}
+
+/// Map of node id -> id of the group node containing it.
+fn node_parents(nodes: &[Value]) -> HashMap<String, String> {
+    let ids: HashSet<&str> = nodes
+        .iter()
+        .filter_map(|n| n.get("id").and_then(|v| v.as_str()))
+        .collect();
+    let mut parents = HashMap::new();
+    for node in nodes {
+        let id = match node.get("id").and_then(|v| v.as_str()) {
+            Some(v) => v,
+            None => continue,
+        };
+        if let Some(group) = node.get("group").and_then(|v| v.as_str())
+            && group != id
+            && ids.contains(group)
+        {
+            parents.insert(id.to_string(), group.to_string());
+        }
+    }
+    parents
+}
 
-    let registry = load_registry(registry_path)?;
+fn is_descendant(parents: &HashMap<String, String>, id: &str, group_id: &str) -> bool {
+    let mut seen = HashSet::new();
+    let mut cur = id;
+    while let Some(p) = parents.get(cur) {
+        if p == group_id {
+            return true;
+        }
+        if !seen.insert(p.as_str()) {
+            break;
+        }
+        cur = p;
+    }
+    false
+}
+
+pub(crate) fn collect_graph(
+    nodes: &[Value],
+    full_ids: bool,
+    json_inputs: bool,
+    group: Option<&str>,
+) -> Result<GraphData> {
+    let parents = node_parents(nodes);
+    let scope = match group {
+        Some(g) => {
+            Some(resolve_node_id(g, nodes).ok_or_else(|| anyhow!("group node not found: {}", g))?)
+        }
+        None => None,
+    };
+    let in_scope = |id: &str| match &scope {
+        Some(g) => is_descendant(&parents, id, g),
+        None => true,
+    };
 
     let mut node_map = Map::new();
     let mut id_map: Map<String, Value> = Map::new();
     let mut id_list: Vec<String> = Vec::new();
     for node in nodes {
         if let Some(id) = node.get("id").and_then(|v| v.as_str()) {
+            id_list.push(id.to_string());
+            if !in_scope(id) {
+                continue;
+            }
             let name = node.get("name").and_then(|v| v.as_str()).unwrap_or("");
             let typ = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
             let label = format!("{}\\n{}", name, typ).trim().to_string();
-            id_list.push(id.to_string());
             node_map.insert(
                 id.to_string(),
                 json!({ "id": id, "name": name, "type": typ, "label": label }),
@@ -63,14 +233,16 @@ pub(crate) fn cmd_graph(
 
     if !full_ids {
         for (i, id) in id_list.iter().enumerate() {
+            if !in_scope(id) {
+                continue;
+            }
             let short = short_id(i);
             id_map.insert(short, Value::String(id.clone()));
         }
     }
 
     let mut edges = Vec::new();
-    let mut outputs_used: std::collections::HashMap<String, std::collections::HashSet<usize>> =
-        std::collections::HashMap::new();
+    let mut outputs_used: HashMap<String, HashSet<usize>> = HashMap::new();
     for node in nodes {
         let to_id = match node.get("id").and_then(|v| v.as_str()) {
             Some(v) => v,
@@ -90,6 +262,9 @@ pub(crate) fn cmd_graph(
                     .entry(from_node.to_string())
                     .or_default()
                     .insert(from_index as usize);
+                if !in_scope(to_id) || !in_scope(from_node) {
+                    continue;
+                }
                 let from_tag = input
                     .get("from_tag")
                     .and_then(|v| v.as_i64().or_else(|| v.as_f64().map(|f| f as i64)));
@@ -110,74 +285,54 @@ pub(crate) fn cmd_graph(
         }
     }
 
+    Ok(GraphData {
+        node_map,
+        id_map,
+        edges,
+        outputs_used,
+        parents,
+        full_ids,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn cmd_graph(
+    path: &Path,
+    format: GraphFormat,
+    mode: GraphMode,
+    pretty: bool,
+    include_id_map: bool,
+    include_ids: bool,
+    include_pos: bool,
+    json_inputs: bool,
+    full_ids: bool,
+    include_edges: bool,
+    registry_path: Option<&Path>,
+    group: Option<&str>,
+    include_notes: bool,
+) -> Result<()> {
+    let pxc = read_pxc(path)?;
+    // Notes live on the top-level graph.
+    let notes = if include_notes && group.is_none() {
+        notes_in_pxc(&pxc)
+    } else {
+        Vec::new()
+    };
+
     match format {
         GraphFormat::Json => {
-            let mut out_nodes = Map::new();
-            for (id, node) in node_map.iter() {
-                let key = if full_ids {
-                    id.clone()
-                } else {
-                    short_for_id(&id_map, id).unwrap_or_else(|| id.clone())
-                };
-                out_nodes.insert(
-                    key,
-                    build_node_dump(
-                        node,
-                        nodes,
-                        id,
-                        mode,
-                        include_ids,
-                        include_pos,
-                        json_inputs,
-                        full_ids,
-                        &id_map,
-                        registry.as_ref(),
-                        &outputs_used,
-                    ),
-                );
-            }
-            let mut out_edges = Vec::new();
-            for edge in edges.iter() {
-                let from = edge.get("f").and_then(|v| v.as_str()).unwrap_or("");
-                let to = edge.get("t").and_then(|v| v.as_str()).unwrap_or("");
-                let from_key = if full_ids {
-                    from.to_string()
-                } else {
-                    short_for_id(&id_map, from).unwrap_or_else(|| from.to_string())
-                };
-                let to_key = if full_ids {
-                    to.to_string()
-                } else {
-                    short_for_id(&id_map, to).unwrap_or_else(|| to.to_string())
-                };
-                let mut out_edge = edge.clone();
-                out_edge["f"] = Value::String(from_key);
-                out_edge["t"] = Value::String(to_key);
-                out_edges.push(out_edge);
-            }
-            let mut out = Map::new();
-            if include_id_map {
-                if full_ids {
-                    out.insert("m".to_string(), Value::Null);
-                } else {
-                    out.insert("m".to_string(), Value::Object(id_map.clone()));
-                }
-            }
-            out.insert(
-                "l".to_string(),
-                json!({
-                    "n":"nodes","e":"edges","m":"id_map","l":"legend",
-                    "node.n":"name","node.t":"type","node.i":"inputs","node.o":"outputs","node.p":"pos","node.id":"full id",
-                    "io.s":"slot","io.n":"name","io.t":"type","io.v":"value","io.a":"attri","io.c":"connection",
-                    "conn.f":"from node","conn.fo":"from output","conn.tg":"tag",
-                    "anim.an":"animated","anim.k":"key count","anim.ad":"animation data (full)"
-                }),
-            );
-            out.insert("n".to_string(), Value::Object(out_nodes));
-            if include_edges {
-                out.insert("e".to_string(), Value::Array(out_edges));
-            }
-            let out = Value::Object(out);
+            let out = graph_json_from_pxc(
+                &pxc,
+                mode,
+                include_id_map,
+                include_ids,
+                include_pos,
+                json_inputs,
+                full_ids,
+                include_edges,
+                registry_path,
+                group,
+            )?;
             if pretty {
                 println!("{}", serde_json::to_string_pretty(&out)?);
             } else {
@@ -185,79 +340,70 @@ pub(crate) fn cmd_graph(
             }
         }
         GraphFormat::Summary => {
+            let nodes = pxc
+                .json
+                .get("nodes")
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| anyhow!("no nodes array found"))?;
+            let graph = collect_graph(nodes, full_ids, json_inputs, group)?;
             let mut summary = Vec::new();
             for node in nodes {
                 let id = match node.get("id").and_then(|v| v.as_str()) {
                     Some(v) => v,
                     None => continue,
                 };
-                let short = if full_ids {
-                    id.to_string()
-                } else {
-                    short_for_id(&id_map, id).unwrap_or_else(|| id.to_string())
-                };
+                if !graph.node_map.contains_key(id) {
+                    continue;
+                }
+                let short = graph.key(id);
                 let name = node.get("name").and_then(|v| v.as_str()).unwrap_or("");
                 let typ = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
                 let x = node.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0);
                 let y = node.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0);
-                summary.push(format!("{} {} ({}) @({}, {})", short, name, typ, x, y));
+                let mut line = format!("{} {} ({}) @({}, {})", short, name, typ, x, y);
+                if let Some(parent) = graph.parent(id) {
+                    line.push_str(&format!(" in {}", graph.key(parent)));
+                }
+                summary.push(line);
             }
             for line in summary {
                 println!("{}", line);
             }
             println!("\nConnections:");
-            for edge in edges {
+            for edge in graph.edges.iter() {
                 let from = edge.get("f").and_then(|v| v.as_str()).unwrap_or("");
                 let to = edge.get("t").and_then(|v| v.as_str()).unwrap_or("");
-                let from_id = if full_ids {
-                    from.to_string()
-                } else {
-                    short_for_id(&id_map, from).unwrap_or_else(|| from.to_string())
-                };
-                let to_id = if full_ids {
-                    to.to_string()
-                } else {
-                    short_for_id(&id_map, to).unwrap_or_else(|| to.to_string())
-                };
                 let from_index = edge.get("fo").and_then(|v| v.as_i64()).unwrap_or(-1);
                 let to_input = edge.get("ti").and_then(|v| v.as_u64()).unwrap_or(0);
                 println!(
                     "{}: out{} -> {}: in{}",
-                    from_id, from_index, to_id, to_input
+                    graph.key(from),
+                    from_index,
+                    graph.key(to),
+                    to_input
                 );
             }
         }
         GraphFormat::Mermaid => {
+            let nodes = pxc
+                .json
+                .get("nodes")
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| anyhow!("no nodes array found"))?;
+            let graph = collect_graph(nodes, full_ids, json_inputs, group)?;
             if !full_ids {
                 println!("%% id_map (short -> full)");
-                for (short, full) in id_map.iter() {
+                for (short, full) in graph.id_map.iter() {
                     let full = full.as_str().unwrap_or("");
                     println!("%% {} = {}", short, full);
                 }
             }
             println!("graph TD");
-            for (id, node) in node_map.iter() {
-                let node_id = if full_ids {
-                    id.clone()
-                } else {
-                    short_for_id(&id_map, id).unwrap_or_else(|| id.clone())
-                };
-                let label = node.get("label").and_then(|v| v.as_str()).unwrap_or("");
-                println!("  {}[\"{}\"]", mermaid_id(&node_id), escape_label(label));
-            }
-            for edge in edges {
+            print_mermaid_nodes(&graph, &graph.roots(), 1);
+            print_mermaid_notes(&notes);
+            for edge in graph.edges.iter() {
                 let from = edge.get("f").and_then(|v| v.as_str()).unwrap_or("");
                 let to = edge.get("t").and_then(|v| v.as_str()).unwrap_or("");
-                let from_id = if full_ids {
-                    from.to_string()
-                } else {
-                    short_for_id(&id_map, from).unwrap_or_else(|| from.to_string())
-                };
-                let to_id = if full_ids {
-                    to.to_string()
-                } else {
-                    short_for_id(&id_map, to).unwrap_or_else(|| to.to_string())
-                };
                 let from_index = edge.get("fo").and_then(|v| v.as_i64()).unwrap_or(-1);
                 let to_input = edge.get("ti").and_then(|v| v.as_u64()).unwrap_or(0);
                 let mut label = format!("out{} -> in{}", from_index, to_input);
@@ -266,48 +412,33 @@ pub(crate) fn cmd_graph(
                 }
                 println!(
                     "  {} -->|\"{}\"| {}",
-                    mermaid_id(&from_id),
-                    escape_label(&label),
-                    mermaid_id(&to_id)
+                    mermaid_id(&graph.key(from)),
+                    escape_mermaid(&label),
+                    mermaid_id(&graph.key(to))
                 );
             }
         }
         GraphFormat::Dot => {
+            let nodes = pxc
+                .json
+                .get("nodes")
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| anyhow!("no nodes array found"))?;
+            let graph = collect_graph(nodes, full_ids, json_inputs, group)?;
             println!("digraph pxc {{");
             println!("  rankdir=LR;");
             if !full_ids {
                 println!("  // id_map (short -> full)");
-                for (short, full) in id_map.iter() {
+                for (short, full) in graph.id_map.iter() {
                     let full = full.as_str().unwrap_or("");
                     println!("  // {} = {}", short, full);
                 }
             }
-            for (id, node) in node_map.iter() {
-                let node_id = if full_ids {
-                    id.clone()
-                } else {
-                    short_for_id(&id_map, id).unwrap_or_else(|| id.clone())
-                };
-                let label = node.get("label").and_then(|v| v.as_str()).unwrap_or("");
-                println!(
-                    "  \"{}\" [label=\"{}\"];\n",
-                    escape_dot(&node_id),
-                    escape_label(label)
-                );
-            }
-            for edge in edges {
+            print_dot_nodes(&graph, &graph.roots(), 1);
+            print_dot_notes(&notes);
+            for edge in graph.edges.iter() {
                 let from = edge.get("f").and_then(|v| v.as_str()).unwrap_or("");
                 let to = edge.get("t").and_then(|v| v.as_str()).unwrap_or("");
-                let from_id = if full_ids {
-                    from.to_string()
-                } else {
-                    short_for_id(&id_map, from).unwrap_or_else(|| from.to_string())
-                };
-                let to_id = if full_ids {
-                    to.to_string()
-                } else {
-                    short_for_id(&id_map, to).unwrap_or_else(|| to.to_string())
-                };
                 let from_index = edge.get("fo").and_then(|v| v.as_i64()).unwrap_or(-1);
                 let to_input = edge.get("ti").and_then(|v| v.as_u64()).unwrap_or(0);
                 let mut label = format!("out{} -> in{}", from_index, to_input);
@@ -316,18 +447,139 @@ pub(crate) fn cmd_graph(
                 }
                 println!(
                     "  \"{}\" -> \"{}\" [label=\"{}\"];\n",
-                    escape_dot(&from_id),
-                    escape_dot(&to_id),
+                    escape_dot(&graph.key(from)),
+                    escape_dot(&graph.key(to)),
                     escape_label(&label)
                 );
             }
             println!("}}");
         }
+        GraphFormat::Svg => {
+            let nodes = pxc
+                .json
+                .get("nodes")
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| anyhow!("no nodes array found"))?;
+            let registry = load_registry(registry_path, pxc.save_version())?;
+            let graph = collect_graph(nodes, full_ids, json_inputs, group)?;
+            print!("{}", render_svg(&graph, nodes, &notes, registry.as_ref()));
+        }
+        GraphFormat::Graphml => {
+            let nodes = pxc
+                .json
+                .get("nodes")
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| anyhow!("no nodes array found"))?;
+            let registry = load_registry(registry_path, pxc.save_version())?;
+            let graph = collect_graph(nodes, full_ids, json_inputs, group)?;
+            print!("{}", render_graphml(&graph, nodes, registry.as_ref()));
+        }
+        GraphFormat::Cytoscape => {
+            let nodes = pxc
+                .json
+                .get("nodes")
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| anyhow!("no nodes array found"))?;
+            let registry = load_registry(registry_path, pxc.save_version())?;
+            let graph = collect_graph(nodes, full_ids, json_inputs, group)?;
+            let out = render_cytoscape(&graph, nodes, registry.as_ref());
+            if pretty {
+                println!("{}", serde_json::to_string_pretty(&out)?);
+            } else {
+                println!("{}", serde_json::to_string(&out)?);
+            }
+        }
     }
 
     Ok(())
 }
 
+fn print_mermaid_nodes(graph: &GraphData, ids: &[String], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for id in ids {
+        let node = &graph.node_map[id.as_str()];
+        let node_id = graph.key(id);
+        let label = node.get("label").and_then(|v| v.as_str()).unwrap_or("");
+        if graph.is_group(id) {
+            let name = node.get("name").and_then(|v| v.as_str()).unwrap_or("");
+            println!(
+                "{}subgraph grp_{}[\"{}\"]",
+                indent,
+                mermaid_id(&node_id),
+                escape_mermaid(name)
+            );
+            println!(
+                "{}  {}[[\"{}\"]]",
+                indent,
+                mermaid_id(&node_id),
+                escape_mermaid(label)
+            );
+            print_mermaid_nodes(graph, &graph.children(id), depth + 1);
+            println!("{}end", indent);
+        } else {
+            println!(
+                "{}{}[\"{}\"]",
+                indent,
+                mermaid_id(&node_id),
+                escape_mermaid(label)
+            );
+        }
+    }
+}
+
+fn print_mermaid_notes(notes: &[Note]) {
+    for note in notes {
+        let text = escape_mermaid(&note.text).replace('\n', "<br/>");
+        println!("  note_{}>\"{}\"]", note.index, text);
+        println!(
+            "  style note_{} fill:{},color:#000",
+            note.index,
+            note.color_hex()
+        );
+    }
+}
+
+fn print_dot_notes(notes: &[Note]) {
+    for note in notes {
+        println!(
+            "  \"note_{}\" [label=\"{}\", shape=note, style=filled, fillcolor=\"{}\"];\n",
+            note.index,
+            escape_label(&note.text).replace('\n', "\\n"),
+            note.color_hex()
+        );
+    }
+}
+
+fn print_dot_nodes(graph: &GraphData, ids: &[String], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for id in ids {
+        let node = &graph.node_map[id.as_str()];
+        let node_id = graph.key(id);
+        let label = node.get("label").and_then(|v| v.as_str()).unwrap_or("");
+        if graph.is_group(id) {
+            let name = node.get("name").and_then(|v| v.as_str()).unwrap_or("");
+            println!("{}subgraph \"cluster_{}\" {{", indent, escape_dot(&node_id));
+            println!("{}  label=\"{}\";", indent, escape_label(name));
+            println!(
+                "{}  \"{}\" [label=\"{}\", shape=box3d];\n",
+                indent,
+                escape_dot(&node_id),
+                escape_label(label)
+            );
+            print_dot_nodes(graph, &graph.children(id), depth + 1);
+            println!("{}}}", indent);
+        } else {
+            println!(
+                "{}\"{}\" [label=\"{}\"];\n",
+                indent,
+                escape_dot(&node_id),
+                escape_label(label)
+            );
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn graph_json_from_pxc(
     pxc: &PxcFile,
     mode: GraphMode,
@@ -338,6 +590,7 @@ pub(crate) fn graph_json_from_pxc(
     full_ids: bool,
     include_edges: bool,
     registry_path: Option<&Path>,
+    group: Option<&str>,
 ) -> Result<Value> {
     let nodes = pxc
         .json
@@ -345,115 +598,37 @@ pub(crate) fn graph_json_from_pxc(
         .and_then(|v| v.as_array())
         .ok_or_else(|| anyhow!("no nodes array found"))?;
 
-    let registry = load_registry(registry_path)?;
-
-    let mut node_map = Map::new();
-    let mut id_map: Map<String, Value> = Map::new();
-    let mut id_list: Vec<String> = Vec::new();
-    for node in nodes {
-        if let Some(id) = node.get("id").and_then(|v| v.as_str()) {
-            let name = node.get("name").and_then(|v| v.as_str()).unwrap_or("");
-            let typ = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
-            let label = format!("{}\n{}", name, typ).trim().to_string();
-            id_list.push(id.to_string());
-            node_map.insert(
-                id.to_string(),
-                json!({ "id": id, "name": name, "type": typ, "label": label }),
-            );
-        }
-    }
-
-    if !full_ids {
-        for (i, id) in id_list.iter().enumerate() {
-            let short = short_id(i);
-            id_map.insert(short, Value::String(id.clone()));
-        }
-    }
-
-    let mut edges = Vec::new();
-    let mut outputs_used: std::collections::HashMap<String, std::collections::HashSet<usize>> =
-        std::collections::HashMap::new();
-    for node in nodes {
-        let to_id = match node.get("id").and_then(|v| v.as_str()) {
-            Some(v) => v,
-            None => continue,
-        };
-        let inputs = match node.get("inputs").and_then(|v| v.as_array()) {
-            Some(v) => v,
-            None => continue,
-        };
-        for (idx, input) in inputs.iter().enumerate() {
-            let from_node = input.get("from_node").and_then(|v| v.as_str());
-            let from_index = input
-                .get("from_index")
-                .and_then(|v| v.as_i64().or_else(|| v.as_f64().map(|f| f as i64)));
-            if let (Some(from_node), Some(from_index)) = (from_node, from_index) {
-                outputs_used
-                    .entry(from_node.to_string())
-                    .or_default()
-                    .insert(from_index as usize);
-                let from_tag = input
-                    .get("from_tag")
-                    .and_then(|v| v.as_i64().or_else(|| v.as_f64().map(|f| f as i64)));
-                let mut edge = json!({
-                    "f": from_node,
-                    "fo": from_index,
-                    "t": to_id,
-                    "ti": idx,
-                });
-                if let Some(tag) = from_tag {
-                    edge["tg"] = Value::Number(tag.into());
-                }
-                if json_inputs {
-                    edge["input"] = input.clone();
-                }
-                edges.push(edge);
-            }
-        }
-    }
+    let registry = load_registry(registry_path, pxc.save_version())?;
+    let graph = collect_graph(nodes, full_ids, json_inputs, group)?;
 
     let mut out_nodes = Map::new();
-    for (id, node) in node_map.iter() {
-        let key = if full_ids {
-            id.clone()
-        } else {
-            short_for_id(&id_map, id).unwrap_or_else(|| id.clone())
-        };
-        out_nodes.insert(
-            key,
-            build_node_dump(
-                node,
-                nodes,
-                id,
-                mode,
-                include_ids,
-                include_pos,
-                json_inputs,
-                full_ids,
-                &id_map,
-                registry.as_ref(),
-                &outputs_used,
-            ),
+    for (id, node) in graph.node_map.iter() {
+        let mut dump = build_node_dump(
+            node,
+            nodes,
+            id,
+            mode,
+            include_ids,
+            include_pos,
+            json_inputs,
+            full_ids,
+            &graph.id_map,
+            registry.as_ref(),
+            &graph.outputs_used,
         );
+        if let Some(parent) = graph.parent(id) {
+            dump["g"] = Value::String(graph.key(parent));
+        }
+        out_nodes.insert(graph.key(id), dump);
     }
 
     let mut out_edges = Vec::new();
-    for edge in edges.iter() {
+    for edge in graph.edges.iter() {
         let from = edge.get("f").and_then(|v| v.as_str()).unwrap_or("");
         let to = edge.get("t").and_then(|v| v.as_str()).unwrap_or("");
-        let from_key = if full_ids {
-            from.to_string()
-        } else {
-            short_for_id(&id_map, from).unwrap_or_else(|| from.to_string())
-        };
-        let to_key = if full_ids {
-            to.to_string()
-        } else {
-            short_for_id(&id_map, to).unwrap_or_else(|| to.to_string())
-        };
         let mut out_edge = edge.clone();
-        out_edge["f"] = Value::String(from_key);
-        out_edge["t"] = Value::String(to_key);
+        out_edge["f"] = Value::String(graph.key(from));
+        out_edge["t"] = Value::String(graph.key(to));
         out_edges.push(out_edge);
     }
 
@@ -462,26 +637,32 @@ pub(crate) fn graph_json_from_pxc(
         if full_ids {
             out.insert("m".to_string(), Value::Null);
         } else {
-            out.insert("m".to_string(), Value::Object(id_map));
+            out.insert("m".to_string(), Value::Object(graph.id_map.clone()));
         }
     }
     out.insert(
         "l".to_string(),
         json!({
-            "n":"nodes","e":"edges","m":"id_map","l":"legend",
-            "node.n":"name","node.t":"type","node.i":"inputs","node.o":"outputs","node.p":"pos","node.id":"full id",
-            "io.s":"slot","io.n":"name","io.t":"type","io.v":"value","io.a":"attri","io.c":"connection",
+            "n":"nodes","e":"edges","m":"id_map","l":"legend","h":"group hierarchy",
+            "node.n":"name","node.t":"type","node.i":"inputs","node.o":"outputs","node.p":"pos","node.id":"full id","node.g":"parent group",
+            "io.s":"slot","io.n":"name","io.t":"type","io.v":"value","io.a":"attri","io.c":"connection","io.x":"expression",
+            "io.d":"registry default","io.e":"enum options","io.r":"slider range [min,max,step]","io.hd":"hidden by default",
             "conn.f":"from node","conn.fo":"from output","conn.tg":"tag",
-            "anim.an":"animated","anim.k":"key count","anim.ad":"animation data (full)"
+            "anim.an":"animated","anim.k":"key count","anim.ad":"animation data (full)",
+            "tree.k":"node key","tree.ch":"children"
         }),
     );
     out.insert("n".to_string(), Value::Object(out_nodes));
     if include_edges {
         out.insert("e".to_string(), Value::Array(out_edges));
     }
+    if graph.node_map.keys().any(|id| graph.parent(id).is_some()) {
+        out.insert("h".to_string(), graph.hierarchy(&graph.roots()));
+    }
     Ok(Value::Object(out))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn graph_json(
     path: &Path,
     mode: GraphMode,
@@ -492,6 +673,7 @@ pub fn graph_json(
     full_ids: bool,
     include_edges: bool,
     registry_path: Option<&Path>,
+    group: Option<&str>,
 ) -> Result<Value> {
     let pxc = read_pxc(path)?;
     graph_json_from_pxc(
@@ -504,13 +686,21 @@ pub fn graph_json(
         full_ids,
         include_edges,
         registry_path,
+        group,
     )
 }
 
+/// DOT quoted-string escaping for labels.
 fn escape_label(s: &str) -> String {
     s.replace('\\', "\\\\").replace('\"', "\\\"")
 }
 
+/// Mermaid has no backslash escapes inside quoted text; `"` is written as the
+/// `#quot;` entity instead.
+fn escape_mermaid(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
+
 fn escape_dot(s: &str) -> String {
     s.replace('\\', "\\\\").replace('\"', "\\\"")
 }
@@ -527,6 +717,7 @@ fn mermaid_id(id: &str) -> String {
     if out.is_empty() { "_".to_string() } else { out }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn build_node_dump(
     node_meta: &Value,
     nodes: &[Value],
@@ -538,7 +729,7 @@ fn build_node_dump(
     full_ids: bool,
     id_map: &Map<String, Value>,
     registry: Option<&Registry>,
-    outputs_used: &std::collections::HashMap<String, std::collections::HashSet<usize>>,
+    outputs_used: &HashMap<String, HashSet<usize>>,
 ) -> Value {
     let name = node_meta.get("name").and_then(|v| v.as_str()).unwrap_or("");
     let typ = node_meta.get("type").and_then(|v| v.as_str()).unwrap_or("");
@@ -565,23 +756,21 @@ fn build_node_dump(
     let mut out = Value::Object(base);
 
     if let Some(n) = node {
-        if include_pos {
-            if let Some(obj) = out.as_object_mut() {
-                obj.insert(
-                    "p".to_string(),
-                    json!([
-                        node.and_then(|n| n.get("x").and_then(|v| v.as_f64()))
-                            .unwrap_or(0.0),
-                        node.and_then(|n| n.get("y").and_then(|v| v.as_f64()))
-                            .unwrap_or(0.0)
-                    ]),
-                );
-            }
+        if include_pos && let Some(obj) = out.as_object_mut() {
+            obj.insert(
+                "p".to_string(),
+                json!([
+                    node.and_then(|n| n.get("x").and_then(|v| v.as_f64()))
+                        .unwrap_or(0.0),
+                    node.and_then(|n| n.get("y").and_then(|v| v.as_f64()))
+                        .unwrap_or(0.0)
+                ]),
+            );
         }
-        if let Some(attri) = n.get("attri") {
-            if !matches!(mode, GraphMode::Compact) {
-                out["a"] = attri.clone();
-            }
+        if let Some(attri) = n.get("attri")
+            && !matches!(mode, GraphMode::Compact)
+        {
+            out["a"] = attri.clone();
         }
         let reg_node = registry.and_then(|r| r.nodes.get(typ));
         if let Some(inputs) = n.get("inputs").and_then(|v| v.as_array()) {
@@ -589,29 +778,47 @@ fn build_node_dump(
             for (i, input) in inputs.iter().enumerate() {
                 let mut entry = Map::new();
                 entry.insert("s".to_string(), Value::Number((i as i64).into()));
-                if let Some(rn) = reg_node {
-                    if let Some(rin) = rn.inputs.get(i) {
-                        if let Some(nm) = &rin.name {
-                            entry.insert("n".to_string(), Value::String(nm.clone()));
+                if let Some(rn) = reg_node
+                    && let Some(rin) = rn.input(i)
+                {
+                    if let Some(nm) = rn.input_name(i) {
+                        entry.insert("n".to_string(), Value::String(nm));
+                    }
+                    if let Some(tp) = &rin.ty
+                        && tp != "unknown"
+                        && tp != "output"
+                    {
+                        entry.insert("t".to_string(), Value::String(tp.clone()));
+                    }
+                    if matches!(mode, GraphMode::Full) {
+                        if let Some(d) = &rin.default {
+                            entry.insert("d".to_string(), d.clone());
+                        }
+                        if let Some(options) = &rin.options {
+                            entry.insert("e".to_string(), json!(options));
                         }
-                        if let Some(tp) = &rin.ty {
-                            if tp != "unknown" && tp != "output" {
-                                entry.insert("t".to_string(), Value::String(tp.clone()));
-                            }
+                        if let Some(range) = rin.range_json() {
+                            entry.insert("r".to_string(), range);
+                        }
+                        if rin.visible == Some(false) {
+                            entry.insert("hd".to_string(), Value::Bool(true));
                         }
                     }
                 }
                 let (val, anim_meta) = extract_input_value_with_anim(input, mode);
-                if let Some(v) = val.clone() {
-                    if v != Value::Number((-4).into()) {
-                        entry.insert("v".to_string(), v);
-                    }
+                if let Some(v) = val.clone()
+                    && v != Value::Number((-4).into())
+                {
+                    entry.insert("v".to_string(), v);
                 }
                 if let Some(meta) = anim_meta {
                     for (k, v) in meta {
                         entry.insert(k, v);
                     }
                 }
+                if let Some(expression) = input_expression(input) {
+                    entry.insert("x".to_string(), Value::String(expression.to_string()));
+                }
                 if let Some(conn) = extract_connection(input, full_ids, id_map) {
                     entry.insert("c".to_string(), conn);
                 }
@@ -623,7 +830,11 @@ fn build_node_dump(
                 }
                 let include = match mode {
                     GraphMode::Summary => false,
-                    GraphMode::Compact => entry.contains_key("c") || entry.contains_key("a"),
+                    GraphMode::Compact => {
+                        entry.contains_key("c")
+                            || entry.contains_key("a")
+                            || entry.contains_key("x")
+                    }
                     GraphMode::Full => true,
                 };
                 if include {
@@ -651,16 +862,17 @@ fn build_node_dump(
                 if i != 0 {
                     entry.insert("s".to_string(), Value::Number((i as i64).into()));
                 }
-                if let Some(rn) = reg_node {
-                    if let Some(rout) = rn.outputs.get(i) {
-                        if let Some(nm) = &rout.name {
-                            entry.insert("n".to_string(), Value::String(nm.clone()));
-                        }
-                        if let Some(tp) = &rout.ty {
-                            if tp != "unknown" && tp != "output" {
-                                entry.insert("t".to_string(), Value::String(tp.clone()));
-                            }
-                        }
+                if let Some(rn) = reg_node
+                    && let Some(rout) = rn.outputs.get(i)
+                {
+                    if let Some(nm) = &rout.name {
+                        entry.insert("n".to_string(), Value::String(nm.clone()));
+                    }
+                    if let Some(tp) = &rout.ty
+                        && tp != "unknown"
+                        && tp != "output"
+                    {
+                        entry.insert("t".to_string(), Value::String(tp.clone()));
                     }
                 }
                 let has_detail = entry.len() > 1;
@@ -680,10 +892,10 @@ fn build_node_dump(
 
 fn extract_input_value(input: &Value) -> Option<Value> {
     let r = input.get("r")?;
-    if let Some(obj) = r.as_object() {
-        if let Some(d) = obj.get("d") {
-            return Some(d.clone());
-        }
+    if let Some(obj) = r.as_object()
+        && let Some(d) = obj.get("d")
+    {
+        return Some(d.clone());
     }
     if r.is_array() {
         return Some(r.clone());
@@ -746,10 +958,10 @@ fn anim_meta_map(
     if let Some(kc) = key_count {
         meta.insert("k".to_string(), Value::Number((kc as i64).into()));
     }
-    if matches!(mode, GraphMode::Full) {
-        if let Some(raw) = raw_anim {
-            meta.insert("ad".to_string(), raw);
-        }
+    if matches!(mode, GraphMode::Full)
+        && let Some(raw) = raw_anim
+    {
+        meta.insert("ad".to_string(), raw);
     }
     Some(meta)
 }
@@ -775,3 +987,16 @@ fn extract_connection(input: &Value, full_ids: bool, id_map: &Map<String, Value>
     }
     Some(Value::Object(map))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mermaid_quotes_use_entities() {
+        assert_eq!(
+            escape_mermaid(r#"say "hi" \o/"#),
+            r#"say #quot;hi#quot; \o/"#
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use serde_json::Value;
+
+use crate::graph::{GraphData, edge_details};
+use crate::registry::Registry;
+
+const NODE_W: f64 = 140.0;
+const NODE_H: f64 = 60.0;
+
+/// (key id, domain, attr.type) for every attribute written to the document.
+const KEYS: &[(&str, &str, &str)] = &[
+    ("name", "node", "string"),
+    ("type", "node", "string"),
+    ("x", "node", "double"),
+    ("y", "node", "double"),
+    ("group", "node", "string"),
+    ("full_id", "node", "string"),
+    ("from_slot", "edge", "int"),
+    ("to_slot", "edge", "int"),
+    ("from_port", "edge", "string"),
+    ("from_type", "edge", "string"),
+    ("to_port", "edge", "string"),
+    ("to_type", "edge", "string"),
+    ("tag", "edge", "int"),
+    ("bypass", "edge", "boolean"),
+];
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn data(s: &mut String, key: &str, value: &str) {
+    let _ = writeln!(
+        s,
+        "      <data key=\"{}\">{}</data>",
+        key,
+        escape_xml(value)
+    );
+}
+
+/// GraphML document with node and edge attributes, plus yEd shape data so the
+/// stored positions are used when the file is opened in yEd.
+pub(crate) fn render_graphml(
+    graph: &GraphData,
+    nodes: &[Value],
+    registry: Option<&Registry>,
+) -> String {
+    let by_id: HashMap<&str, &Value> = nodes
+        .iter()
+        .filter_map(|n| n.get("id").and_then(|v| v.as_str()).map(|id| (id, n)))
+        .collect();
+
+    let mut s = String::new();
+    s.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    s.push_str(
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\" \
+         xmlns:y=\"http://www.yworks.com/xml/graphml\">\n",
+    );
+    for (id, domain, ty) in KEYS {
+        let _ = writeln!(
+            s,
+            "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+            id, domain, id, ty
+        );
+    }
+    s.push_str("  <key id=\"graphics\" for=\"node\" yfiles.type=\"nodegraphics\"/>\n");
+    s.push_str("  <graph id=\"pxc\" edgedefault=\"directed\">\n");
+
+    for (id, meta) in graph.node_map.iter() {
+        let node = by_id.get(id.as_str());
+        let name = meta.get("name").and_then(|v| v.as_str()).unwrap_or("");
+        let typ = meta.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        let x = node
+            .and_then(|n| n.get("x"))
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0);
+        let y = node
+            .and_then(|n| n.get("y"))
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0);
+        let _ = writeln!(s, "    <node id=\"{}\">", escape_xml(&graph.key(id)));
+        data(&mut s, "name", name);
+        data(&mut s, "type", typ);
+        data(&mut s, "x", &x.to_string());
+        data(&mut s, "y", &y.to_string());
+        if let Some(parent) = graph.parent(id) {
+            data(&mut s, "group", &graph.key(parent));
+        }
+        data(&mut s, "full_id", id);
+        let _ = writeln!(
+            s,
+            "      <data key=\"graphics\"><y:ShapeNode><y:Geometry x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/><y:NodeLabel>{}</y:NodeLabel></y:ShapeNode></data>",
+            x,
+            y,
+            NODE_W,
+            NODE_H,
+            escape_xml(&format!("{}\n{}", name, typ))
+        );
+        s.push_str("    </node>\n");
+    }
+
+    for (i, edge) in edge_details(graph, registry).iter().enumerate() {
+        let _ = writeln!(
+            s,
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">",
+            i,
+            escape_xml(&graph.key(edge.from)),
+            escape_xml(&graph.key(edge.to))
+        );
+        data(&mut s, "from_slot", &edge.from_slot.to_string());
+        data(&mut s, "to_slot", &edge.to_slot.to_string());
+        if let Some(port) = edge.from_port {
+            if let Some(name) = &port.name {
+                data(&mut s, "from_port", name);
+            }
+            if let Some(ty) = &port.ty {
+                data(&mut s, "from_type", ty);
+            }
+        }
+        if let Some(port) = edge.to_port {
+            if let Some(name) = &port.name {
+                data(&mut s, "to_port", name);
+            }
+            if let Some(ty) = &port.ty {
+                data(&mut s, "to_type", ty);
+            }
+        }
+        if let Some(tag) = edge.tag {
+            data(&mut s, "tag", &tag.to_string());
+        }
+        data(&mut s, "bypass", if edge.bypass { "true" } else { "false" });
+        s.push_str("    </edge>\n");
+    }
+
+    s.push_str("  </graph>\n</graphml>\n");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::collect_graph;
+    use serde_json::json;
+
+    #[test]
+    fn writes_edges_and_groups() {
+        // `g` (A) holds `a` (B); `b` (C) reads `a` directly and through a bypass.
+        let nodes = json!([
+            {"id": "g", "type": "Node_Group", "x": 0, "y": 0},
+            {"id": "a", "type": "Node_T", "group": "g", "x": 0, "y": 0, "outputs": [{}]},
+            {"id": "b", "type": "Node_T", "x": 300, "y": 0, "inputs": [
+                {"from_node": "a", "from_index": 0},
+                {"from_node": "a", "from_index": 1000},
+            ]},
+        ]);
+        let nodes = nodes.as_array().unwrap();
+        let graph = collect_graph(nodes, false, false, None).unwrap();
+        let xml = render_graphml(&graph, nodes, None);
+        assert!(xml.contains("<node id=\"B\">"));
+        assert!(xml.contains("<data key=\"group\">A</data>"));
+        let edges: Vec<&str> = xml.split("<edge ").skip(1).collect();
+        assert_eq!(edges.len(), 2);
+        for (i, edge) in edges.iter().enumerate() {
+            let head = format!("id=\"e{}\" source=\"B\" target=\"C\">", i);
+            assert!(edge.starts_with(&head), "{}", edge);
+        }
+        assert!(edges[0].contains("<data key=\"bypass\">false</data>"));
+        assert!(edges[1].contains("<data key=\"from_slot\">1000</data>"));
+        assert!(edges[1].contains("<data key=\"bypass\">true</data>"));
+    }
+}
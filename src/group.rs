@@ -0,0 +1,580 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use anyhow::{Result, anyhow, bail};
+use serde_json::{Map, Value};
+
+use crate::favorites::prune_input_refs_in_pxc;
+use crate::ops::{build_node, resolve_node_id};
+use crate::pxc::PxcFile;
+use crate::registry::{Registry, RegistryPort};
+
+pub(crate) const GROUP_TYPE: &str = "Node_Group";
+pub(crate) const GROUP_INPUT_TYPE: &str = "Node_Group_Input";
+pub(crate) const GROUP_OUTPUT_TYPE: &str = "Node_Group_Output";
+
+const MAX_GROUP_DEPTH: usize = 64;
+
+/// A connection source: (from_node, from_index, from_tag).
+pub(crate) type Link = (String, i64, Option<i64>);
+
+/// A resolved connection: (consumer id, input slot, producer link).
+type FlatLink = (String, usize, Link);
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(|v| v.as_str()).unwrap_or("")
+}
+
+fn node_group(node: &Value) -> Option<&str> {
+    node.get("group").and_then(|v| v.as_str())
+}
+
+fn is_group_io(node: &Value) -> bool {
+    matches!(node_type(node), GROUP_INPUT_TYPE | GROUP_OUTPUT_TYPE)
+}
+
+pub(crate) fn input_link(input: &Value) -> Option<Link> {
+    let from = input.get("from_node")?.as_str()?;
+    let from_index = input
+        .get("from_index")
+        .and_then(|v| v.as_i64().or_else(|| v.as_f64().map(|f| f as i64)))?;
+    let from_tag = input
+        .get("from_tag")
+        .and_then(|v| v.as_i64().or_else(|| v.as_f64().map(|f| f as i64)));
+    Some((from.to_string(), from_index, from_tag))
+}
+
+pub(crate) fn set_input_link(input: &mut Value, link: Option<&Link>) {
+    if !input.is_object() {
+        *input = Value::Object(Map::new());
+    }
+    let Some(map) = input.as_object_mut() else {
+        return;
+    };
+    map.remove("from_node");
+    map.remove("from_index");
+    map.remove("from_tag");
+    if let Some((from, index, tag)) = link {
+        map.insert("from_node".to_string(), Value::String(from.clone()));
+        map.insert("from_index".to_string(), Value::Number((*index).into()));
+        if let Some(tag) = tag {
+            map.insert("from_tag".to_string(), Value::Number((*tag).into()));
+        }
+    }
+}
+
+/// Number of static inputs on a group node; junctions for group inputs follow them.
+fn group_input_offset(group: &Value) -> usize {
+    group
+        .get("input_fix_len")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as usize
+}
+
+fn io_priority(node: &Value) -> Option<i64> {
+    node.get("attri")
+        .and_then(|a| a.get("input_priority"))
+        .and_then(|v| v.as_i64().or_else(|| v.as_f64().map(|f| f as i64)))
+}
+
+/// Group input/output nodes of one kind inside `group_id`, in junction order.
+fn group_io_nodes<'a>(nodes: &'a [Value], group_id: &str, io_type: &str) -> Vec<&'a Value> {
+    let mut io: Vec<(i64, usize, &Value)> = nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| node_group(n) == Some(group_id) && node_type(n) == io_type)
+        .map(|(pos, n)| (io_priority(n).unwrap_or(pos as i64), pos, n))
+        .collect();
+    io.sort_by_key(|(priority, pos, _)| (*priority, *pos));
+    io.into_iter().map(|(_, _, n)| n).collect()
+}
+
+fn io_index(nodes: &[Value], io_node: &Value) -> Option<usize> {
+    let group_id = node_group(io_node)?;
+    let id = io_node.get("id").and_then(|v| v.as_str())?;
+    group_io_nodes(nodes, group_id, node_type(io_node))
+        .iter()
+        .position(|n| n.get("id").and_then(|v| v.as_str()) == Some(id))
+}
+
+/// Follows a link through group input/output nodes to the producing node.
+fn resolve_source(
+    nodes: &[Value],
+    by_id: &HashMap<&str, &Value>,
+    link: Link,
+    depth: usize,
+) -> Option<Link> {
+    if depth > MAX_GROUP_DEPTH {
+        return None;
+    }
+    let node = by_id.get(link.0.as_str())?;
+    match node_type(node) {
+        GROUP_INPUT_TYPE => {
+            let group = by_id.get(node_group(node)?)?;
+            let k = io_index(nodes, node)?;
+            let inputs = group.get("inputs").and_then(|v| v.as_array())?;
+            let next = input_link(inputs.get(group_input_offset(group) + k)?)?;
+            resolve_source(nodes, by_id, next, depth + 1)
+        }
+        GROUP_TYPE => {
+            let outputs = group_io_nodes(nodes, &link.0, GROUP_OUTPUT_TYPE);
+            let out_node = outputs.get(usize::try_from(link.1).ok()?)?;
+            let inputs = out_node.get("inputs").and_then(|v| v.as_array())?;
+            let next = input_link(inputs.first()?)?;
+            resolve_source(nodes, by_id, next, depth + 1)
+        }
+        _ => Some(link),
+    }
+}
+
+/// Every connection between real nodes, with group plumbing resolved away.
+pub(crate) fn flattened_links(nodes: &[Value]) -> BTreeSet<FlatLink> {
+    let by_id: HashMap<&str, &Value> = nodes
+        .iter()
+        .filter_map(|n| n.get("id").and_then(|v| v.as_str()).map(|id| (id, n)))
+        .collect();
+    let mut out = BTreeSet::new();
+    for node in nodes {
+        if is_group_io(node) || node_type(node) == GROUP_TYPE {
+            continue;
+        }
+        let Some(id) = node.get("id").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let Some(inputs) = node.get("inputs").and_then(|v| v.as_array()) else {
+            continue;
+        };
+        for (idx, input) in inputs.iter().enumerate() {
+            if let Some(link) = input_link(input)
+                && let Some(src) = resolve_source(nodes, &by_id, link, 0)
+            {
+                out.insert((id.to_string(), idx, src));
+            }
+        }
+    }
+    out
+}
+
+fn registry_port<'a>(
+    registry: Option<&'a Registry>,
+    node: &Value,
+    slot: usize,
+    output: bool,
+) -> Option<&'a RegistryPort> {
+    let registry = registry?;
+    if output {
+        registry.output_port(node_type(node), slot)
+    } else {
+        registry.input_port(node_type(node), slot)
+    }
+}
+
+pub(crate) fn port_types_compatible(from: Option<&str>, to: Option<&str>) -> bool {
+    let (Some(from), Some(to)) = (from, to) else {
+        return true;
+    };
+    let from = from.to_ascii_lowercase();
+    let to = to.to_ascii_lowercase();
+    let loose = |t: &str| matches!(t, "" | "unknown" | "any" | "output");
+    let numeric = |t: &str| {
+        matches!(
+            t,
+            "float" | "integer" | "boolean" | "enum" | "rotation" | "trigger" | "color"
+        )
+    };
+    loose(&from) || loose(&to) || from == to || (numeric(&from) && numeric(&to))
+}
+
+fn node_pos(node: &Value) -> (f64, f64) {
+    (
+        node.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0),
+        node.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0),
+    )
+}
+
+/// Moves `members` into a new `Node_Group`, creating group input/output nodes for
+/// every connection that crosses the boundary. Returns the new group's id.
+pub fn group_nodes_in_pxc(
+    pxc: &mut PxcFile,
+    members: &[&str],
+    name: Option<&str>,
+    registry: Option<&Registry>,
+) -> Result<String> {
+    let original = pxc
+        .json
+        .get("nodes")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("no nodes array found"))?;
+
+    let mut member_ids: Vec<String> = Vec::new();
+    for m in members {
+        let id = resolve_node_id(m, original).ok_or_else(|| anyhow!("node not found: {}", m))?;
+        if !member_ids.contains(&id) {
+            member_ids.push(id);
+        }
+    }
+    if member_ids.is_empty() {
+        bail!("no nodes selected");
+    }
+    let member_set: HashSet<&str> = member_ids.iter().map(|s| s.as_str()).collect();
+    let member_nodes: Vec<&Value> = original
+        .iter()
+        .filter(|n| {
+            n.get("id")
+                .and_then(|v| v.as_str())
+                .is_some_and(|id| member_set.contains(id))
+        })
+        .collect();
+    if member_nodes.iter().any(|n| is_group_io(n)) {
+        bail!("group input/output nodes cannot be grouped");
+    }
+    let parent = member_nodes[0].get("group").cloned();
+    if member_nodes
+        .iter()
+        .any(|n| node_group(n) != member_nodes[0].get("group").and_then(|v| v.as_str()))
+    {
+        bail!("selected nodes must all be in the same group");
+    }
+
+    let before = flattened_links(original);
+    let mut nodes = original.clone();
+
+    let positions: Vec<(f64, f64)> = member_nodes.iter().map(|n| node_pos(n)).collect();
+    let min_x = positions.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let max_x = positions
+        .iter()
+        .map(|p| p.0)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let min_y = positions.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let cx = positions.iter().map(|p| p.0).sum::<f64>() / positions.len() as f64;
+    let cy = positions.iter().map(|p| p.1).sum::<f64>() / positions.len() as f64;
+
+    let (group_id, mut group_node) = build_node(
+        GROUP_TYPE,
+        cx.round() as i32,
+        cy.round() as i32,
+        Some(name.unwrap_or("Group")),
+        None,
+    );
+    if let Some(p) = &parent {
+        group_node["group"] = p.clone();
+    }
+
+    // Inbound: one group input per distinct outside source.
+    let mut inbound: Vec<Link> = Vec::new();
+    let mut inbound_names: Vec<Option<String>> = Vec::new();
+    let mut inbound_rewires: Vec<(usize, usize, usize)> = Vec::new();
+    // Outbound: one group output per distinct member output read from outside.
+    let mut outbound: Vec<Link> = Vec::new();
+    let mut outbound_names: Vec<Option<String>> = Vec::new();
+    let mut outbound_rewires: Vec<(usize, usize, usize)> = Vec::new();
+
+    let by_id: HashMap<&str, &Value> = original
+        .iter()
+        .filter_map(|n| n.get("id").and_then(|v| v.as_str()).map(|id| (id, n)))
+        .collect();
+    for (ni, node) in original.iter().enumerate() {
+        let Some(id) = node.get("id").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let Some(inputs) = node.get("inputs").and_then(|v| v.as_array()) else {
+            continue;
+        };
+        let is_member = member_set.contains(id);
+        for (ii, input) in inputs.iter().enumerate() {
+            let Some(link) = input_link(input) else {
+                continue;
+            };
+            let from_member = member_set.contains(link.0.as_str());
+            if is_member == from_member {
+                continue;
+            }
+            if let Some(from) = by_id.get(link.0.as_str())
+                && link.1 >= 0
+            {
+                let from_ty = registry_port(registry, from, link.1 as usize, true)
+                    .and_then(|p| p.value_type());
+                let to_ty = registry_port(registry, node, ii, false).and_then(|p| p.value_type());
+                if !port_types_compatible(from_ty.as_deref(), to_ty.as_deref()) {
+                    bail!(
+                        "connection {} out{} ({}) -> {} in{} ({}) has incompatible port types",
+                        link.0,
+                        link.1,
+                        from_ty.unwrap_or_default(),
+                        id,
+                        ii,
+                        to_ty.unwrap_or_default()
+                    );
+                }
+            }
+            if is_member {
+                let k = match inbound.iter().position(|l| *l == link) {
+                    Some(k) => k,
+                    None => {
+                        inbound.push(link);
+                        inbound_names.push(
+                            registry_port(registry, node, ii, false).and_then(|p| p.name.clone()),
+                        );
+                        inbound.len() - 1
+                    }
+                };
+                inbound_rewires.push((ni, ii, k));
+            } else {
+                let k = match outbound.iter().position(|l| *l == link) {
+                    Some(k) => k,
+                    None => {
+                        let name = by_id.get(link.0.as_str()).and_then(|from| {
+                            usize::try_from(link.1)
+                                .ok()
+                                .and_then(|slot| registry_port(registry, from, slot, true))
+                                .and_then(|p| p.name.clone())
+                        });
+                        outbound.push(link);
+                        outbound_names.push(name);
+                        outbound.len() - 1
+                    }
+                };
+                outbound_rewires.push((ni, ii, k));
+            }
+        }
+    }
+
+    let reg_node = |ty: &str| registry.and_then(|r| r.nodes.get(ty));
+    let mut new_nodes = Vec::new();
+    let mut group_inputs = Vec::new();
+    let mut input_ids = Vec::new();
+    for (k, link) in inbound.iter().enumerate() {
+        let label = inbound_names[k]
+            .clone()
+            .unwrap_or_else(|| format!("Input {}", k));
+        let (id, mut node) = build_node(
+            GROUP_INPUT_TYPE,
+            (min_x - 160.0).round() as i32,
+            (min_y + 80.0 * k as f64).round() as i32,
+            Some(&label),
+            reg_node(GROUP_INPUT_TYPE),
+        );
+        node["group"] = Value::String(group_id.clone());
+        node["attri"]["input_priority"] = Value::Number((k as u64).into());
+        if node["outputs"].as_array().is_none_or(|o| o.is_empty()) {
+            node["outputs"] = Value::Array(vec![Value::Object(Map::new())]);
+        }
+        let mut junction = Value::Object(Map::new());
+        set_input_link(&mut junction, Some(link));
+        group_inputs.push(junction);
+        input_ids.push(id);
+        new_nodes.push(node);
+    }
+    let mut group_outputs = Vec::new();
+    for (k, link) in outbound.iter().enumerate() {
+        let label = outbound_names[k]
+            .clone()
+            .unwrap_or_else(|| format!("Output {}", k));
+        let (_, mut node) = build_node(
+            GROUP_OUTPUT_TYPE,
+            (max_x + 160.0).round() as i32,
+            (min_y + 80.0 * k as f64).round() as i32,
+            Some(&label),
+            reg_node(GROUP_OUTPUT_TYPE),
+        );
+        node["group"] = Value::String(group_id.clone());
+        node["attri"]["input_priority"] = Value::Number((k as u64).into());
+        if node["inputs"].as_array().is_none_or(|i| i.is_empty()) {
+            node["inputs"] = Value::Array(vec![Value::Object(Map::new())]);
+        }
+        set_input_link(&mut node["inputs"][0], Some(link));
+        group_outputs.push(Value::Object(Map::new()));
+        new_nodes.push(node);
+    }
+    group_node["inputs"] = Value::Array(group_inputs);
+    group_node["outputs"] = Value::Array(group_outputs);
+
+    for (ni, ii, k) in inbound_rewires {
+        let link = (input_ids[k].clone(), 0, None);
+        set_input_link(&mut nodes[ni]["inputs"][ii], Some(&link));
+    }
+    for (ni, ii, k) in outbound_rewires {
+        let link = (group_id.clone(), k as i64, None);
+        set_input_link(&mut nodes[ni]["inputs"][ii], Some(&link));
+    }
+    for node in nodes.iter_mut() {
+        let is_member = node
+            .get("id")
+            .and_then(|v| v.as_str())
+            .is_some_and(|id| member_set.contains(id));
+        if is_member {
+            node["group"] = Value::String(group_id.clone());
+        }
+    }
+    nodes.push(group_node);
+    nodes.extend(new_nodes);
+
+    if flattened_links(&nodes) != before {
+        bail!("grouping would change the graph's connections");
+    }
+    pxc.json["nodes"] = Value::Array(nodes);
+    Ok(group_id)
+}
+
+/// Dissolves a `Node_Group`: its contents move to the enclosing graph, group
+/// input/output nodes are removed and connections are wired straight through.
+/// Returns the number of nodes moved out of the group.
+pub fn ungroup_in_pxc(pxc: &mut PxcFile, group_arg: &str) -> Result<usize> {
+    let original = pxc
+        .json
+        .get("nodes")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("no nodes array found"))?;
+    let group_id = resolve_node_id(group_arg, original)
+        .ok_or_else(|| anyhow!("group node not found: {}", group_arg))?;
+    let group = original
+        .iter()
+        .find(|n| n.get("id").and_then(|v| v.as_str()) == Some(group_id.as_str()))
+        .ok_or_else(|| anyhow!("group node not found: {}", group_arg))?;
+    if node_type(group) != GROUP_TYPE {
+        bail!(
+            "{} is a {}, not a {}",
+            group_arg,
+            node_type(group),
+            GROUP_TYPE
+        );
+    }
+
+    let before = flattened_links(original);
+    let parent = group.get("group").cloned();
+    let offset = group_input_offset(group);
+    let group_inputs = group.get("inputs").and_then(|v| v.as_array());
+
+    let mut input_sources: HashMap<String, Option<Link>> = HashMap::new();
+    for (k, io) in group_io_nodes(original, &group_id, GROUP_INPUT_TYPE)
+        .into_iter()
+        .enumerate()
+    {
+        if let Some(id) = io.get("id").and_then(|v| v.as_str()) {
+            let src = group_inputs
+                .and_then(|inputs| inputs.get(offset + k))
+                .and_then(input_link);
+            input_sources.insert(id.to_string(), src);
+        }
+    }
+    let mut output_sources: Vec<Option<Link>> = Vec::new();
+    for io in group_io_nodes(original, &group_id, GROUP_OUTPUT_TYPE) {
+        let src = io
+            .get("inputs")
+            .and_then(|v| v.as_array())
+            .and_then(|inputs| inputs.first())
+            .and_then(input_link);
+        output_sources.push(src);
+    }
+
+    let mut nodes = Vec::with_capacity(original.len());
+    let mut moved = 0usize;
+    for node in original {
+        let id = node.get("id").and_then(|v| v.as_str()).unwrap_or("");
+        if id == group_id || input_sources.contains_key(id) {
+            continue;
+        }
+        let in_group = node_group(node) == Some(group_id.as_str());
+        if in_group && node_type(node) == GROUP_OUTPUT_TYPE {
+            continue;
+        }
+        let mut node = node.clone();
+        if in_group {
+            match &parent {
+                Some(p) => node["group"] = p.clone(),
+                None => {
+                    if let Some(obj) = node.as_object_mut() {
+                        obj.remove("group");
+                    }
+                }
+            }
+            moved += 1;
+        }
+        if let Some(inputs) = node.get_mut("inputs").and_then(|v| v.as_array_mut()) {
+            for input in inputs.iter_mut() {
+                let Some(link) = input_link(input) else {
+                    continue;
+                };
+                if let Some(src) = input_sources.get(&link.0) {
+                    set_input_link(input, src.as_ref());
+                } else if link.0 == group_id {
+                    let src = usize::try_from(link.1)
+                        .ok()
+                        .and_then(|k| output_sources.get(k))
+                        .cloned()
+                        .flatten();
+                    set_input_link(input, src.as_ref());
+                }
+            }
+        }
+        nodes.push(node);
+    }
+
+    if flattened_links(&nodes) != before {
+        bail!("ungrouping would change the graph's connections");
+    }
+    pxc.json["nodes"] = Value::Array(nodes);
+    // The group's own inputs (and any favourites on them) are gone.
+    prune_input_refs_in_pxc(pxc);
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+    use crate::registry::RegistryNode;
+    use serde_json::json;
+
+    fn port(ty: &str) -> RegistryPort {
+        RegistryPort {
+            ty: Some(ty.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// `a -> b -> c`, plus `a` feeding `c` directly.
+    fn chain() -> PxcFile {
+        let mut pxc = empty_pxc();
+        pxc.json["nodes"] = json!([
+            {"id": "a", "type": "Node_T", "x": 0, "y": 0, "inputs": [], "outputs": [{}]},
+            {"id": "b", "type": "Node_T", "x": 200, "y": 0, "outputs": [{}],
+             "inputs": [{"from_node": "a", "from_index": 0}]},
+            {"id": "c", "type": "Node_T", "x": 400, "y": 0, "outputs": [{}],
+             "inputs": [{"from_node": "b", "from_index": 0}, {"from_node": "a", "from_index": 0}]},
+        ]);
+        pxc
+    }
+
+    #[test]
+    fn group_then_ungroup_restores_links() {
+        let mut pxc = chain();
+        let before = flattened_links(pxc.json["nodes"].as_array().unwrap());
+        let group = group_nodes_in_pxc(&mut pxc, &["b"], Some("G"), None).unwrap();
+
+        let nodes = pxc.json["nodes"].as_array().unwrap();
+        let types: Vec<&str> = nodes.iter().map(node_type).collect();
+        assert!(types.contains(&GROUP_INPUT_TYPE) && types.contains(&GROUP_OUTPUT_TYPE));
+        // `c` now reads the group, yet the resolved graph is unchanged.
+        assert_eq!(nodes[2]["inputs"][0]["from_node"], json!(group));
+        assert_eq!(flattened_links(nodes), before);
+
+        assert_eq!(ungroup_in_pxc(&mut pxc, &group).unwrap(), 1);
+        assert_eq!(pxc.json["nodes"], chain().json["nodes"]);
+    }
+
+    #[test]
+    fn incompatible_crossing_is_rejected() {
+        let node = RegistryNode {
+            inputs: vec![port("nodeValue_Float"), port("nodeValue_Float")],
+            outputs: vec![port("nodeValue_Surface")],
+            ..Default::default()
+        };
+        let registry = Registry {
+            nodes: HashMap::from([("Node_T".to_string(), node)]),
+        };
+        let mut pxc = chain();
+        let err = group_nodes_in_pxc(&mut pxc, &["b"], None, Some(&registry)).unwrap_err();
+        assert!(err.to_string().contains("incompatible port types"));
+        assert_eq!(pxc.json["nodes"], chain().json["nodes"]);
+    }
+}
@@ -0,0 +1,757 @@
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+use crate::ops::{input_mut, input_ref, stored_value};
+use crate::pxc::PxcFile;
+use crate::registry::{Registry, RegistryPort};
+
+/// Curve type on either side of a key; Pixel Composer's `CURVE_TYPE`
+/// (`none`, `bezier`, `cut`), saved as its index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpolation {
+    #[default]
+    #[value(alias = "none")]
+    Linear,
+    Bezier,
+    #[value(alias = "cut")]
+    Hold,
+}
+
+impl Interpolation {
+    fn from_index(index: i64) -> Self {
+        match index {
+            1 => Self::Bezier,
+            2 => Self::Hold,
+            _ => Self::Linear,
+        }
+    }
+
+    fn index(self) -> i64 {
+        match self {
+            Self::Linear => 0,
+            Self::Bezier => 1,
+            Self::Hold => 2,
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        <Self as ValueEnum>::from_str(name, true).map_err(|_| {
+            anyhow!(
+                "unknown interpolation {:?} (expected linear, bezier or hold)",
+                name
+            )
+        })
+    }
+}
+
+/// One key of an animated input.
+#[derive(Clone, Debug, Serialize)]
+pub struct Keyframe {
+    pub frame: i64,
+    pub value: Value,
+    /// Bezier handle `[x, y]` towards the previous key.
+    pub ease_in: [f64; 2],
+    /// Bezier handle `[x, y]` towards the next key.
+    pub ease_out: [f64; 2],
+    pub interp_in: Interpolation,
+    pub interp_out: Interpolation,
+}
+
+const DEFAULT_EASE_IN: [f64; 2] = [0.0, 1.0];
+const DEFAULT_EASE_OUT: [f64; 2] = [0.0, 0.0];
+
+/// Optional key settings for `set_keyframe_in_pxc`; `None` keeps the key's
+/// current setting, or the default for a new key.
+#[derive(Clone, Debug, Default)]
+pub struct KeyframeEase {
+    pub ease_in: Option<[f64; 2]>,
+    pub ease_out: Option<[f64; 2]>,
+    pub interp_in: Option<Interpolation>,
+    pub interp_out: Option<Interpolation>,
+}
+
+fn number(v: Option<&Value>) -> Option<f64> {
+    v.and_then(|v| v.as_f64())
+}
+
+fn ease(v: Option<&Value>, default: [f64; 2]) -> [f64; 2] {
+    match v {
+        Some(Value::Array(a)) => [
+            number(a.first()).unwrap_or(default[0]),
+            number(a.get(1)).unwrap_or(default[1]),
+        ],
+        // Older saves store only the x handle.
+        Some(v) => [v.as_f64().unwrap_or(default[0]), default[1]],
+        None => default,
+    }
+}
+
+fn key_frame(raw: &Value) -> Option<i64> {
+    number(raw.as_array()?.first()).map(|f| f.round() as i64)
+}
+
+/// Reads `[time, value, ease_in, ease_out, ease_in_type, ease_out_type, ...]`.
+fn parse_key(raw: &Value) -> Option<Keyframe> {
+    let a = raw.as_array()?;
+    Some(Keyframe {
+        frame: key_frame(raw)?,
+        value: a.get(1).cloned().unwrap_or(Value::Null),
+        ease_in: ease(a.get(2), DEFAULT_EASE_IN),
+        ease_out: ease(a.get(3), DEFAULT_EASE_OUT),
+        interp_in: Interpolation::from_index(number(a.get(4)).unwrap_or(0.0) as i64),
+        interp_out: Interpolation::from_index(number(a.get(5)).unwrap_or(0.0) as i64),
+    })
+}
+
+fn new_key(frame: i64, value: Value) -> Value {
+    json!([frame, value, DEFAULT_EASE_IN, DEFAULT_EASE_OUT, 0, 0, true])
+}
+
+/// Writes the edited fields into a raw key, keeping anything after them
+/// (`ease_y_lock`, drivers).
+fn apply_ease(raw: &mut Value, ease: &KeyframeEase) {
+    let Some(a) = raw.as_array_mut() else {
+        return;
+    };
+    while a.len() < 6 {
+        let fill = match a.len() {
+            2 => json!(DEFAULT_EASE_IN),
+            3 => json!(DEFAULT_EASE_OUT),
+            _ => json!(0),
+        };
+        a.push(fill);
+    }
+    if let Some(v) = ease.ease_in {
+        a[2] = json!(v);
+    }
+    if let Some(v) = ease.ease_out {
+        a[3] = json!(v);
+    }
+    if let Some(v) = ease.interp_in {
+        a[4] = json!(v.index());
+    }
+    if let Some(v) = ease.interp_out {
+        a[5] = json!(v.index());
+    }
+}
+
+/// Keys of an input, or empty when it holds a static value.
+pub fn keyframes_in_pxc(
+    pxc: &PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    registry: Option<&Registry>,
+) -> Result<Vec<Keyframe>> {
+    let (input, _) = input_ref(pxc, node_arg, input_slot, input_name, registry)?;
+    let keys = input.get("r").and_then(|r| r.as_array());
+    Ok(keys.into_iter().flatten().filter_map(parse_key).collect())
+}
+
+/// The static value of an input: `r.d`, or the first key of an animated one.
+fn static_value(input: &Map<String, Value>) -> Value {
+    match input.get("r") {
+        Some(Value::Object(r)) => r.get("d").cloned().unwrap_or(Value::Null),
+        Some(Value::Array(keys)) => keys
+            .first()
+            .and_then(|k| k.get(1))
+            .cloned()
+            .unwrap_or(Value::Null),
+        _ => Value::Null,
+    }
+}
+
+/// Turns an input's keys into a mutable list, converting a static value into a
+/// single key at frame 0.
+fn animated_keys(input: &mut Map<String, Value>) -> &mut Vec<Value> {
+    if !input.get("r").is_some_and(|r| r.is_array()) {
+        let value = static_value(input);
+        input.insert("r".to_string(), json!([new_key(0, value)]));
+    }
+    input.insert("anim".to_string(), Value::Bool(true));
+    input
+        .get_mut("r")
+        .and_then(|r| r.as_array_mut())
+        .expect("keys were just written")
+}
+
+fn sort_keys(keys: &mut [Value]) {
+    keys.sort_by_key(|k| key_frame(k).unwrap_or(i64::MAX));
+}
+
+fn retime_keys(keys: &mut Vec<Value>, map: &dyn Fn(f64) -> f64) -> (usize, usize) {
+    let (mut moved, mut merged) = (0, 0);
+    let mut taken = std::collections::HashSet::new();
+    keys.retain_mut(|key| {
+        let Some(a) = key.as_array_mut() else {
+            return true;
+        };
+        let Some(frame) = number(a.first()) else {
+            return true;
+        };
+        let to = map(frame).round() as i64;
+        if !taken.insert(to) {
+            merged += 1;
+            return false;
+        }
+        if to as f64 != frame {
+            a[0] = json!(to);
+            moved += 1;
+        }
+        true
+    });
+    sort_keys(keys);
+    (moved, merged)
+}
+
+/// Maps every key time of an input (`r` and per-axis `animators`) through `map`,
+/// rounded to whole frames. When two keys land on one frame the earlier key is
+/// kept. Returns the number of keys moved and merged away.
+pub(crate) fn retime_input_keys(input: &mut Value, map: &dyn Fn(f64) -> f64) -> (usize, usize) {
+    let mut lists: Vec<&mut Vec<Value>> = Vec::new();
+    let Some(input) = input.as_object_mut() else {
+        return (0, 0);
+    };
+    for (key, value) in input.iter_mut() {
+        match (key.as_str(), value) {
+            ("r", Value::Array(keys)) => lists.push(keys),
+            ("animators", Value::Array(axes)) => {
+                lists.extend(axes.iter_mut().filter_map(|a| a.as_array_mut()))
+            }
+            _ => {}
+        }
+    }
+    lists.into_iter().fold((0, 0), |(moved, merged), keys| {
+        let (m, g) = retime_keys(keys, map);
+        (moved + m, merged + g)
+    })
+}
+
+/// Adds a key at `frame`, or updates the key already there, and turns animation
+/// on. `value` may be omitted when only the easing of an existing key changes.
+#[allow(clippy::too_many_arguments)]
+pub fn set_keyframe_in_pxc(
+    pxc: &mut PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    frame: i64,
+    value: Option<Value>,
+    ease: &KeyframeEase,
+    registry: Option<&Registry>,
+) -> Result<()> {
+    let (input, port) = input_mut(pxc, node_arg, input_slot, input_name, registry)?;
+    let value = value.map(|v| stored_value(port, v)).transpose()?;
+    let was_animated = input.get("r").is_some_and(|r| r.is_array());
+    if !was_animated && value.is_none() {
+        bail!("input is not animated; a value is required for the first key");
+    }
+    let keys = animated_keys(input);
+    if !was_animated && frame != 0 {
+        // The static value only seeds the key that is being set.
+        keys.clear();
+    }
+    let existing = keys.iter().position(|k| key_frame(k) == Some(frame));
+    let index = match (existing, value) {
+        (Some(i), Some(value)) => {
+            if let Some(a) = keys[i].as_array_mut() {
+                a.resize(a.len().max(2), Value::Null);
+                a[1] = value;
+            }
+            i
+        }
+        (Some(i), None) => i,
+        (None, Some(value)) => {
+            keys.push(new_key(frame, value));
+            keys.len() - 1
+        }
+        (None, None) => bail!("no key at frame {}", frame),
+    };
+    apply_ease(&mut keys[index], ease);
+    sort_keys(keys);
+    Ok(())
+}
+
+fn animated_keys_mut(input: &mut Map<String, Value>) -> Result<&mut Vec<Value>> {
+    input
+        .get_mut("r")
+        .and_then(|r| r.as_array_mut())
+        .ok_or_else(|| anyhow!("input is not animated"))
+}
+
+/// Moves the key at `frame` to `to_frame`.
+pub fn move_keyframe_in_pxc(
+    pxc: &mut PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    frame: i64,
+    to_frame: i64,
+    registry: Option<&Registry>,
+) -> Result<()> {
+    let (input, _) = input_mut(pxc, node_arg, input_slot, input_name, registry)?;
+    let keys = animated_keys_mut(input)?;
+    let index = keys
+        .iter()
+        .position(|k| key_frame(k) == Some(frame))
+        .ok_or_else(|| anyhow!("no key at frame {}", frame))?;
+    if frame != to_frame && keys.iter().any(|k| key_frame(k) == Some(to_frame)) {
+        bail!("frame {} already has a key", to_frame);
+    }
+    if let Some(a) = keys[index].as_array_mut() {
+        a[0] = json!(to_frame);
+    }
+    sort_keys(keys);
+    Ok(())
+}
+
+/// Deletes the key at `frame`. Removing the last key leaves its value as the
+/// input's static value.
+pub fn delete_keyframe_in_pxc(
+    pxc: &mut PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    frame: i64,
+    registry: Option<&Registry>,
+) -> Result<()> {
+    let (input, _) = input_mut(pxc, node_arg, input_slot, input_name, registry)?;
+    let keys = animated_keys_mut(input)?;
+    let index = keys
+        .iter()
+        .position(|k| key_frame(k) == Some(frame))
+        .ok_or_else(|| anyhow!("no key at frame {}", frame))?;
+    let removed = keys.remove(index);
+    if keys.is_empty() {
+        let value = removed.get(1).cloned().unwrap_or(Value::Null);
+        input.insert("r".to_string(), json!({ "d": value }));
+        input.remove("anim");
+    }
+    Ok(())
+}
+
+/// Turns animation on (the static value becomes a key at frame 0) or off (the
+/// first key's value becomes the static value and the other keys are dropped).
+pub fn set_animated_in_pxc(
+    pxc: &mut PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    animated: bool,
+    registry: Option<&Registry>,
+) -> Result<()> {
+    let (input, _) = input_mut(pxc, node_arg, input_slot, input_name, registry)?;
+    if animated {
+        animated_keys(input);
+    } else {
+        let value = static_value(input);
+        input.insert("r".to_string(), json!({ "d": value }));
+        input.remove("anim");
+    }
+    Ok(())
+}
+
+/// How values between two keys are blended, from the input's registry type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Blend {
+    Number,
+    Integer,
+    /// `0xAABBGGRR` colors, blended per channel.
+    Color,
+    /// Text, booleans, enums and gradients keep the earlier key's value.
+    Step,
+}
+
+impl Blend {
+    fn of(port: Option<&RegistryPort>) -> Self {
+        let Some(port) = port else {
+            return Self::Number;
+        };
+        if port.options.as_ref().is_some_and(|o| !o.is_empty()) {
+            return Self::Step;
+        }
+        match port.value_type().as_deref() {
+            Some("integer") => Self::Integer,
+            Some("color") => Self::Color,
+            Some("boolean" | "text" | "gradient" | "path" | "font") => Self::Step,
+            _ => Self::Number,
+        }
+    }
+}
+
+/// What a track does past its last key; Pixel Composer's `KEYFRAME_END`, saved
+/// as `on_end`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OnEnd {
+    Hold,
+    Loop,
+    PingPong,
+    /// Loops, offset by the change over one loop each time round.
+    Wrap,
+}
+
+impl OnEnd {
+    fn from_index(index: i64) -> Self {
+        match index {
+            1 => Self::Loop,
+            2 => Self::PingPong,
+            3 => Self::Wrap,
+            _ => Self::Hold,
+        }
+    }
+}
+
+/// Point on the unit bezier `(0,0) p1 p2 (1,1)` whose x is `x`.
+fn bezier_y(p1: [f64; 2], p2: [f64; 2], x: f64) -> f64 {
+    let axis = |t: f64, a: f64, b: f64| {
+        let u = 1.0 - t;
+        3.0 * u * u * t * a + 3.0 * u * t * t * b + t * t * t
+    };
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..48 {
+        let mid = (lo + hi) / 2.0;
+        if axis(mid, p1[0], p2[0]) < x {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    axis((lo + hi) / 2.0, p1[1], p2[1])
+}
+
+/// Eased progress between two keys for a linear progress `rat`.
+fn ease_progress(from: &Keyframe, to: &Keyframe, rat: f64) -> f64 {
+    if from.interp_out == Interpolation::Hold {
+        return 0.0;
+    }
+    if to.interp_in == Interpolation::Hold {
+        return 1.0;
+    }
+    if from.interp_out == Interpolation::Linear && to.interp_in == Interpolation::Linear {
+        return rat;
+    }
+    // A linear side keeps the default handle, which leaves that end straight.
+    let p1 = match from.interp_out {
+        Interpolation::Bezier => from.ease_out,
+        _ => DEFAULT_EASE_OUT,
+    };
+    let ease_in = match to.interp_in {
+        Interpolation::Bezier => to.ease_in,
+        _ => DEFAULT_EASE_IN,
+    };
+    bezier_y(p1, [1.0 - ease_in[0], ease_in[1]], rat)
+}
+
+fn blend_color(a: f64, b: f64, t: f64) -> f64 {
+    let (a, b) = (a as u32, b as u32);
+    let mut out = 0u32;
+    for shift in [0, 8, 16, 24] {
+        let ca = ((a >> shift) & 0xFF) as f64;
+        let cb = ((b >> shift) & 0xFF) as f64;
+        let c = (ca + (cb - ca) * t).round().clamp(0.0, 255.0) as u32;
+        out |= c << shift;
+    }
+    out as f64
+}
+
+fn number_value(f: f64) -> Value {
+    if f.fract() == 0.0 && f.abs() < 9.0e15 {
+        json!(f as i64)
+    } else {
+        json!(f)
+    }
+}
+
+fn blend_values(a: &Value, b: &Value, t: f64, blend: Blend) -> Value {
+    if t <= 0.0 {
+        return a.clone();
+    }
+    if t >= 1.0 {
+        return b.clone();
+    }
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+            match blend {
+                Blend::Number => json!(x + (y - x) * t),
+                Blend::Integer => number_value((x + (y - x) * t).round()),
+                Blend::Color => number_value(blend_color(x, y, t)),
+                Blend::Step => a.clone(),
+            }
+        }
+        (Value::Array(xs), Value::Array(ys)) if xs.len() == ys.len() && blend != Blend::Step => {
+            Value::Array(
+                xs.iter()
+                    .zip(ys)
+                    .map(|(x, y)| blend_values(x, y, t, blend))
+                    .collect(),
+            )
+        }
+        _ => a.clone(),
+    }
+}
+
+/// `value + delta * times` for numbers and arrays of numbers.
+fn offset_value(value: &Value, delta: &Value, times: f64) -> Value {
+    match (value, delta) {
+        (Value::Number(v), Value::Number(d)) => {
+            json!(v.as_f64().unwrap_or(0.0) + d.as_f64().unwrap_or(0.0) * times)
+        }
+        (Value::Array(vs), Value::Array(ds)) if vs.len() == ds.len() => Value::Array(
+            vs.iter()
+                .zip(ds)
+                .map(|(v, d)| offset_value(v, d, times))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// Change in value across the looped keys, for `OnEnd::Wrap`.
+fn loop_delta(first: &Value, last: &Value) -> Value {
+    match (first, last) {
+        (Value::Number(a), Value::Number(b)) => {
+            json!(b.as_f64().unwrap_or(0.0) - a.as_f64().unwrap_or(0.0))
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            Value::Array(a.iter().zip(b).map(|(x, y)| loop_delta(x, y)).collect())
+        }
+        _ => Value::Null,
+    }
+}
+
+/// Playback settings of one input that apply to its keys.
+#[derive(Clone, Copy, Debug)]
+struct Playback {
+    on_end: OnEnd,
+    /// Number of final key intervals that repeat; -1 repeats them all.
+    loop_range: i64,
+    blend: Blend,
+}
+
+fn value_between(keys: &[Keyframe], frame: f64, blend: Blend) -> Value {
+    let last = keys.len() - 1;
+    if frame <= keys[0].frame as f64 {
+        return keys[0].value.clone();
+    }
+    if frame >= keys[last].frame as f64 {
+        return keys[last].value.clone();
+    }
+    let i = keys
+        .windows(2)
+        .position(|w| (w[1].frame as f64) > frame)
+        .unwrap_or(last - 1);
+    let (from, to) = (&keys[i], &keys[i + 1]);
+    let span = (to.frame - from.frame) as f64;
+    let rat = (frame - from.frame as f64) / span;
+    blend_values(&from.value, &to.value, ease_progress(from, to, rat), blend)
+}
+
+fn track_value(keys: &[Keyframe], frame: f64, playback: Playback) -> Value {
+    if keys.is_empty() {
+        return Value::Null;
+    }
+    let last = keys.len() - 1;
+    let end = keys[last].frame as f64;
+    if frame <= end || playback.on_end == OnEnd::Hold {
+        return value_between(keys, frame, playback.blend);
+    }
+    let start_index = if playback.loop_range < 0 {
+        0
+    } else {
+        last.saturating_sub(playback.loop_range as usize)
+    };
+    let start = keys[start_index].frame as f64;
+    let span = end - start;
+    if span <= 0.0 {
+        return keys[last].value.clone();
+    }
+    let past = frame - start;
+    let local = match playback.on_end {
+        OnEnd::PingPong => {
+            let t = past.rem_euclid(span * 2.0);
+            if t <= span { t } else { span * 2.0 - t }
+        }
+        _ => past.rem_euclid(span),
+    };
+    let value = value_between(keys, start + local, playback.blend);
+    if playback.on_end == OnEnd::Wrap {
+        let delta = loop_delta(&keys[start_index].value, &keys[last].value);
+        return offset_value(&value, &delta, (past / span).floor());
+    }
+    value
+}
+
+/// Value of one serialized animator (`r`, or an entry of `animators`).
+fn animator_value(r: &Value, animated: bool, frame: f64, playback: Playback) -> Value {
+    match r {
+        Value::Object(map) => map.get("d").cloned().unwrap_or(Value::Null),
+        Value::Array(raw) => {
+            let keys: Vec<Keyframe> = raw.iter().filter_map(parse_key).collect();
+            if !animated {
+                return keys.first().map_or(Value::Null, |k| k.value.clone());
+            }
+            track_value(&keys, frame, playback)
+        }
+        _ => Value::Null,
+    }
+}
+
+/// The value an input takes at `frame`, following its keys, easing, per-axis
+/// animators and end behaviour. Connected inputs are refused, since their value
+/// comes from another node.
+pub fn get_input_value_at_in_pxc(
+    pxc: &PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    frame: f64,
+    registry: Option<&Registry>,
+) -> Result<Value> {
+    let (input, port) = input_ref(pxc, node_arg, input_slot, input_name, registry)?;
+    if let Some(from) = input.get("from_node").and_then(|v| v.as_str()) {
+        bail!(
+            "input is connected to {}; its value comes from that node",
+            from
+        );
+    }
+    let int_field = |key: &str| input.get(key).and_then(|v| v.as_f64()).map(|f| f as i64);
+    let playback = Playback {
+        on_end: OnEnd::from_index(int_field("on_end").unwrap_or(0)),
+        loop_range: int_field("loop_range").unwrap_or(-1),
+        blend: Blend::of(port),
+    };
+    // Saves without `anim` predate the flag; their key lists are animated.
+    let animated = input.get("anim").and_then(|v| v.as_bool()).unwrap_or(true);
+    let sep_axis = input.get("sep_axis").and_then(|v| v.as_bool()) == Some(true);
+    if sep_axis && let Some(axes) = input.get("animators").and_then(|v| v.as_array()) {
+        return Ok(Value::Array(
+            axes.iter()
+                .map(|axis| animator_value(axis, animated, frame, playback))
+                .collect(),
+        ));
+    }
+    Ok(input.get("r").map_or(Value::Null, |r| {
+        animator_value(r, animated, frame, playback)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+
+    fn project(input: Value) -> PxcFile {
+        let mut pxc = empty_pxc();
+        pxc.json["nodes"] = json!([{"id": "n", "type": "Node_T", "inputs": [input]}]);
+        pxc
+    }
+
+    fn input(pxc: &PxcFile) -> &Value {
+        &pxc.json["nodes"][0]["inputs"][0]
+    }
+
+    #[test]
+    fn keys_round_trip_through_the_animator_layout() {
+        let mut pxc = project(json!({"r": {"d": 1}}));
+        let bezier = KeyframeEase {
+            interp_out: Some(Interpolation::Bezier),
+            ease_out: Some([0.3, 0.0]),
+            ..Default::default()
+        };
+        set_keyframe_in_pxc(
+            &mut pxc,
+            "n",
+            Some(0),
+            None,
+            10,
+            Some(json!(8)),
+            &bezier,
+            None,
+        )
+        .unwrap();
+        let ease = KeyframeEase::default();
+        set_keyframe_in_pxc(&mut pxc, "n", Some(0), None, 0, Some(json!(2)), &ease, None).unwrap();
+        assert_eq!(input(&pxc)["anim"], true);
+        assert_eq!(
+            input(&pxc)["r"],
+            json!([
+                [0, 2, [0.0, 1.0], [0.0, 0.0], 0, 0, true],
+                [10, 8, [0.0, 1.0], [0.3, 0.0], 0, 1, true],
+            ])
+        );
+
+        move_keyframe_in_pxc(&mut pxc, "n", Some(0), None, 10, 20, None).unwrap();
+        assert!(move_keyframe_in_pxc(&mut pxc, "n", Some(0), None, 0, 20, None).is_err());
+        let keys = keyframes_in_pxc(&pxc, "n", Some(0), None, None).unwrap();
+        assert_eq!(keys[1].frame, 20);
+        assert_eq!(keys[1].interp_out, Interpolation::Bezier);
+
+        delete_keyframe_in_pxc(&mut pxc, "n", Some(0), None, 0, None).unwrap();
+        delete_keyframe_in_pxc(&mut pxc, "n", Some(0), None, 20, None).unwrap();
+        assert_eq!(input(&pxc)["r"], json!({"d": 8}));
+        assert!(input(&pxc).get("anim").is_none());
+    }
+
+    #[test]
+    fn toggling_animation_keeps_the_value() {
+        let mut pxc = project(json!({"r": {"d": [1, 2]}}));
+        set_animated_in_pxc(&mut pxc, "n", Some(0), None, true, None).unwrap();
+        assert_eq!(input(&pxc)["r"][0][0], 0);
+        assert_eq!(input(&pxc)["r"][0][1], json!([1, 2]));
+        set_animated_in_pxc(&mut pxc, "n", Some(0), None, false, None).unwrap();
+        assert_eq!(input(&pxc)["r"], json!({"d": [1, 2]}));
+    }
+
+    fn value_at(input: Value, frame: f64) -> Value {
+        get_input_value_at_in_pxc(&project(input), "n", Some(0), None, frame, None).unwrap()
+    }
+
+    #[test]
+    fn values_follow_easing_between_keys() {
+        let linear = json!({"anim": true, "r": [[0, 0.0], [10, 10.0]]});
+        assert_eq!(value_at(linear.clone(), -5.0), json!(0.0));
+        assert_eq!(value_at(linear.clone(), 2.5), json!(2.5));
+        assert_eq!(value_at(linear, 20.0), json!(10.0));
+
+        // Default handles are a straight line even when marked bezier.
+        let bezier =
+            json!({"r": [[0, 0.0, [0, 1], [0, 0], 1, 1], [10, 10.0, [0, 1], [0, 0], 1, 1]]});
+        let mid = value_at(bezier, 2.5).as_f64().unwrap();
+        assert!((mid - 2.5).abs() < 1e-6);
+
+        let eased =
+            json!({"r": [[0, 0.0, [0, 1], [1, 0], 1, 1], [10, 10.0, [1, 1], [0, 0], 1, 1]]});
+        assert!(value_at(eased, 2.5).as_f64().unwrap() < 1.0);
+
+        let hold = json!({"r": [[0, [1, 2], [0, 1], [0, 0], 0, 2], [10, [3, 4]]]});
+        assert_eq!(value_at(hold, 9.0), json!([1, 2]));
+
+        let unanimated = json!({"anim": false, "r": [[0, 1], [10, 2]]});
+        assert_eq!(value_at(unanimated, 10.0), json!(1));
+    }
+
+    #[test]
+    fn end_behaviour_and_axes() {
+        let keys = json!([[0, 0.0], [10, 10.0]]);
+        let with_end = |on_end: i64| json!({"on_end": on_end, "r": keys.clone()});
+        assert_eq!(value_at(with_end(0), 15.0), json!(10.0));
+        assert_eq!(value_at(with_end(1), 15.0), json!(5.0));
+        assert_eq!(value_at(with_end(2), 13.0), json!(7.0));
+        assert_eq!(value_at(with_end(3), 25.0), json!(25.0));
+
+        // Only the last interval repeats.
+        let ranged = json!({"on_end": 1, "loop_range": 1, "r": [[0, 0.0], [10, 10.0], [20, 0.0]]});
+        assert_eq!(value_at(ranged, 25.0), json!(5.0));
+
+        let axes = json!({
+            "sep_axis": true,
+            "r": {"d": [0, 0]},
+            "animators": [[[0, 0.0], [10, 10.0]], {"d": 7}],
+        });
+        assert_eq!(value_at(axes, 5.0), json!([5.0, 7]));
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+use crate::graph::collect_graph;
+use crate::ops::resolve_node_id;
+use crate::pxc::PxcFile;
+
+pub const DEFAULT_SPACING: (f64, f64) = (200.0, 160.0);
+
+const ORDERING_SWEEPS: usize = 8;
+
+/// One vertex of the layered graph; dummies route edges that skip layers.
+struct Vertex {
+    id: Option<String>,
+    layer: usize,
+}
+
+/// Layered (Sugiyama-style) layout of one graph scope. Returns each node's
+/// `(layer, row)` with rows centred around zero.
+fn layer_positions(ids: &[String], edges: &[(String, String)]) -> HashMap<String, (usize, f64)> {
+    let index: HashMap<&str, usize> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect();
+    let n = ids.len();
+    let mut succ: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (f, t) in edges {
+        if let (Some(&a), Some(&b)) = (index.get(f.as_str()), index.get(t.as_str()))
+            && a != b
+            && !succ[a].contains(&b)
+        {
+            succ[a].push(b);
+        }
+    }
+
+    // Break cycles by dropping edges that point back into the DFS stack.
+    let mut state = vec![0u8; n];
+    let mut acyclic: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for start in 0..n {
+        if state[start] != 0 {
+            continue;
+        }
+        let mut stack = vec![(start, 0usize)];
+        state[start] = 1;
+        while let Some((v, i)) = stack.pop() {
+            if i < succ[v].len() {
+                stack.push((v, i + 1));
+                let w = succ[v][i];
+                match state[w] {
+                    0 => {
+                        acyclic[v].push(w);
+                        state[w] = 1;
+                        stack.push((w, 0));
+                    }
+                    1 => {}
+                    _ => acyclic[v].push(w),
+                }
+            } else {
+                state[v] = 2;
+            }
+        }
+    }
+
+    // Longest-path layering.
+    let mut indegree = vec![0usize; n];
+    for targets in acyclic.iter() {
+        for &w in targets {
+            indegree[w] += 1;
+        }
+    }
+    let mut layer = vec![0usize; n];
+    let mut queue: Vec<usize> = (0..n).filter(|&v| indegree[v] == 0).collect();
+    let mut head = 0;
+    while head < queue.len() {
+        let v = queue[head];
+        head += 1;
+        for &w in acyclic[v].iter() {
+            layer[w] = layer[w].max(layer[v] + 1);
+            indegree[w] -= 1;
+            if indegree[w] == 0 {
+                queue.push(w);
+            }
+        }
+    }
+
+    // Split long edges with dummy vertices so every edge spans one layer.
+    let mut vertices: Vec<Vertex> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| Vertex {
+            id: Some(id.clone()),
+            layer: layer[i],
+        })
+        .collect();
+    let mut links: Vec<(usize, usize)> = Vec::new();
+    for (v, targets) in acyclic.iter().enumerate() {
+        for &w in targets {
+            let mut prev = v;
+            for l in layer[v] + 1..layer[w] {
+                vertices.push(Vertex { id: None, layer: l });
+                let d = vertices.len() - 1;
+                links.push((prev, d));
+                prev = d;
+            }
+            links.push((prev, w));
+        }
+    }
+
+    let layer_count = vertices.iter().map(|v| v.layer + 1).max().unwrap_or(0);
+    let mut layers: Vec<Vec<usize>> = vec![Vec::new(); layer_count];
+    for (i, v) in vertices.iter().enumerate() {
+        layers[v.layer].push(i);
+    }
+    let mut preds: Vec<Vec<usize>> = vec![Vec::new(); vertices.len()];
+    let mut succs: Vec<Vec<usize>> = vec![Vec::new(); vertices.len()];
+    for &(a, b) in links.iter() {
+        succs[a].push(b);
+        preds[b].push(a);
+    }
+
+    // Crossing reduction: alternate downward and upward barycenter sweeps.
+    let mut pos = vec![0f64; vertices.len()];
+    for layer in layers.iter() {
+        for (i, &v) in layer.iter().enumerate() {
+            pos[v] = i as f64;
+        }
+    }
+    for sweep in 0..ORDERING_SWEEPS {
+        let down = sweep % 2 == 0;
+        let order: Vec<usize> = if down {
+            (1..layer_count).collect()
+        } else {
+            (0..layer_count.saturating_sub(1)).rev().collect()
+        };
+        for l in order {
+            let neighbours = if down { &preds } else { &succs };
+            let mut keyed: Vec<(f64, usize)> = layers[l]
+                .iter()
+                .map(|&v| {
+                    let adj = &neighbours[v];
+                    let bary = if adj.is_empty() {
+                        pos[v]
+                    } else {
+                        adj.iter().map(|&u| pos[u]).sum::<f64>() / adj.len() as f64
+                    };
+                    (bary, v)
+                })
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0).then(pos[a.1].total_cmp(&pos[b.1])));
+            layers[l] = keyed.into_iter().map(|(_, v)| v).collect();
+            for (i, &v) in layers[l].iter().enumerate() {
+                pos[v] = i as f64;
+            }
+        }
+    }
+
+    let mut out = HashMap::new();
+    for layer in layers.iter() {
+        let centre = (layer.len() as f64 - 1.0) / 2.0;
+        for (i, &v) in layer.iter().enumerate() {
+            if let Some(id) = &vertices[v].id {
+                out.insert(id.clone(), (vertices[v].layer, i as f64 - centre));
+            }
+        }
+    }
+    out
+}
+
+/// Assigns `x`/`y` to nodes from a layered layout of the connection graph. Each
+/// group's contents are laid out on their own. `only` restricts which nodes
+/// move, and `origin_only` limits it further to nodes still at (0, 0). Returns
+/// the number of nodes positioned.
+pub fn auto_layout_pxc(
+    pxc: &mut PxcFile,
+    only: Option<&[&str]>,
+    spacing: (f64, f64),
+    origin_only: bool,
+) -> Result<usize> {
+    let nodes = pxc
+        .json
+        .get("nodes")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("no nodes array found"))?;
+    let graph = collect_graph(nodes, true, false, None)?;
+
+    let mut selected: Option<HashSet<String>> = None;
+    if let Some(only) = only {
+        let mut set = HashSet::new();
+        for n in only {
+            let id = resolve_node_id(n, nodes).ok_or_else(|| anyhow!("node not found: {}", n))?;
+            set.insert(id);
+        }
+        selected = Some(set);
+    }
+
+    let mut scopes: BTreeMap<Option<String>, Vec<String>> = BTreeMap::new();
+    let mut positions: HashMap<String, (f64, f64)> = HashMap::new();
+    for node in nodes {
+        let Some(id) = node.get("id").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let x = node.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        let y = node.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        positions.insert(id.to_string(), (x, y));
+        scopes
+            .entry(graph.parents.get(id).cloned())
+            .or_default()
+            .push(id.to_string());
+    }
+    let edges: Vec<(String, String)> = graph
+        .edges
+        .iter()
+        .filter_map(|e| {
+            let f = e.get("f").and_then(|v| v.as_str())?;
+            let t = e.get("t").and_then(|v| v.as_str())?;
+            Some((f.to_string(), t.to_string()))
+        })
+        .collect();
+
+    let mut placed: HashMap<String, (f64, f64)> = HashMap::new();
+    for ids in scopes.values() {
+        let movable: Vec<&String> = ids
+            .iter()
+            .filter(|id| selected.as_ref().is_none_or(|s| s.contains(*id)))
+            .filter(|id| !origin_only || positions[*id] == (0.0, 0.0))
+            .collect();
+        if movable.is_empty() {
+            continue;
+        }
+        let layout = layer_positions(ids, &edges);
+        let fixed: Vec<(f64, f64, usize)> = ids
+            .iter()
+            .filter(|id| !movable.contains(id))
+            .map(|id| {
+                let (x, y) = positions[id];
+                (x, y, layout.get(id).map(|p| p.0).unwrap_or(0))
+            })
+            .collect();
+        // Line the layers up with nodes that stay put, and stack moved nodes below them.
+        let (base_x, base_y) = if fixed.is_empty() {
+            (0.0, 0.0)
+        } else {
+            (
+                fixed
+                    .iter()
+                    .map(|(x, _, layer)| x - *layer as f64 * spacing.0)
+                    .sum::<f64>()
+                    / fixed.len() as f64,
+                fixed.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max) + spacing.1,
+            )
+        };
+        let min_row = movable
+            .iter()
+            .filter_map(|id| layout.get(*id).map(|p| p.1))
+            .fold(f64::INFINITY, f64::min);
+        for id in movable {
+            if let Some(&(layer, row)) = layout.get(id) {
+                let x = base_x + layer as f64 * spacing.0;
+                let y = base_y + (row - min_row) * spacing.1;
+                placed.insert(id.clone(), (x.round(), y.round()));
+            }
+        }
+    }
+
+    let count = placed.len();
+    if let Some(nodes) = pxc.json.get_mut("nodes").and_then(|v| v.as_array_mut()) {
+        for node in nodes.iter_mut() {
+            let Some(id) = node.get("id").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            if let Some(&(x, y)) = placed.get(id) {
+                node["x"] = Value::Number((x as i64).into());
+                node["y"] = Value::Number((y as i64).into());
+            }
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+    use serde_json::json;
+
+    fn layers(ids: &[&str], edges: &[(&str, &str)]) -> Vec<usize> {
+        let ids: Vec<String> = ids.iter().map(|s| s.to_string()).collect();
+        let edges: Vec<(String, String)> = edges
+            .iter()
+            .map(|(f, t)| (f.to_string(), t.to_string()))
+            .collect();
+        let layout = layer_positions(&ids, &edges);
+        ids.iter().map(|id| layout[id].0).collect()
+    }
+
+    #[test]
+    fn layers_follow_the_longest_path() {
+        // `a -> c` is skipped over by `a -> b -> c`; `d` stands alone.
+        let edges = [("a", "b"), ("b", "c"), ("a", "c")];
+        assert_eq!(layers(&["a", "b", "c", "d"], &edges), [0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn cycles_are_broken() {
+        let edges = [("a", "b"), ("b", "c"), ("c", "a")];
+        assert_eq!(layers(&["a", "b", "c"], &edges), [0, 1, 2]);
+    }
+
+    #[test]
+    fn origin_only_keeps_placed_nodes() {
+        let mut pxc = empty_pxc();
+        pxc.json["nodes"] = json!([
+            {"id": "a", "type": "Node_T", "x": 400, "y": 0, "outputs": [{}]},
+            {"id": "b", "type": "Node_T", "x": 0, "y": 0, "outputs": [{}],
+             "inputs": [{"from_node": "a", "from_index": 0}]},
+        ]);
+        let moved = auto_layout_pxc(&mut pxc, None, DEFAULT_SPACING, true).unwrap();
+        assert_eq!(moved, 1);
+        let at = |i: usize| {
+            (
+                pxc.json["nodes"][i]["x"].clone(),
+                pxc.json["nodes"][i]["y"].clone(),
+            )
+        };
+        assert_eq!(at(0), (json!(400), json!(0)));
+        // One layer right of `a`, on a row below it.
+        assert_eq!(at(1), (json!(600), json!(160)));
+    }
+}
@@ -1,17 +1,76 @@
+mod anim;
+mod apply;
 mod cli;
 mod color;
+mod cytoscape;
+mod diff;
+mod dsl;
+mod dynamic;
+mod favorites;
+mod globals;
+mod gml;
 mod graph;
+mod graphml;
+mod group;
 mod ids;
+mod keyframe;
+mod layout;
+mod lint;
+mod metadata;
+mod migrate;
+mod notes;
 mod ops;
+mod palette;
 mod pxc;
 mod registry;
+mod svg;
 
 #[cfg(feature = "python")]
 mod python;
 
+pub use anim::{
+    AnimationSettings, RetimeReport, animation_settings, retime_pxc, set_animation_settings,
+};
+pub use apply::apply_graph_json_to_pxc;
 pub use cli::run;
 pub use color::hue_set_pxc;
+pub use diff::{NodeDiff, PortDiff, RegistryDiff, diff_registries};
+pub use dsl::build_graph_into_pxc;
+pub use dynamic::{add_input_group_in_pxc, remove_input_group_in_pxc};
+pub use favorites::{
+    CustomPanel, InputRef, add_favorite_in_pxc, add_panel_to_pxc, favorites_in_pxc, panels_in_pxc,
+    prune_input_refs_in_pxc, remove_favorite_in_pxc, remove_panel_from_pxc,
+};
+pub use globals::{
+    GlobalEdit, GlobalType, GlobalVar, add_global_to_pxc, get_global_in_pxc, globals_in_pxc,
+    remove_global_from_pxc, set_global_in_pxc,
+};
 pub use graph::{GraphFormat, GraphMode, graph_json};
-pub use ops::{get_input_value_in_pxc, set_input_value_in_pxc};
+pub use group::{group_nodes_in_pxc, ungroup_in_pxc};
+pub use keyframe::{
+    Interpolation, Keyframe, KeyframeEase, delete_keyframe_in_pxc, get_input_value_at_in_pxc,
+    keyframes_in_pxc, move_keyframe_in_pxc, set_animated_in_pxc, set_keyframe_in_pxc,
+};
+pub use layout::{DEFAULT_SPACING, auto_layout_pxc};
+pub use lint::{LintIssue, lint_pxc};
+pub use metadata::{
+    MetaEdit, Metadata, metadata_in_pxc, project_files, set_metadata_in_dir, set_metadata_in_pxc,
+};
+pub use migrate::{MigrationIssue, MigrationReport, migrate_pxc};
+pub use notes::{
+    Note, NoteEdit, add_note_to_pxc, edit_note_in_pxc, notes_in_pxc, parse_note_color,
+    remove_note_from_pxc,
+};
+pub use ops::{
+    add_node_to_pxc, get_input_expression_in_pxc, get_input_value_in_pxc,
+    set_input_expression_in_pxc, set_input_value_in_pxc,
+};
+pub use palette::{
+    ColorKind, ColorUse, NodeRemap, PaletteColor, PaletteFormat, PaletteMap, palette_in_pxc,
+    parse_rgb, remap_palette_in_pxc, render_palette,
+};
 pub use pxc::{Header, Meta, PxcFile, Thumbnail, parse_pxc, read_pxc, write_pxc};
-pub use registry::{Registry, RegistryNode, RegistryPort, embedded_registry, load_registry};
+pub use registry::{
+    Registry, RegistryChoice, RegistryNode, RegistryPort, embedded_registry, load_registry,
+    registry_choice,
+};
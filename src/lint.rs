@@ -0,0 +1,202 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::ops::{default_value_for_port, input_expression};
+use crate::pxc::PxcFile;
+use crate::registry::{Registry, RegistryPort};
+
+/// A likely mistake in a project; nothing is changed.
+#[derive(Clone, Debug, Serialize)]
+pub struct LintIssue {
+    pub node: String,
+    pub node_type: String,
+    pub input: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_name: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}) input {}", self.node, self.node_type, self.input)?;
+        if let Some(name) = &self.input_name {
+            write!(f, " \"{}\"", name)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Numbers compare by value, so a saved `1.0` matches a default of `1`.
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(a, b)| same_value(a, b))
+        }
+        _ => a == b,
+    }
+}
+
+/// Why an expression-driven input's own value or connection has no effect.
+fn expression_issue(input: &Value, expression: &str, default: Option<&Value>) -> Option<String> {
+    if input.get("from_node").and_then(|v| v.as_str()).is_some() {
+        return Some(format!(
+            "connected, so its expression {:?} is ignored",
+            expression
+        ));
+    }
+    let r = input.get("r")?;
+    if let Some(keys) = r.as_array()
+        && !keys.is_empty()
+        && input.get("anim").and_then(|v| v.as_bool()) != Some(false)
+    {
+        return Some(format!(
+            "{} keyframes are ignored while expression {:?} drives it",
+            keys.len(),
+            expression
+        ));
+    }
+    let value = r.get("d")?;
+    let default = default?;
+    if same_value(value, default) {
+        return None;
+    }
+    Some(format!(
+        "literal value {} is ignored while expression {:?} drives it",
+        value, expression
+    ))
+}
+
+/// A static value (or keyframe) outside the port's slider range. Pixel
+/// Composer accepts it, but the slider can't show it.
+fn range_issue(input: &Value, port: &RegistryPort) -> Option<String> {
+    if input.get("from_node").and_then(|v| v.as_str()).is_some() {
+        return None;
+    }
+    match input.get("r")? {
+        Value::Array(keys) => keys
+            .iter()
+            .filter_map(|k| k.as_array()?.get(1))
+            .find_map(|v| port.range_issue(v)),
+        r => port.range_issue(r.get("d").unwrap_or(r)),
+    }
+}
+
+/// Checks every node input. A value counts as set when it differs from the
+/// port's default, or from the default a new node would get when the registry
+/// has none; slider ranges are only checked on static values.
+pub fn lint_pxc(pxc: &PxcFile, registry: Option<&Registry>) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let nodes = pxc.json.get("nodes").and_then(|v| v.as_array());
+    for node in nodes.into_iter().flatten() {
+        let id = node.get("id").and_then(|v| v.as_str()).unwrap_or("");
+        let node_type = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        let reg_node = registry.and_then(|r| r.nodes.get(node_type));
+        let inputs = node.get("inputs").and_then(|v| v.as_array());
+        for (slot, input) in inputs.into_iter().flatten().enumerate() {
+            let port = reg_node.and_then(|n| n.input(slot));
+            let message = match input_expression(input) {
+                Some(expression) => {
+                    let default = port.map(default_value_for_port).filter(|d| !d.is_null());
+                    expression_issue(input, expression, default.as_ref())
+                }
+                None => port.and_then(|p| range_issue(input, p)),
+            };
+            if let Some(message) = message {
+                issues.push(LintIssue {
+                    node: id.to_string(),
+                    node_type: node_type.to_string(),
+                    input: slot,
+                    input_name: reg_node.and_then(|n| n.input_name(slot)),
+                    message,
+                });
+            }
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+    use crate::registry::RegistryNode;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn literal_on_expression_input_is_reported() {
+        let default = json!(1);
+        let expr = |extra: Value| {
+            let mut input = json!({"global_use": true, "global_key": "size * 2"});
+            for (k, v) in extra.as_object().unwrap() {
+                input[k] = v.clone();
+            }
+            input
+        };
+        let unchanged = expr(json!({"r": {"d": 1.0}}));
+        assert_eq!(
+            expression_issue(&unchanged, "size * 2", Some(&default)),
+            None
+        );
+        let literal = expr(json!({"r": {"d": 4}}));
+        let message = expression_issue(&literal, "size * 2", Some(&default)).unwrap();
+        assert!(message.starts_with("literal value 4"));
+        let keyed = expr(json!({"anim": true, "r": [[0, 1], [10, 2]]}));
+        assert!(
+            expression_issue(&keyed, "size * 2", None)
+                .unwrap()
+                .starts_with("2 keyframes")
+        );
+        // Without `global_use` the key is only a stored draft.
+        assert_eq!(input_expression(&json!({"global_key": "x"})), None);
+    }
+
+    #[test]
+    fn literal_is_reported_without_a_registry_default() {
+        let port = RegistryPort {
+            name: Some("Strength".to_string()),
+            ty: Some("nodeValue_Float".to_string()),
+            ..Default::default()
+        };
+        let node = RegistryNode {
+            inputs: vec![port],
+            ..Default::default()
+        };
+        let registry = Registry {
+            nodes: HashMap::from([("Node_T".to_string(), node)]),
+        };
+        let mut pxc = empty_pxc();
+        let input = |d: Value| json!({"global_use": true, "global_key": "t", "r": {"d": d}});
+        pxc.json["nodes"] = json!([
+            {"id": "a", "type": "Node_T", "inputs": [input(json!(0))]},
+            {"id": "b", "type": "Node_T", "inputs": [input(json!(0.5))]},
+        ]);
+        let issues = lint_pxc(&pxc, Some(&registry));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].node, "b");
+        assert_eq!(issues[0].input_name.as_deref(), Some("Strength"));
+        assert!(issues[0].message.starts_with("literal value 0.5"));
+    }
+
+    #[test]
+    fn out_of_range_values_are_warnings() {
+        let port = RegistryPort {
+            range: Some((0.0, 1.0)),
+            ..Default::default()
+        };
+        assert_eq!(range_issue(&json!({"r": {"d": 0.5}}), &port), None);
+        let message = range_issue(&json!({"r": {"d": 2}}), &port).unwrap();
+        assert_eq!(message, "2 is outside the slider range 0..1");
+        assert!(range_issue(&json!({"r": [[0, 0.5], [10, -1]]}), &port).is_some());
+        let linked = json!({"from_node": "a", "r": {"d": 2}});
+        assert_eq!(range_issue(&linked, &port), None);
+        // Without a range the port takes anything.
+        assert_eq!(
+            range_issue(&json!({"r": {"d": 2}}), &RegistryPort::default()),
+            None
+        );
+    }
+}
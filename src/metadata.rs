@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use walkdir::WalkDir;
+
+use crate::pxc::{PxcFile, read_pxc, write_pxc};
+
+/// The project's `metadata` struct, as `MetaDataManager.serialize` writes it.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Metadata {
+    pub author: String,
+    pub description: String,
+    pub contact: String,
+    pub alias: String,
+    pub tags: Vec<String>,
+    /// Keys without a typed field (`version`, `aut_id`, `file_id`, ...).
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// Changes to the metadata; fields left as `None` are kept. `add_tags` and
+/// `remove_tags` apply after `tags` replaces the list.
+#[derive(Clone, Debug, Default)]
+pub struct MetaEdit {
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub contact: Option<String>,
+    pub alias: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+}
+
+const TEXT_FIELDS: [&str; 4] = ["author", "description", "contact", "alias"];
+
+pub fn metadata_in_pxc(pxc: &PxcFile) -> Metadata {
+    let mut other = pxc
+        .json
+        .get("metadata")
+        .and_then(|v| v.as_object())
+        .cloned()
+        .unwrap_or_default();
+    let mut text = |key: &str| match other.remove(key) {
+        Some(Value::String(s)) => s,
+        Some(Value::Null) | None => String::new(),
+        Some(v) => v.to_string(),
+    };
+    let (author, description, contact, alias) = (
+        text("author"),
+        text("description"),
+        text("contact"),
+        text("alias"),
+    );
+    let tags = match other.remove("tags") {
+        Some(Value::Array(tags)) => tags
+            .iter()
+            .filter_map(|t| t.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    };
+    Metadata {
+        author,
+        description,
+        contact,
+        alias,
+        tags,
+        other,
+    }
+}
+
+/// Applies `edit` to the project's metadata. Returns whether anything changed.
+pub fn set_metadata_in_pxc(pxc: &mut PxcFile, edit: &MetaEdit) -> Result<bool> {
+    let root = pxc
+        .json
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("project JSON is not an object"))?;
+    let meta = root.entry("metadata").or_insert_with(|| json!({}));
+    if !meta.is_object() {
+        *meta = json!({});
+    }
+    let meta = meta.as_object_mut().expect("metadata is an object");
+    let before = meta.clone();
+    let values = [&edit.author, &edit.description, &edit.contact, &edit.alias];
+    for (key, value) in TEXT_FIELDS.iter().zip(values) {
+        if let Some(value) = value {
+            meta.insert(key.to_string(), json!(value));
+        }
+    }
+    if edit.tags.is_some() || !edit.add_tags.is_empty() || !edit.remove_tags.is_empty() {
+        let mut tags: Vec<String> = match &edit.tags {
+            Some(tags) => tags.clone(),
+            None => meta
+                .get("tags")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|t| t.as_str().map(str::to_string))
+                .collect(),
+        };
+        for tag in edit.add_tags.iter() {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        tags.retain(|t| !edit.remove_tags.contains(t));
+        meta.insert("tags".to_string(), json!(tags));
+    }
+    Ok(*meta != before)
+}
+
+/// Every `.pxc` file under `dir`, sorted by path.
+pub fn project_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("pxc"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Applies `edit` to every project under `dir`. Projects are rewritten in
+/// place, or written to the same relative path under `out_dir`. Returns each
+/// project with whether its metadata changed; unchanged projects are not
+/// rewritten in place.
+pub fn set_metadata_in_dir(
+    dir: &Path,
+    edit: &MetaEdit,
+    out_dir: Option<&Path>,
+) -> Result<Vec<(PathBuf, bool)>> {
+    let mut results = Vec::new();
+    for path in project_files(dir) {
+        let mut pxc = read_pxc(&path).with_context(|| format!("reading {}", path.display()))?;
+        let changed = set_metadata_in_pxc(&mut pxc, edit)?;
+        match out_dir {
+            Some(out_dir) => {
+                let target = out_dir.join(path.strip_prefix(dir).unwrap_or(&path));
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                write_pxc(&target, &pxc, true)?;
+            }
+            None if changed => write_pxc(&path, &pxc, true)?,
+            None => {}
+        }
+        results.push((path, changed));
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+
+    #[test]
+    fn edits_are_typed_and_keep_other_keys() {
+        let mut pxc = empty_pxc();
+        pxc.json["metadata"] = json!({"author": "A", "tags": ["wip", "fx"], "version": 1800});
+        let edit = MetaEdit {
+            description: Some("Glow \"pack\"".to_string()),
+            add_tags: vec!["cc-by".to_string(), "fx".to_string()],
+            remove_tags: vec!["wip".to_string()],
+            ..Default::default()
+        };
+        assert!(set_metadata_in_pxc(&mut pxc, &edit).unwrap());
+        assert!(!set_metadata_in_pxc(&mut pxc, &edit).unwrap());
+
+        let meta = metadata_in_pxc(&pxc);
+        assert_eq!(meta.author, "A");
+        assert_eq!(meta.description, "Glow \"pack\"");
+        assert_eq!(meta.tags, ["fx", "cc-by"]);
+        assert_eq!(meta.other["version"], 1800);
+    }
+}
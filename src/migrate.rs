@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::diff::{diff_ports, match_ports};
+use crate::favorites::update_input_refs;
+use crate::graph::BYPASS_INDEX_OFFSET;
+use crate::group::{input_link, set_input_link};
+use crate::ops::default_input;
+use crate::pxc::PxcFile;
+use crate::registry::{Registry, RegistryNode, RegistryPort};
+
+/// Something `migrate_pxc` could not map by name.
+#[derive(Clone, Debug)]
+pub struct MigrationIssue {
+    pub node: String,
+    pub node_type: String,
+    pub message: String,
+}
+
+impl fmt::Display for MigrationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.node, self.node_type, self.message)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MigrationReport {
+    /// Nodes whose inputs or outputs were rewritten.
+    pub nodes: usize,
+    /// Connections whose `from_index` was rewritten or removed.
+    pub links: usize,
+    pub issues: Vec<MigrationIssue>,
+}
+
+fn port_name(port: &RegistryPort) -> String {
+    port.name
+        .as_deref()
+        .map_or("(unnamed)".to_string(), |n| format!("\"{}\"", n))
+}
+
+/// Old output slot -> new output slot, or `None` when the outputs did not change.
+fn output_map(old: &RegistryNode, new: &RegistryNode) -> Option<Vec<Option<usize>>> {
+    if diff_ports(&old.outputs, &new.outputs).is_empty() {
+        return None;
+    }
+    let mut map = vec![None; old.outputs.len()];
+    for (o, n) in match_ports(&old.outputs, &new.outputs) {
+        if let Some(o) = o {
+            map[o] = n;
+        }
+    }
+    Some(map)
+}
+
+/// Moves the values of one run of ports (the fixed inputs, or one dynamic group)
+/// to their new slots. Ports without a match keep their registry default.
+fn remap_ports(
+    values: &[Value],
+    old: &[RegistryPort],
+    new: &[RegistryPort],
+    label: &str,
+    issues: &mut Vec<String>,
+) -> Vec<Value> {
+    let mut out: Vec<Value> = new.iter().map(default_input).collect();
+    for (o, n) in match_ports(old, new) {
+        let Some(o) = o else { continue };
+        let Some(value) = values.get(o) else { continue };
+        let Some(n) = n else {
+            let linked = input_link(value).map_or(String::new(), |(from, _, _)| {
+                format!(" and its link from {}", from)
+            });
+            issues.push(format!(
+                "{} {} {} has no match; value{} dropped",
+                label,
+                o,
+                port_name(&old[o]),
+                linked
+            ));
+            continue;
+        };
+        if old[o].name != new[n].name {
+            issues.push(format!(
+                "{} {} {} mapped by slot to {}",
+                label,
+                o,
+                port_name(&old[o]),
+                port_name(&new[n])
+            ));
+        }
+        if old[o].ty.is_some() && new[n].ty.is_some() && old[o].ty != new[n].ty {
+            issues.push(format!(
+                "{} {} {} changed type {} -> {}",
+                label,
+                n,
+                port_name(&new[n]),
+                old[o].ty.as_deref().unwrap_or("?"),
+                new[n].ty.as_deref().unwrap_or("?")
+            ));
+        }
+        out[n] = value.clone();
+    }
+    out
+}
+
+/// New `inputs` array for one node, or an error message when the saved layout
+/// does not fit the old registry. `slots` gets the new slot of each old input.
+fn remap_inputs(
+    node: &Value,
+    old: &RegistryNode,
+    new: &RegistryNode,
+    issues: &mut Vec<String>,
+    slots: &mut Vec<Option<usize>>,
+) -> std::result::Result<Vec<Value>, String> {
+    let inputs = node
+        .get("inputs")
+        .and_then(|v| v.as_array())
+        .map(|a| a.as_slice())
+        .unwrap_or(&[]);
+    let fix_len = old.inputs.len();
+    if let Some(saved) = node.get("input_fix_len").and_then(|v| v.as_u64())
+        && !old.dynamic_inputs.is_empty()
+        && saved as usize != fix_len
+    {
+        return Err(format!(
+            "input_fix_len {} does not match {} fixed inputs in the old registry",
+            saved, fix_len
+        ));
+    }
+    let split = fix_len.min(inputs.len());
+    let (fixed, tail) = inputs.split_at(split);
+    *slots = vec![None; inputs.len()];
+    for (o, n) in match_ports(&old.inputs, &new.inputs) {
+        if let Some(o) = o.filter(|o| *o < split) {
+            slots[o] = n;
+        }
+    }
+    let mut out = remap_ports(fixed, &old.inputs, &new.inputs, "input", issues);
+    if old.dynamic_inputs.is_empty() {
+        // Extra inputs the registry does not describe (group junctions and the
+        // like) are kept as they are.
+        for (i, slot) in slots.iter_mut().enumerate().skip(split) {
+            *slot = Some(out.len() + i - split);
+        }
+        out.extend(tail.iter().cloned());
+        return Ok(out);
+    }
+    let data_len = old.dynamic_inputs.len();
+    if !tail.len().is_multiple_of(data_len) {
+        return Err(format!(
+            "{} dynamic inputs, not a multiple of the group size {}",
+            tail.len(),
+            data_len
+        ));
+    }
+    let pairs = match_ports(&old.dynamic_inputs, &new.dynamic_inputs);
+    for (index, group) in tail.chunks(data_len).enumerate() {
+        for &(o, n) in pairs.iter() {
+            if let Some(o) = o {
+                slots[split + index * data_len + o] = n.map(|n| out.len() + n);
+            }
+        }
+        let label = format!("group {} input", index);
+        out.extend(remap_ports(
+            group,
+            &old.dynamic_inputs,
+            &new.dynamic_inputs,
+            &label,
+            issues,
+        ));
+    }
+    Ok(out)
+}
+
+/// Rewrites every node from the slot layout of `from` to that of `to`: inputs
+/// follow their port by name, outputs are reordered and connections to moved
+/// outputs get the new `from_index`. Whatever could not be matched by name is
+/// listed in the report.
+pub fn migrate_pxc(pxc: &mut PxcFile, from: &Registry, to: &Registry) -> Result<MigrationReport> {
+    let nodes = pxc
+        .json
+        .get_mut("nodes")
+        .and_then(|v| v.as_array_mut())
+        .ok_or_else(|| anyhow!("no nodes array found"))?;
+    let mut report = MigrationReport::default();
+    let mut output_maps: HashMap<String, Vec<Option<usize>>> = HashMap::new();
+    let mut input_maps: HashMap<String, Vec<Option<usize>>> = HashMap::new();
+
+    for node in nodes.iter_mut() {
+        let id = node
+            .get("id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let node_type = node
+            .get("type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let issue = |message: String| MigrationIssue {
+            node: id.clone(),
+            node_type: node_type.clone(),
+            message,
+        };
+        let (old, new) = match (from.nodes.get(&node_type), to.nodes.get(&node_type)) {
+            (Some(old), Some(new)) => (old, new),
+            (Some(_), None) => {
+                report
+                    .issues
+                    .push(issue("not in the new registry; left as is".to_string()));
+                continue;
+            }
+            (None, _) => {
+                report
+                    .issues
+                    .push(issue("not in the old registry; left as is".to_string()));
+                continue;
+            }
+        };
+        let mut changed = false;
+
+        let inputs_changed = !diff_ports(&old.inputs, &new.inputs).is_empty()
+            || !diff_ports(&old.dynamic_inputs, &new.dynamic_inputs).is_empty();
+        if inputs_changed {
+            let mut messages = Vec::new();
+            let mut slots = Vec::new();
+            match remap_inputs(node, old, new, &mut messages, &mut slots) {
+                Ok(inputs) => {
+                    input_maps.insert(id.clone(), slots);
+                    let map = node.as_object_mut().expect("node is an object");
+                    if map.get("inputs").and_then(|v| v.as_array()) != Some(&inputs) {
+                        map.insert("inputs".to_string(), Value::Array(inputs));
+                        changed = true;
+                    }
+                    let saved_fix_len = map.get("input_fix_len").and_then(|v| v.as_u64());
+                    if saved_fix_len == Some(old.inputs.len() as u64)
+                        || !new.dynamic_inputs.is_empty()
+                    {
+                        map.insert("input_fix_len".to_string(), json!(new.inputs.len()));
+                    }
+                    if !new.dynamic_inputs.is_empty() {
+                        map.insert("data_length".to_string(), json!(new.dynamic_inputs.len()));
+                    }
+                }
+                Err(message) => messages.push(format!("{}; left as is", message)),
+            }
+            report.issues.extend(messages.into_iter().map(&issue));
+        }
+
+        if let Some(map) = output_map(old, new) {
+            if let Some(outputs) = node.get_mut("outputs").and_then(|v| v.as_array_mut()) {
+                let mut moved = vec![json!({}); new.outputs.len()];
+                for (o, n) in map.iter().enumerate() {
+                    if let (Some(n), Some(value)) = (n, outputs.get(o)) {
+                        moved[*n] = value.clone();
+                    }
+                }
+                moved.extend(outputs.iter().skip(map.len()).cloned());
+                if *outputs != moved {
+                    *outputs = moved;
+                    changed = true;
+                }
+            }
+            output_maps.insert(id.clone(), map);
+        }
+        if changed {
+            report.nodes += 1;
+        }
+    }
+
+    if output_maps.is_empty() {
+        migrate_input_refs(pxc, &input_maps);
+        return Ok(report);
+    }
+    for node in nodes.iter_mut() {
+        let id = node
+            .get("id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let node_type = node
+            .get("type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let Some(inputs) = node.get_mut("inputs").and_then(|v| v.as_array_mut()) else {
+            continue;
+        };
+        for (slot, input) in inputs.iter_mut().enumerate() {
+            let Some((from_id, from_index, tag)) = input_link(input) else {
+                continue;
+            };
+            let Some(map) = output_maps.get(&from_id) else {
+                continue;
+            };
+            let (offset, index) = if from_index >= BYPASS_INDEX_OFFSET {
+                (BYPASS_INDEX_OFFSET, from_index - BYPASS_INDEX_OFFSET)
+            } else {
+                (0, from_index)
+            };
+            // Outputs past the old registry's list are dynamic and stay put.
+            let Some(mapped) = usize::try_from(index).ok().and_then(|i| map.get(i)) else {
+                continue;
+            };
+            match mapped {
+                Some(new_index) if *new_index as i64 == index => {}
+                Some(new_index) => {
+                    let link = (from_id, *new_index as i64 + offset, tag);
+                    set_input_link(input, Some(&link));
+                    report.links += 1;
+                }
+                None => {
+                    set_input_link(input, None);
+                    report.links += 1;
+                    report.issues.push(MigrationIssue {
+                        node: id.clone(),
+                        node_type: node_type.clone(),
+                        message: format!(
+                            "input {} was linked to output {} of {}, which no longer exists; disconnected",
+                            slot, index, from_id
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    migrate_input_refs(pxc, &input_maps);
+    Ok(report)
+}
+
+/// Moves favourites and panel entries along with their inputs; entries of
+/// dropped inputs are removed.
+fn migrate_input_refs(pxc: &mut PxcFile, input_maps: &HashMap<String, Vec<Option<usize>>>) {
+    update_input_refs(pxc, &|id, slot| match input_maps.get(id) {
+        Some(map) => map.get(slot).copied().flatten(),
+        None => Some(slot),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+
+    fn port(name: &str) -> RegistryPort {
+        RegistryPort {
+            name: Some(name.to_string()),
+            ty: Some("nodeValue_Float".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn registry(inputs: &[&str], outputs: &[&str]) -> Registry {
+        let node = RegistryNode {
+            inputs: inputs.iter().map(|n| port(n)).collect(),
+            outputs: outputs.iter().map(|n| port(n)).collect(),
+            ..Default::default()
+        };
+        Registry {
+            nodes: HashMap::from([("Node_T".to_string(), node)]),
+        }
+    }
+
+    #[test]
+    fn inputs_and_links_follow_their_ports() {
+        let from = registry(&["A", "B", "C"], &["Out", "Mask"]);
+        let to = registry(&["A", "New", "B"], &["Preview", "Out"]);
+        let mut pxc = empty_pxc();
+        pxc.json["nodes"] = json!([
+            {"id": "p", "type": "Node_T", "inputs": [{"r": {"d": 1}}, {"r": {"d": 2}}, {"r": {"d": 3}}], "outputs": [{}, {}]},
+            {"id": "c", "type": "Node_T", "inputs": [
+                {"from_node": "p", "from_index": 0},
+                {"from_node": "p", "from_index": 1},
+                {"from_node": "p", "from_index": 1000},
+            ]},
+        ]);
+        pxc.json["favVal"] = json!([["p", 1], ["p", 2]]);
+        let report = migrate_pxc(&mut pxc, &from, &to).unwrap();
+
+        let nodes = &pxc.json["nodes"];
+        assert_eq!(nodes[0]["inputs"][0]["r"]["d"], 1);
+        assert_eq!(nodes[0]["inputs"][2]["r"]["d"], 2);
+        // The consumer's first input stays put and now points at the moved output.
+        assert_eq!(nodes[1]["inputs"][0]["from_index"], 1);
+        // Its second input moved to slot 2; the output it used is gone.
+        assert!(nodes[1]["inputs"][2].get("from_node").is_none());
+        assert_eq!(report.links, 2);
+        // Favourites follow "B" to slot 2 and drop with "C".
+        assert_eq!(pxc.json["favVal"], json!([["p", 2]]));
+        let messages: Vec<String> = report.issues.iter().map(|i| i.message.clone()).collect();
+        assert!(messages.iter().any(|m| m.contains("\"C\" has no match")));
+        assert!(messages.iter().any(|m| m.contains("no longer exists")));
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+use crate::pxc::PxcFile;
+
+const DEFAULT_W: f64 = 240.0;
+const DEFAULT_H: f64 = 160.0;
+/// GameMaker `c_white`; note colors are `0xBBGGRR` without alpha.
+const DEFAULT_COLOR: u32 = 0xFF_FFFF;
+
+/// One editor note of the top-level `notes` array.
+#[derive(Clone, Debug, Serialize)]
+pub struct Note {
+    pub index: usize,
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+    pub text: String,
+    /// `0xBBGGRR`, as GameMaker stores colors.
+    pub color: u32,
+}
+
+impl Note {
+    /// The color as `#rrggbb`.
+    pub fn color_hex(&self) -> String {
+        let c = self.color;
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            c & 0xFF,
+            (c >> 8) & 0xFF,
+            (c >> 16) & 0xFF
+        )
+    }
+}
+
+/// Changes to a note; fields left as `None` are kept.
+#[derive(Clone, Debug, Default)]
+pub struct NoteEdit {
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub w: Option<f64>,
+    pub h: Option<f64>,
+    pub text: Option<String>,
+    pub color: Option<u32>,
+}
+
+/// Reads `#rrggbb` (or `rrggbb`) into GameMaker's `0xBBGGRR`.
+pub fn parse_note_color(s: &str) -> Result<u32> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        bail!("invalid color {:?} (expected #rrggbb)", s);
+    }
+    let rgb = u32::from_str_radix(hex, 16).map_err(|_| anyhow!("invalid color {:?}", s))?;
+    Ok(((rgb & 0xFF) << 16) | (rgb & 0xFF00) | ((rgb >> 16) & 0xFF))
+}
+
+fn note(index: usize, value: &Value) -> Note {
+    let number =
+        |key: &str, default: f64| value.get(key).and_then(|v| v.as_f64()).unwrap_or(default);
+    Note {
+        index,
+        x: number("x", 0.0),
+        y: number("y", 0.0),
+        w: number("w", DEFAULT_W),
+        h: number("h", DEFAULT_H),
+        text: value
+            .get("content")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        color: value
+            .get("color")
+            .and_then(|v| v.as_f64())
+            .map_or(DEFAULT_COLOR, |c| c as u32 & 0xFF_FFFF),
+    }
+}
+
+fn notes_mut(pxc: &mut PxcFile) -> Result<&mut Vec<Value>> {
+    let root = pxc
+        .json
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("project JSON is not an object"))?;
+    let notes = root.entry("notes").or_insert_with(|| json!([]));
+    if !notes.is_array() {
+        *notes = json!([]);
+    }
+    Ok(notes.as_array_mut().expect("notes is an array"))
+}
+
+fn apply_edit(note: &mut Map<String, Value>, edit: &NoteEdit) -> Result<()> {
+    for (key, value) in [("w", edit.w), ("h", edit.h)] {
+        if value.is_some_and(|v| v <= 0.0) {
+            bail!("note {} must be positive", key);
+        }
+    }
+    for (key, value) in [("x", edit.x), ("y", edit.y), ("w", edit.w), ("h", edit.h)] {
+        if let Some(value) = value {
+            note.insert(key.to_string(), json!(value));
+        }
+    }
+    if let Some(text) = &edit.text {
+        note.insert("content".to_string(), json!(text));
+    }
+    if let Some(color) = edit.color {
+        note.insert("color".to_string(), json!(color & 0xFF_FFFF));
+    }
+    Ok(())
+}
+
+pub fn notes_in_pxc(pxc: &PxcFile) -> Vec<Note> {
+    pxc.json
+        .get("notes")
+        .and_then(|v| v.as_array())
+        .map(|notes| notes.iter().enumerate().map(|(i, n)| note(i, n)).collect())
+        .unwrap_or_default()
+}
+
+/// Appends a note and returns its index. Unset fields get the editor's defaults.
+pub fn add_note_to_pxc(pxc: &mut PxcFile, text: &str, edit: &NoteEdit) -> Result<usize> {
+    let mut map = Map::new();
+    map.insert("x".to_string(), json!(0));
+    map.insert("y".to_string(), json!(0));
+    map.insert("w".to_string(), json!(DEFAULT_W));
+    map.insert("h".to_string(), json!(DEFAULT_H));
+    map.insert("content".to_string(), json!(text));
+    map.insert("color".to_string(), json!(DEFAULT_COLOR));
+    apply_edit(&mut map, edit)?;
+    let notes = notes_mut(pxc)?;
+    notes.push(Value::Object(map));
+    Ok(notes.len() - 1)
+}
+
+/// Applies `edit` to the note at `index`, keeping any fields it does not know.
+pub fn edit_note_in_pxc(pxc: &mut PxcFile, index: usize, edit: &NoteEdit) -> Result<()> {
+    let notes = notes_mut(pxc)?;
+    let count = notes.len();
+    let note = notes
+        .get_mut(index)
+        .ok_or_else(|| anyhow!("note {} not found ({} notes)", index, count))?;
+    if !note.is_object() {
+        *note = json!({});
+    }
+    apply_edit(note.as_object_mut().expect("note is an object"), edit)
+}
+
+/// Removes the note at `index`; later notes move down by one.
+pub fn remove_note_from_pxc(pxc: &mut PxcFile, index: usize) -> Result<Note> {
+    let notes = notes_mut(pxc)?;
+    if index >= notes.len() {
+        bail!("note {} not found ({} notes)", index, notes.len());
+    }
+    Ok(note(index, &notes.remove(index)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+
+    #[test]
+    fn edits_keep_unknown_fields() {
+        let mut pxc = empty_pxc();
+        pxc.json["notes"] =
+            json!([{"x": 10, "y": 20, "w": 100, "h": 50, "content": "a", "alpha": 0.5}]);
+        let edit = NoteEdit {
+            text: Some("b".to_string()),
+            color: Some(parse_note_color("#ff8000").unwrap()),
+            ..Default::default()
+        };
+        edit_note_in_pxc(&mut pxc, 0, &edit).unwrap();
+
+        assert_eq!(pxc.json["notes"][0]["alpha"], 0.5);
+        assert_eq!(pxc.json["notes"][0]["color"], 0x0080FF);
+        let note = &notes_in_pxc(&pxc)[0];
+        assert_eq!((note.text.as_str(), note.x), ("b", 10.0));
+        assert_eq!(note.color_hex(), "#ff8000");
+        assert!(edit_note_in_pxc(&mut pxc, 1, &edit).is_err());
+    }
+}
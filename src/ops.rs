@@ -1,8 +1,15 @@
-use anyhow::{Result, anyhow};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow, bail};
 use serde_json::{Map, Value, json};
 
+use crate::color::default_gradient_value;
 use crate::ids::short_id;
-use crate::registry::Registry;
+use crate::pxc::{PxcFile, SAVE_VERSION};
+use crate::registry::{Registry, RegistryNode, RegistryPort};
+
+static NODE_COUNTER: AtomicU64 = AtomicU64::new(1);
 
 pub(crate) fn resolve_input_slot(
     node: &Value,
@@ -20,14 +27,9 @@ pub(crate) fn resolve_input_slot(
                 .get(node.get("type").and_then(|v| v.as_str()).unwrap_or(""))
         })
         .ok_or_else(|| anyhow!("registry missing node type"))?;
-    for (i, inp) in reg_node.inputs.iter().enumerate() {
-        if let Some(nm) = &inp.name {
-            if nm == name {
-                return Ok(i);
-            }
-        }
-    }
-    Err(anyhow!("input name not found: {}", name))
+    reg_node
+        .input_slot(name)
+        .ok_or_else(|| anyhow!("input name not found: {}", name))
 }
 
 pub(crate) fn resolve_output_slot(
@@ -41,14 +43,14 @@ pub(crate) fn resolve_output_slot(
     }
     let name = output_name.ok_or_else(|| anyhow!("output slot or name required"))?;
     let node_type = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
-    if let Some(reg) = registry {
-        if let Some(reg_node) = reg.nodes.get(node_type) {
-            for (idx, port) in reg_node.outputs.iter().enumerate() {
-                if let Some(port_name) = &port.name {
-                    if port_name == name {
-                        return Ok(idx);
-                    }
-                }
+    if let Some(reg) = registry
+        && let Some(reg_node) = reg.nodes.get(node_type)
+    {
+        for (idx, port) in reg_node.outputs.iter().enumerate() {
+            if let Some(port_name) = &port.name
+                && port_name == name
+            {
+                return Ok(idx);
             }
         }
     }
@@ -76,14 +78,174 @@ pub(crate) fn resolve_node_id(node_arg: &str, nodes: &[Value]) -> Option<String>
     None
 }
 
-pub fn set_input_value_in_pxc(
-    pxc: &mut crate::pxc::PxcFile,
+pub(crate) fn display_name_from_type(node_type: &str) -> String {
+    let base = node_type.strip_prefix("Node_").unwrap_or(node_type);
+    base.replace('_', " ")
+}
+
+pub(crate) fn default_value_for_port(port: &RegistryPort) -> Value {
+    if let Some(d) = &port.default {
+        return d.clone();
+    }
+    let ty = port.ty.as_deref().unwrap_or("");
+    let ty_lower = ty.to_ascii_lowercase();
+    let name_lower = port.name.as_deref().unwrap_or("").to_ascii_lowercase();
+    let zeros = |n: usize| Value::Array(vec![Value::Number(0.into()); n]);
+    if ty_lower.contains("gradient") {
+        return default_gradient_value();
+    }
+    // The type decides before any name heuristic, so "Color Mode" (an enum)
+    // or "Color R Range" (a slider range) never gets a color value.
+    if ty_lower.contains("enum") || ty_lower.contains("escroll") || ty_lower.contains("ebutton") {
+        return Value::Number(0.into());
+    }
+    if ty_lower.contains("slider_range") {
+        return json!([0, 1]);
+    }
+    if ty_lower.contains("range") {
+        let n = if ty_lower.contains("vec3") {
+            6
+        } else if ty_lower.contains("vec2") {
+            4
+        } else {
+            2
+        };
+        return zeros(n);
+    }
+    if ty_lower.contains("color")
+        || (ty.is_empty() && (name_lower.contains("color") || name_lower.contains("colour")))
+    {
+        return Value::Number(0xFFFF_FFFFu32.into());
+    }
+    if ty_lower.contains("toggle") || ty_lower.contains("bool") || name_lower.contains("enable") {
+        return Value::Bool(false);
+    }
+    if ty_lower.contains("string") || ty_lower.contains("text") || ty_lower.contains("path") {
+        return Value::String(String::new());
+    }
+    if ty_lower.contains("vector2") || ty_lower.contains("vec2") {
+        return zeros(2);
+    }
+    if ty_lower.contains("vector3") || ty_lower.contains("vec3") {
+        return zeros(3);
+    }
+    if ty_lower.contains("vector4") || ty_lower.contains("vec4") {
+        return zeros(4);
+    }
+    if ty_lower.contains("array") {
+        return Value::Array(vec![]);
+    }
+    if ty_lower.contains("number")
+        || ty_lower.contains("float")
+        || ty_lower.contains("integer")
+        || ty_lower.ends_with("_int")
+        || ty_lower.contains("slider")
+    {
+        return Value::Number(0.into());
+    }
+    Value::Null
+}
+
+/// A new input entry holding the port's default value.
+pub(crate) fn default_input(port: &RegistryPort) -> Value {
+    let dv = default_value_for_port(port);
+    let mut input = json!({"m":1,"r":{"d": dv}});
+    if port.visible == Some(false) {
+        input["visible"] = Value::Bool(false);
+    }
+    input
+}
+
+/// Builds a fresh node object; ports come from `reg_node` when the type is known.
+pub(crate) fn build_node(
+    node_type: &str,
+    x: i32,
+    y: i32,
+    name: Option<&str>,
+    reg_node: Option<&RegistryNode>,
+) -> (String, Value) {
+    let reg_inputs = reg_node.map(|n| n.inputs.as_slice()).unwrap_or(&[]);
+    let reg_outputs = reg_node.map(|n| n.outputs.as_slice()).unwrap_or(&[]);
+    let mut inputs = Vec::with_capacity(reg_inputs.len());
+    for port in reg_inputs {
+        inputs.push(default_input(port));
+    }
+    let mut outputs = Vec::with_capacity(reg_outputs.len());
+    for _ in reg_outputs {
+        outputs.push(json!({}));
+    }
+
+    let base = display_name_from_type(node_type);
+    let base_id = node_type
+        .strip_prefix("Node_")
+        .unwrap_or(node_type)
+        .replace('_', "");
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let c = NODE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let id = format!("node{}_{}", ts, c);
+    let iname = format!("{}{}", base_id, c);
+
+    let node_name = name.map(|s| s.to_string()).unwrap_or_else(|| base.clone());
+    let mut node = Map::new();
+    node.insert("id".to_string(), Value::String(id.clone()));
+    node.insert("type".to_string(), Value::String(node_type.to_string()));
+    node.insert("name".to_string(), Value::String(node_name));
+    node.insert("iname".to_string(), Value::String(iname));
+    node.insert("x".to_string(), Value::Number(x.into()));
+    node.insert("y".to_string(), Value::Number(y.into()));
+    node.insert("version".to_string(), Value::Number(SAVE_VERSION.into()));
+    node.insert("renamed".to_string(), Value::Bool(name.is_some()));
+    let data_length = reg_node.map_or(0, |n| n.dynamic_inputs.len()).max(1);
+    node.insert(
+        "data_length".to_string(),
+        Value::Number((data_length as u64).into()),
+    );
+    node.insert(
+        "input_fix_len".to_string(),
+        Value::Number((reg_inputs.len() as u64).into()),
+    );
+    node.insert("inputs".to_string(), Value::Array(inputs));
+    node.insert("outputs".to_string(), Value::Array(outputs));
+    node.insert("attri".to_string(), Value::Object(Map::new()));
+    node.insert("insp_col".to_string(), Value::Object(Map::new()));
+    node.insert("inspectInputs".to_string(), Value::Array(vec![]));
+    (id, Value::Object(node))
+}
+
+pub fn add_node_to_pxc(
+    pxc: &mut PxcFile,
+    node_type: &str,
+    x: i32,
+    y: i32,
+    name: Option<&str>,
+    registry: &Registry,
+) -> Result<String> {
+    let reg_node = registry
+        .nodes
+        .get(node_type)
+        .ok_or_else(|| anyhow!("unknown node type: {}", node_type))?;
+    let (id, node) = build_node(node_type, x, y, name, Some(reg_node));
+    let nodes = pxc
+        .json
+        .get_mut("nodes")
+        .and_then(|v| v.as_array_mut())
+        .ok_or_else(|| anyhow!("nodes array missing"))?;
+    nodes.push(node);
+    Ok(id)
+}
+
+/// An input object, created (with any gaps) when the node's `inputs` array is
+/// shorter than the slot, and its registry port.
+pub(crate) fn input_mut<'a, 'r>(
+    pxc: &'a mut PxcFile,
     node_arg: &str,
     input_slot: Option<usize>,
     input_name: Option<&str>,
-    value: Value,
-    registry: Option<&Registry>,
-) -> Result<()> {
+    registry: Option<&'r Registry>,
+) -> Result<(&'a mut Map<String, Value>, Option<&'r RegistryPort>)> {
     let nodes = pxc
         .json
         .get_mut("nodes")
@@ -99,11 +261,8 @@ pub fn set_input_value_in_pxc(
 
     let slot = resolve_input_slot(node, input_slot, input_name, registry)?;
 
-    let node_type = node
-        .get("type")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
+    let node_type = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let port = registry.and_then(|r| r.input_port(node_type, slot));
     let inputs = node
         .get_mut("inputs")
         .and_then(|v| v.as_array_mut())
@@ -115,38 +274,16 @@ pub fn set_input_value_in_pxc(
         .get_mut(slot)
         .and_then(|v| v.as_object_mut())
         .ok_or_else(|| anyhow!("input slot is not an object"))?;
-
-    input.remove("from_node");
-    input.remove("from_index");
-    input.remove("from_tag");
-    input.remove("anim");
-    let mut final_value = value;
-    if let Some(reg) = registry {
-        if let Some(reg_node) = reg.nodes.get(&node_type) {
-            if let Some(port) = reg_node.inputs.get(slot) {
-                if let Some(ty) = &port.ty {
-                    let ty_lower = ty.to_ascii_lowercase();
-                    if ty_lower.contains("gradient") {
-                        if !final_value.is_string() {
-                            let s = serde_json::to_string(&final_value)?;
-                            final_value = Value::String(s);
-                        }
-                    }
-                }
-            }
-        }
-    }
-    input.insert("r".to_string(), json!({ "d": final_value }));
-    Ok(())
+    Ok((input, port))
 }
 
-pub fn get_input_value_in_pxc(
-    pxc: &crate::pxc::PxcFile,
+pub(crate) fn input_ref<'a, 'r>(
+    pxc: &'a PxcFile,
     node_arg: &str,
     input_slot: Option<usize>,
     input_name: Option<&str>,
-    registry: Option<&Registry>,
-) -> Result<Value> {
+    registry: Option<&'r Registry>,
+) -> Result<(&'a Value, Option<&'r RegistryPort>)> {
     let nodes = pxc
         .json
         .get("nodes")
@@ -161,18 +298,124 @@ pub fn get_input_value_in_pxc(
         .ok_or_else(|| anyhow!("node not found after resolve: {}", node_id))?;
 
     let slot = resolve_input_slot(node, input_slot, input_name, registry)?;
-    let inputs = node
+    let node_type = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let port = registry.and_then(|r| r.input_port(node_type, slot));
+    let input = node
         .get("inputs")
         .and_then(|v| v.as_array())
-        .ok_or_else(|| anyhow!("node has no inputs array"))?;
-    let input = inputs
+        .ok_or_else(|| anyhow!("node has no inputs array"))?
         .get(slot)
         .ok_or_else(|| anyhow!("input slot out of range"))?;
-    if let Some(r) = input.get("r") {
-        if let Some(obj) = r.as_object() {
-            if let Some(d) = obj.get("d") {
-                return Ok(d.clone());
+    Ok((input, port))
+}
+
+/// Checks a value against its port and converts it to the form a project
+/// stores; gradients are saved as JSON strings.
+pub(crate) fn stored_value(port: Option<&RegistryPort>, value: Value) -> Result<Value> {
+    let Some(port) = port else {
+        return Ok(value);
+    };
+    port.check_value(&value)?;
+    if let Some(ty) = port.ty.as_ref() {
+        let ty_lower = ty.to_ascii_lowercase();
+        if ty_lower.contains("gradient") && !value.is_string() {
+            return Ok(Value::String(serde_json::to_string(&value)?));
+        }
+    }
+    Ok(value)
+}
+
+pub fn set_input_value_in_pxc(
+    pxc: &mut PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    value: Value,
+    registry: Option<&Registry>,
+) -> Result<()> {
+    let (input, port) = input_mut(pxc, node_arg, input_slot, input_name, registry)?;
+    let final_value = stored_value(port, value)?;
+
+    input.remove("from_node");
+    input.remove("from_index");
+    input.remove("from_tag");
+    input.remove("anim");
+    input.insert("r".to_string(), json!({ "d": final_value }));
+    Ok(())
+}
+
+/// The expression driving an input: `global_key` while `global_use` is on.
+pub(crate) fn input_expression(input: &Value) -> Option<&str> {
+    if !input
+        .get("global_use")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        return None;
+    }
+    input
+        .get("global_key")
+        .and_then(|v| v.as_str())
+        .filter(|k| !k.trim().is_empty())
+}
+
+pub fn get_input_expression_in_pxc(
+    pxc: &PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    registry: Option<&Registry>,
+) -> Result<Option<String>> {
+    let (input, _) = input_ref(pxc, node_arg, input_slot, input_name, registry)?;
+    Ok(input_expression(input).map(str::to_string))
+}
+
+/// Drives an input by `expression`, replacing any connection, or with `None`
+/// turns its expression off so the stored value applies again.
+pub fn set_input_expression_in_pxc(
+    pxc: &mut PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    expression: Option<&str>,
+    registry: Option<&Registry>,
+) -> Result<()> {
+    let (input, _) = input_mut(pxc, node_arg, input_slot, input_name, registry)?;
+    match expression {
+        Some(expression) => {
+            if expression.trim().is_empty() {
+                bail!("expression is empty");
             }
+            input.remove("from_node");
+            input.remove("from_index");
+            input.remove("from_tag");
+            input.insert("global_use".to_string(), Value::Bool(true));
+            input.insert(
+                "global_key".to_string(),
+                Value::String(expression.to_string()),
+            );
+        }
+        None => {
+            input.remove("global_use");
+            input.remove("global_key");
+        }
+    }
+    Ok(())
+}
+
+pub fn get_input_value_in_pxc(
+    pxc: &PxcFile,
+    node_arg: &str,
+    input_slot: Option<usize>,
+    input_name: Option<&str>,
+    registry: Option<&Registry>,
+) -> Result<Value> {
+    let (input, _) = input_ref(pxc, node_arg, input_slot, input_name, registry)?;
+    if let Some(r) = input.get("r") {
+        if let Some(obj) = r.as_object()
+            && let Some(d) = obj.get("d")
+        {
+            return Ok(d.clone());
         }
         return Ok(r.clone());
     }
@@ -266,3 +509,37 @@ pub(crate) fn remove_json_pointer(root: &mut Value, pointer: &str) -> Result<()>
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(name: &str, ty: Option<&str>) -> RegistryPort {
+        RegistryPort {
+            name: Some(name.to_string()),
+            ty: ty.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn defaults_follow_the_port_type_before_its_name() {
+        let value = |name, ty| default_value_for_port(&port(name, ty));
+        let white = json!(0xFFFF_FFFFu32);
+        assert_eq!(value("Color Mode", Some("nodeValue_Enum_Button")), json!(0));
+        assert_eq!(
+            value("Color R Range", Some("nodeValue_Slider_Range")),
+            json!([0, 1])
+        );
+        assert_eq!(
+            value("Range", Some("nodeValue_Vec2_Range")),
+            json!([0, 0, 0, 0])
+        );
+        assert_eq!(value("Color", Some("nodeValue_Color")), white);
+        assert_eq!(value("Color", None), white);
+        assert_eq!(value("Color Count", Some("nodeValue_Int")), json!(0));
+        let mut given = port("Opacity", Some("nodeValue_Slider"));
+        given.default = Some(json!(1));
+        assert_eq!(default_value_for_port(&given), json!(1));
+    }
+}
@@ -0,0 +1,570 @@
+use std::io::Cursor;
+
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use image::ImageFormat;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::color::{color_from_rgba, color_from_value, looks_like_color_array};
+use crate::pxc::{PxcFile, rgba_bytes_to_image};
+use crate::registry::Registry;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorKind {
+    /// A single packed colour.
+    Color,
+    /// One entry of a colour array.
+    Palette,
+    /// One key of a gradient.
+    Gradient,
+}
+
+/// Where a colour sits inside one input: its shape, the palette entry or
+/// gradient key, and the animation key frame when the input is animated.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ColorSpot {
+    pub kind: ColorKind,
+    pub index: Option<usize>,
+    pub frame: Option<f64>,
+}
+
+/// One place a colour is used.
+#[derive(Clone, Debug, Serialize)]
+pub struct ColorUse {
+    pub node: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_name: Option<String>,
+    pub input: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_name: Option<String>,
+    pub kind: ColorKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame: Option<f64>,
+}
+
+/// A distinct colour of a project and everywhere it is used.
+#[derive(Clone, Debug, Serialize)]
+pub struct PaletteColor {
+    /// `0xAABBGGRR`, as Pixel Composer stores colours.
+    pub color: u32,
+    /// `#rrggbb`.
+    pub hex: String,
+    pub alpha: u8,
+    pub uses: Vec<ColorUse>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PaletteFormat {
+    Json,
+    Png,
+    Hex,
+    Gpl,
+}
+
+impl PaletteFormat {
+    /// The format matching a file extension, if any.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "png" => Some(Self::Png),
+            "hex" => Some(Self::Hex),
+            "gpl" => Some(Self::Gpl),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn rgb(color: u32) -> [u8; 3] {
+    [
+        (color & 0xFF) as u8,
+        ((color >> 8) & 0xFF) as u8,
+        ((color >> 16) & 0xFF) as u8,
+    ]
+}
+
+fn hex(color: u32) -> String {
+    let [r, g, b] = rgb(color);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// A packed colour. On ports the registry types as colours any `u32` counts,
+/// fully transparent ones included; elsewhere only values with some alpha do,
+/// so plain numbers are not taken for colours.
+fn read_color(value: &Value, typed: bool) -> Option<u32> {
+    if !typed {
+        return color_from_value(value);
+    }
+    let n = value.as_f64()?;
+    (n.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&n)).then_some(n as u32)
+}
+
+/// Calls `f` on every colour in a value: a packed colour, a colour array or a
+/// gradient (a JSON string or object with `keys`). When `f` returns a colour it
+/// replaces the old one. Returns the number replaced.
+fn visit_value(
+    value: &mut Value,
+    frame: Option<f64>,
+    typed: bool,
+    f: &mut dyn FnMut(ColorSpot, u32) -> Option<u32>,
+) -> usize {
+    let spot = |kind, index| ColorSpot { kind, index, frame };
+    let mut replace = |value: &mut Value, spot: ColorSpot| {
+        let new = f(spot, read_color(value, typed)?)?;
+        *value = Value::Number(new.into());
+        Some(())
+    };
+    let is_palette = |arr: &[Value]| {
+        if typed {
+            !arr.is_empty() && arr.iter().all(|v| read_color(v, true).is_some())
+        } else {
+            looks_like_color_array(arr)
+        }
+    };
+    match value {
+        Value::Number(_) => replace(value, spot(ColorKind::Color, None)).map_or(0, |_| 1),
+        Value::Array(arr) if is_palette(arr) => arr
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, v)| replace(v, spot(ColorKind::Palette, Some(i))))
+            .count(),
+        Value::Object(gradient) => {
+            let keys = gradient.get_mut("keys").and_then(|v| v.as_array_mut());
+            keys.into_iter()
+                .flatten()
+                .enumerate()
+                .filter_map(|(i, key)| {
+                    let value = key.get_mut("value")?;
+                    replace(value, spot(ColorKind::Gradient, Some(i)))
+                })
+                .count()
+        }
+        Value::String(s) => {
+            let Ok(mut gradient) = serde_json::from_str::<Value>(s) else {
+                return 0;
+            };
+            if !gradient.is_object() {
+                return 0;
+            }
+            let changed = visit_value(&mut gradient, frame, typed, f);
+            if changed > 0 {
+                *s = gradient.to_string();
+            }
+            changed
+        }
+        _ => 0,
+    }
+}
+
+/// Visits the colours of one input: its static `r.d`, or the value of every
+/// animation key. `typed` marks a port the registry types as a colour.
+pub(crate) fn visit_input_colors(
+    input: &mut Value,
+    typed: bool,
+    f: &mut dyn FnMut(ColorSpot, u32) -> Option<u32>,
+) -> usize {
+    match input.get_mut("r") {
+        Some(Value::Object(r)) => r.get_mut("d").map_or(0, |d| visit_value(d, None, typed, f)),
+        Some(Value::Array(keys)) => keys
+            .iter_mut()
+            .filter_map(|key| {
+                let key = key.as_array_mut()?;
+                let frame = key.first().and_then(|v| v.as_f64());
+                Some(visit_value(key.get_mut(1)?, frame, typed, f))
+            })
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Whether a port of this type can hold colours. Ports the registry does not
+/// know are searched too.
+fn may_hold_colors(ty: Option<&str>) -> bool {
+    match ty {
+        Some(ty) => matches!(
+            ty,
+            "nodeValue_Color" | "nodeValue_Palette" | "nodeValue_Gradient"
+        ),
+        None => true,
+    }
+}
+
+/// Calls `visit(node, slot, input, typed)` for every node input that may hold
+/// colours; `typed` is set when the registry types the port as one.
+pub(crate) fn for_each_color_input(
+    nodes: &mut [Value],
+    registry: Option<&Registry>,
+    visit: &mut dyn FnMut(&Value, usize, &mut Value, bool),
+) {
+    for node in nodes.iter_mut() {
+        let node_type = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        let reg_node = registry.and_then(|r| r.nodes.get(node_type));
+        let Some(mut inputs) = node.get_mut("inputs").map(Value::take) else {
+            continue;
+        };
+        for (slot, input) in inputs.as_array_mut().into_iter().flatten().enumerate() {
+            let ty = reg_node
+                .and_then(|n| n.input(slot))
+                .and_then(|p| p.ty.as_deref());
+            if may_hold_colors(ty) {
+                visit(node, slot, input, ty.is_some());
+            }
+        }
+        node["inputs"] = inputs;
+    }
+}
+
+/// Every distinct colour of the project's node inputs, in the order first
+/// found, with each place it is used. Colours that differ only in alpha are
+/// listed separately.
+pub fn palette_in_pxc(pxc: &PxcFile, registry: Option<&Registry>) -> Vec<PaletteColor> {
+    let mut nodes = match pxc.json.get("nodes").and_then(|v| v.as_array()) {
+        Some(nodes) => nodes.clone(),
+        None => return Vec::new(),
+    };
+    let mut colors: Vec<PaletteColor> = Vec::new();
+    for_each_color_input(&mut nodes, registry, &mut |node, slot, input, typed| {
+        let node_type = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        visit_input_colors(input, typed, &mut |spot, color| {
+            let used = ColorUse {
+                node: node
+                    .get("id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+                node_name: node
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                input: slot,
+                input_name: registry
+                    .and_then(|r| r.nodes.get(node_type))
+                    .and_then(|n| n.input_name(slot)),
+                kind: spot.kind,
+                index: spot.index,
+                frame: spot.frame,
+            };
+            match colors.iter_mut().find(|c| c.color == color) {
+                Some(entry) => entry.uses.push(used),
+                None => colors.push(PaletteColor {
+                    color,
+                    hex: hex(color),
+                    alpha: (color >> 24) as u8,
+                    uses: vec![used],
+                }),
+            }
+            None
+        });
+    });
+    colors
+}
+
+/// Distinct `[r, g, b]` values in order; the text formats have no alpha.
+fn distinct_rgb(colors: &[PaletteColor]) -> Vec<[u8; 3]> {
+    let mut out: Vec<[u8; 3]> = Vec::new();
+    for c in colors {
+        let rgb = rgb(c.color);
+        if !out.contains(&rgb) {
+            out.push(rgb);
+        }
+    }
+    out
+}
+
+/// Encodes a palette. `name` titles `.gpl` files; `swatch` is the side of each
+/// square of the PNG strip.
+pub fn render_palette(
+    colors: &[PaletteColor],
+    format: PaletteFormat,
+    name: &str,
+    swatch: u32,
+) -> Result<Vec<u8>> {
+    let text = match format {
+        PaletteFormat::Json => serde_json::to_string_pretty(colors)? + "\n",
+        PaletteFormat::Hex => distinct_rgb(colors)
+            .iter()
+            .map(|[r, g, b]| format!("{:02x}{:02x}{:02x}\n", r, g, b))
+            .collect(),
+        PaletteFormat::Gpl => {
+            let mut out = format!("GIMP Palette\nName: {}\nColumns: 0\n#\n", name);
+            for [r, g, b] in distinct_rgb(colors) {
+                out.push_str(&format!(
+                    "{:3} {:3} {:3}\t#{:02x}{:02x}{:02x}\n",
+                    r, g, b, r, g, b
+                ));
+            }
+            out
+        }
+        PaletteFormat::Png => {
+            if colors.is_empty() {
+                bail!("no colours to draw");
+            }
+            if swatch == 0 {
+                bail!("swatch size must be positive");
+            }
+            let width = swatch * colors.len() as u32;
+            let mut raw = Vec::with_capacity((width * swatch * 4) as usize);
+            for _ in 0..swatch {
+                for c in colors {
+                    let [r, g, b] = rgb(c.color);
+                    for _ in 0..swatch {
+                        raw.extend_from_slice(&[r, g, b, (c.color >> 24) as u8]);
+                    }
+                }
+            }
+            let img = rgba_bytes_to_image(&raw, width, swatch)?;
+            let mut out = Vec::new();
+            img.write_to(&mut Cursor::new(&mut out), ImageFormat::Png)?;
+            return Ok(out);
+        }
+    };
+    Ok(text.into_bytes())
+}
+
+/// Source to target colours of a remap, compared and written as RGB.
+#[derive(Clone, Debug, Default)]
+pub struct PaletteMap {
+    pub pairs: Vec<([u8; 3], [u8; 3])>,
+}
+
+/// Substitutions made in one node.
+#[derive(Clone, Debug, Serialize)]
+pub struct NodeRemap {
+    pub node: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_name: Option<String>,
+    pub substitutions: usize,
+}
+
+/// Reads `#rrggbb` (or `rrggbb`).
+pub fn parse_rgb(s: &str) -> Result<[u8; 3]> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        bail!("invalid color {:?} (expected #rrggbb)", s);
+    }
+    let v = u32::from_str_radix(hex, 16).map_err(|_| anyhow!("invalid color {:?}", s))?;
+    Ok([(v >> 16) as u8, (v >> 8) as u8, v as u8])
+}
+
+impl PaletteMap {
+    /// Reads `{"#source": "#target", ...}`, or `{"from": [...], "to": [...]}`
+    /// with two lists of the same length.
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let map = value
+            .as_object()
+            .ok_or_else(|| anyhow!("palette map must be a JSON object"))?;
+        let text = |v: &Value| {
+            v.as_str()
+                .ok_or_else(|| anyhow!("expected a color string, got {}", v))
+                .and_then(parse_rgb)
+        };
+        let pairs = match (map.get("from"), map.get("to")) {
+            (Some(Value::Array(from)), Some(Value::Array(to))) => {
+                if from.len() != to.len() {
+                    bail!(
+                        "\"from\" has {} colors but \"to\" has {}",
+                        from.len(),
+                        to.len()
+                    );
+                }
+                let from = from.iter().map(text).collect::<Result<Vec<_>>>()?;
+                let to = to.iter().map(text).collect::<Result<Vec<_>>>()?;
+                from.into_iter().zip(to).collect()
+            }
+            _ => map
+                .iter()
+                .map(|(from, to)| Ok((parse_rgb(from)?, text(to)?)))
+                .collect::<Result<Vec<_>>>()?,
+        };
+        if pairs.is_empty() {
+            bail!("palette map is empty");
+        }
+        Ok(PaletteMap { pairs })
+    }
+
+    /// The target for `color`: the pair with the same RGB, or with `nearest` the
+    /// pair whose source is closest in RGB.
+    fn target(&self, color: [u8; 3], nearest: bool) -> Option<[u8; 3]> {
+        if !nearest {
+            return self
+                .pairs
+                .iter()
+                .find(|(from, _)| *from == color)
+                .map(|p| p.1);
+        }
+        let distance = |a: [u8; 3]| {
+            (0..3)
+                .map(|i| (a[i] as i32 - color[i] as i32).pow(2))
+                .sum::<i32>()
+        };
+        // `min_by_key` keeps the first of equal distances, so the first pair wins.
+        self.pairs
+            .iter()
+            .min_by_key(|(from, _)| distance(*from))
+            .map(|p| p.1)
+    }
+}
+
+/// Replaces the colours of every node input through `map`, keeping each
+/// colour's alpha. Covers what `palette_in_pxc` finds: single colours, colour
+/// arrays, gradient keys and animation keys. Returns the nodes that changed.
+pub fn remap_palette_in_pxc(
+    pxc: &mut PxcFile,
+    map: &PaletteMap,
+    nearest: bool,
+    registry: Option<&Registry>,
+) -> Vec<NodeRemap> {
+    let Some(nodes) = pxc.json.get_mut("nodes").and_then(|v| v.as_array_mut()) else {
+        return Vec::new();
+    };
+    let mut report: Vec<NodeRemap> = Vec::new();
+    for_each_color_input(nodes, registry, &mut |node, _, input, typed| {
+        let substitutions = visit_input_colors(input, typed, &mut |_, color| {
+            let [r, g, b] = map.target(rgb(color), nearest)?;
+            let new = color_from_rgba(r, g, b, (color >> 24) as u8);
+            (new != color).then_some(new)
+        });
+        if substitutions == 0 {
+            return;
+        }
+        let id = node.get("id").and_then(|v| v.as_str()).unwrap_or("");
+        match report.iter_mut().find(|n| n.node == id) {
+            Some(entry) => entry.substitutions += substitutions,
+            None => report.push(NodeRemap {
+                node: id.to_string(),
+                node_name: node
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                substitutions,
+            }),
+        }
+    });
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxc::empty_pxc;
+    use crate::registry::{RegistryNode, RegistryPort};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn colours_are_found_in_every_shape() {
+        let red = 0xFF00_00FFu32;
+        let blue = 0x80FF_0000u32;
+        let gradient = json!({"type": 0, "keys": [{"time": 0, "value": blue}]}).to_string();
+        let mut pxc = empty_pxc();
+        pxc.json["nodes"] = json!([{"id": "a", "name": "fill", "type": "Node_T", "inputs": [
+            {"r": {"d": red}},
+            {"r": {"d": [red, blue]}},
+            {"r": {"d": gradient}},
+            {"anim": true, "r": [[0, red], [12, 0xFF00_FF00u32]]},
+            {"r": {"d": 3}},
+        ]}]);
+        let palette = palette_in_pxc(&pxc, None);
+
+        let found: Vec<&str> = palette.iter().map(|c| c.hex.as_str()).collect();
+        assert_eq!(found, ["#ff0000", "#0000ff", "#00ff00"]);
+        assert_eq!(palette[0].uses.len(), 3);
+        let in_gradient = &palette[1].uses[1];
+        assert_eq!(
+            (in_gradient.kind, in_gradient.index),
+            (ColorKind::Gradient, Some(0))
+        );
+        assert_eq!(palette[2].uses[0].frame, Some(12.0));
+        assert_eq!(palette[1].alpha, 0x80);
+
+        let gpl = render_palette(&palette, PaletteFormat::Gpl, "t", 8).unwrap();
+        assert!(
+            String::from_utf8(gpl)
+                .unwrap()
+                .ends_with("  0 255   0\t#00ff00\n")
+        );
+    }
+
+    #[test]
+    fn typed_ports_keep_transparent_colours() {
+        let port = |ty: &str| RegistryPort {
+            ty: Some(ty.to_string()),
+            ..Default::default()
+        };
+        let node = RegistryNode {
+            inputs: vec![
+                port("nodeValue_Color"),
+                port("nodeValue_Palette"),
+                port("nodeValue_Float"),
+            ],
+            ..Default::default()
+        };
+        let registry = Registry {
+            nodes: HashMap::from([("Node_T".to_string(), node)]),
+        };
+        let clear_white = 0x00FF_FFFFu32;
+        let mut pxc = empty_pxc();
+        pxc.json["nodes"] = json!([
+            {"id": "a", "type": "Node_T", "inputs": [
+                {"r": {"d": clear_white}},
+                {"r": {"d": [0, 0xFF00_00FFu32]}},
+                {"r": {"d": 0x0012_3456u32}},
+            ]},
+            // Not in the registry, so alpha still decides.
+            {"id": "b", "type": "Node_U", "inputs": [{"r": {"d": clear_white}}]},
+        ]);
+        let palette = palette_in_pxc(&pxc, Some(&registry));
+        let found: Vec<(u32, usize)> = palette.iter().map(|c| (c.color, c.uses.len())).collect();
+        assert_eq!(found, [(clear_white, 1), (0, 1), (0xFF00_00FF, 1)]);
+
+        let map = PaletteMap::from_json(&json!({"#ffffff": "#000000"})).unwrap();
+        let report = remap_palette_in_pxc(&mut pxc, &map, false, Some(&registry));
+        assert_eq!(report.len(), 1);
+        assert_eq!(pxc.json["nodes"][0]["inputs"][0]["r"]["d"], 0);
+        assert_eq!(pxc.json["nodes"][1]["inputs"][0]["r"]["d"], clear_white);
+    }
+
+    #[test]
+    fn nearest_ties_go_to_the_first_pair() {
+        let map = PaletteMap::from_json(&json!({
+            "from": ["#000000", "#000002"],
+            "to": ["#111111", "#222222"]
+        }))
+        .unwrap();
+        // #000001 is equally close to both sources.
+        assert_eq!(map.target([0, 0, 1], true), Some([0x11, 0x11, 0x11]));
+        assert_eq!(map.target([0, 0, 1], false), None);
+    }
+
+    #[test]
+    fn remap_keeps_alpha_and_reaches_gradients() {
+        let gradient = json!({"keys": [{"time": 0, "value": 0x80FF_0000u32}]}).to_string();
+        let mut pxc = empty_pxc();
+        pxc.json["nodes"] = json!([{"id": "a", "type": "Node_T", "inputs": [
+            {"r": {"d": gradient}},
+            {"anim": true, "r": [[0, 0xFF00_00F0u32], [8, 0xFF30_2010u32]]},
+        ]}]);
+        let map = PaletteMap::from_json(
+            &json!({"from": ["#0000ff", "#ff0000"], "to": ["#102030", "#00ff00"]}),
+        )
+        .unwrap();
+
+        let exact = remap_palette_in_pxc(&mut pxc.clone(), &map, false, None);
+        assert_eq!(exact[0].substitutions, 1);
+        let report = remap_palette_in_pxc(&mut pxc, &map, true, None);
+        assert_eq!(report[0].substitutions, 2);
+        let keys = &pxc.json["nodes"][0]["inputs"];
+        assert!(
+            keys[0]["r"]["d"]
+                .as_str()
+                .unwrap()
+                .contains(&0x8030_2010u32.to_string())
+        );
+        assert_eq!(keys[1]["r"][0][1], 0xFF00_FF00u32);
+        // Already the target colour, so not counted.
+        assert_eq!(keys[1]["r"][1][1], 0xFF30_2010u32);
+    }
+}
@@ -9,7 +9,10 @@ use flate2::Compression;
 use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use image::{DynamicImage, ImageBuffer, Rgba};
-use serde_json::Value;
+use serde_json::{Value, json};
+
+/// Save version written into nodes and projects created by this crate.
+pub(crate) const SAVE_VERSION: u32 = 120000;
 
 #[derive(Clone, Debug)]
 pub struct Thumbnail {
@@ -35,6 +38,30 @@ pub struct PxcFile {
     pub json: Value,
 }
 
+impl PxcFile {
+    /// The project's save version: the JSON `version`, else the header's
+    /// `META.save_version`.
+    pub fn save_version(&self) -> Option<u32> {
+        self.json
+            .get("version")
+            .and_then(|v| v.as_u64().or_else(|| v.as_f64().map(|f| f as u64)))
+            .and_then(|v| u32::try_from(v).ok())
+            .or_else(|| self.header.meta.as_ref().map(|m| m.save_version))
+    }
+}
+
+/// A project with no nodes, for building graphs from scratch.
+pub(crate) fn empty_pxc() -> PxcFile {
+    PxcFile {
+        header: Header {
+            thumbnail: None,
+            meta: None,
+            header_size: 0,
+        },
+        json: json!({ "version": SAVE_VERSION, "nodes": [] }),
+    }
+}
+
 pub fn read_pxc(path: &Path) -> Result<PxcFile> {
     let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
     parse_pxc(&data)
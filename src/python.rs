@@ -3,25 +3,47 @@
 #![allow(unsafe_op_in_unsafe_fn)]
 
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
 
 use pyo3::exceptions::PyRuntimeError;
 use pyo3::prelude::*;
-use serde_json::{Map, Value, json};
-
-use crate::color::{
-    color_from_rgba, color_from_value, default_gradient_value, gradient_value_from_keys,
+use serde_json::{Map, Value};
+
+use crate::anim::{animation_settings, retime_pxc, set_animation_settings};
+use crate::apply::apply_graph_json_to_pxc;
+use crate::color::{color_from_rgba, color_from_value, gradient_value_from_keys};
+use crate::dsl::build_graph_into_pxc;
+use crate::dynamic::{add_input_group_in_pxc, remove_input_group_in_pxc};
+use crate::favorites::{
+    add_favorite_in_pxc, add_panel_to_pxc, favorites_in_pxc, panels_in_pxc, remove_favorite_in_pxc,
+    remove_panel_from_pxc, resolve_input,
+};
+use crate::globals::{
+    GlobalEdit, GlobalType, add_global_to_pxc, get_global_in_pxc, globals_in_pxc,
+    remove_global_from_pxc, set_global_in_pxc,
 };
 use crate::graph::{GraphMode, graph_json_from_pxc};
+use crate::group::{group_nodes_in_pxc, ungroup_in_pxc};
+use crate::keyframe::{
+    Interpolation, KeyframeEase, delete_keyframe_in_pxc, get_input_value_at_in_pxc,
+    keyframes_in_pxc, move_keyframe_in_pxc, set_animated_in_pxc, set_keyframe_in_pxc,
+};
+use crate::layout::{DEFAULT_SPACING, auto_layout_pxc};
+use crate::lint::lint_pxc;
+use crate::metadata::{MetaEdit, metadata_in_pxc, set_metadata_in_pxc};
+use crate::notes::{
+    NoteEdit, add_note_to_pxc, edit_note_in_pxc, notes_in_pxc, parse_note_color,
+    remove_note_from_pxc,
+};
 use crate::ops::{
-    get_input_value_in_pxc, remove_json_pointer, resolve_input_slot, resolve_node_id,
-    resolve_output_slot, set_input_value_in_pxc, set_json_pointer,
+    add_node_to_pxc, get_input_expression_in_pxc, get_input_value_in_pxc, remove_json_pointer,
+    resolve_input_slot, resolve_node_id, resolve_output_slot, set_input_expression_in_pxc,
+    set_input_value_in_pxc, set_json_pointer,
+};
+use crate::palette::{
+    PaletteFormat, PaletteMap, palette_in_pxc, remap_palette_in_pxc, render_palette,
 };
 use crate::pxc::{PxcFile, read_pxc, write_pxc};
-use crate::registry::{RegistryPort, embedded_registry_inner};
-
-static NODE_COUNTER: AtomicU64 = AtomicU64::new(1);
+use crate::registry::{Registry, embedded_registry_for};
 
 fn py_err<E: std::fmt::Display>(err: E) -> PyErr {
     PyRuntimeError::new_err(err.to_string())
@@ -34,64 +56,19 @@ fn py_any_to_value(py: Python<'_>, value: &Bound<'_, PyAny>) -> PyResult<Value>
     serde_json::from_str(&s).map_err(py_err)
 }
 
-fn display_name_from_type(node_type: &str) -> String {
-    let base = node_type.strip_prefix("Node_").unwrap_or(node_type);
-    base.replace('_', " ")
-}
-
-fn default_value_for_port(port: &RegistryPort) -> Value {
-    let ty = port.ty.as_deref().unwrap_or("");
-    let ty_lower = ty.to_ascii_lowercase();
-    let name_lower = port.name.as_deref().unwrap_or("").to_ascii_lowercase();
-    if ty_lower.contains("gradient") {
-        return default_gradient_value();
-    }
-    if ty_lower.contains("color") || name_lower.contains("color") || name_lower.contains("colour") {
-        return Value::Number(0xFFFF_FFFFu32.into());
-    }
-    if ty_lower.contains("toggle") || ty_lower.contains("bool") || name_lower.contains("enable") {
-        return Value::Bool(false);
-    }
-    if ty_lower.contains("string") || ty_lower.contains("text") || ty_lower.contains("path") {
-        return Value::String(String::new());
-    }
-    if ty_lower.contains("vector2") || ty_lower.contains("vec2") {
-        return Value::Array(vec![Value::Number(0.into()), Value::Number(0.into())]);
-    }
-    if ty_lower.contains("vector3") || ty_lower.contains("vec3") {
-        return Value::Array(vec![
-            Value::Number(0.into()),
-            Value::Number(0.into()),
-            Value::Number(0.into()),
-        ]);
-    }
-    if ty_lower.contains("vector4") || ty_lower.contains("vec4") {
-        return Value::Array(vec![
-            Value::Number(0.into()),
-            Value::Number(0.into()),
-            Value::Number(0.into()),
-            Value::Number(0.into()),
-        ]);
-    }
-    if ty_lower.contains("array") {
-        return Value::Array(vec![]);
-    }
-    if ty_lower.contains("number")
-        || ty_lower.contains("float")
-        || ty_lower.contains("integer")
-        || ty_lower.contains("slider")
-    {
-        return Value::Number(0.into());
-    }
-    Value::Null
-}
-
 #[pyclass]
 struct Project {
     inner: PxcFile,
     path: Option<PathBuf>,
 }
 
+impl Project {
+    /// The embedded registry matching this project's save version.
+    fn registry(&self) -> Registry {
+        embedded_registry_for(self.inner.save_version())
+    }
+}
+
 #[pymethods]
 impl Project {
     #[staticmethod]
@@ -125,6 +102,7 @@ impl Project {
         Ok(s)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn graph_json(
         &self,
         pretty: Option<bool>,
@@ -134,6 +112,7 @@ impl Project {
         include_edges: Option<bool>,
         full_ids: Option<bool>,
         mode: Option<&str>,
+        group: Option<&str>,
     ) -> PyResult<String> {
         let mode = match mode.unwrap_or("compact") {
             "summary" => GraphMode::Summary,
@@ -150,6 +129,7 @@ impl Project {
             full_ids.unwrap_or(false),
             include_edges.unwrap_or(false),
             None,
+            group,
         )
         .map_err(py_err)?;
         let s = if pretty.unwrap_or(false) {
@@ -161,6 +141,13 @@ impl Project {
         Ok(s)
     }
 
+    #[pyo3(signature = (graph_json, group=None))]
+    fn apply_graph_json(&mut self, graph_json: &str, group: Option<&str>) -> PyResult<Vec<String>> {
+        let doc: Value = serde_json::from_str(graph_json).map_err(py_err)?;
+        let registry = self.registry();
+        apply_graph_json_to_pxc(&mut self.inner, &doc, &registry, group).map_err(py_err)
+    }
+
     fn get(&self, pointer: &str) -> PyResult<String> {
         let val = self
             .inner
@@ -177,14 +164,9 @@ impl Project {
         input: Option<usize>,
         input_name: Option<&str>,
     ) -> PyResult<String> {
-        let val = get_input_value_in_pxc(
-            &self.inner,
-            node,
-            input,
-            input_name,
-            Some(&embedded_registry_inner()),
-        )
-        .map_err(py_err)?;
+        let val =
+            get_input_value_in_pxc(&self.inner, node, input, input_name, Some(&self.registry()))
+                .map_err(py_err)?;
         serde_json::to_string(&val).map_err(py_err)
     }
 
@@ -195,7 +177,12 @@ impl Project {
     }
 
     #[pyo3(signature = (pointer, value))]
-    fn set_value(&mut self, py: Python<'_>, pointer: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+    fn set_value(
+        &mut self,
+        py: Python<'_>,
+        pointer: &str,
+        value: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
         let val = py_any_to_value(py, value)?;
         set_json_pointer(&mut self.inner.json, pointer, val).map_err(py_err)?;
         Ok(())
@@ -215,18 +202,60 @@ impl Project {
         input_name: Option<&str>,
     ) -> PyResult<()> {
         let value: Value = serde_json::from_str(value_json).map_err(py_err)?;
+        let registry = self.registry();
         set_input_value_in_pxc(
             &mut self.inner,
             node,
             input,
             input_name,
             value,
-            Some(&embedded_registry_inner()),
+            Some(&registry),
         )
         .map_err(py_err)?;
         Ok(())
     }
 
+    #[pyo3(signature = (node, input=None, input_name=None))]
+    fn input_expression(
+        &self,
+        node: &str,
+        input: Option<usize>,
+        input_name: Option<&str>,
+    ) -> PyResult<Option<String>> {
+        let registry = self.registry();
+        get_input_expression_in_pxc(&self.inner, node, input, input_name, Some(&registry))
+            .map_err(py_err)
+    }
+
+    #[pyo3(signature = (node, expression, input=None, input_name=None))]
+    fn set_input_expression(
+        &mut self,
+        node: &str,
+        expression: Option<&str>,
+        input: Option<usize>,
+        input_name: Option<&str>,
+    ) -> PyResult<()> {
+        let registry = self.registry();
+        set_input_expression_in_pxc(
+            &mut self.inner,
+            node,
+            input,
+            input_name,
+            expression,
+            Some(&registry),
+        )
+        .map_err(py_err)
+    }
+
+    fn lint(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let registry = self.registry();
+        let issues = lint_pxc(&self.inner, Some(&registry));
+        let json_str = serde_json::to_string(&issues).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
     #[pyo3(signature = (node, value, input=None, input_name=None))]
     fn set_input_value(
         &mut self,
@@ -237,13 +266,14 @@ impl Project {
         input_name: Option<&str>,
     ) -> PyResult<()> {
         let value = py_any_to_value(py, value)?;
+        let registry = self.registry();
         set_input_value_in_pxc(
             &mut self.inner,
             node,
             input,
             input_name,
             value,
-            Some(&embedded_registry_inner()),
+            Some(&registry),
         )
         .map_err(py_err)?;
         Ok(())
@@ -262,7 +292,7 @@ impl Project {
         let arr = ops
             .as_array()
             .ok_or_else(|| PyRuntimeError::new_err("ops_json must be a JSON array"))?;
-        let registry = embedded_registry_inner();
+        let registry = self.registry();
         let mut changed = 0usize;
         for op in arr {
             let obj = op
@@ -303,63 +333,16 @@ impl Project {
         y: Option<i32>,
         name: Option<&str>,
     ) -> PyResult<String> {
-        let registry = embedded_registry_inner();
-        let reg_node = registry
-            .nodes
-            .get(node_type)
-            .ok_or_else(|| PyRuntimeError::new_err("unknown node type"))?;
-        let mut inputs = Vec::with_capacity(reg_node.inputs.len());
-        for port in &reg_node.inputs {
-            let dv = default_value_for_port(port);
-            inputs.push(json!({"m":1,"r":{"d": dv}}));
-        }
-        let mut outputs = Vec::with_capacity(reg_node.outputs.len());
-        for _ in &reg_node.outputs {
-            outputs.push(json!({}));
-        }
-
-        let base = display_name_from_type(node_type);
-        let base_id = node_type
-            .strip_prefix("Node_")
-            .unwrap_or(node_type)
-            .replace('_', "");
-        let ts = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-        let c = NODE_COUNTER.fetch_add(1, Ordering::Relaxed);
-        let id = format!("node{}_{}", ts, c);
-        let iname = format!("{}{}", base_id, c);
-
-        let node_name = name.map(|s| s.to_string()).unwrap_or_else(|| base.clone());
-        let mut node = Map::new();
-        node.insert("id".to_string(), Value::String(id.clone()));
-        node.insert("type".to_string(), Value::String(node_type.to_string()));
-        node.insert("name".to_string(), Value::String(node_name));
-        node.insert("iname".to_string(), Value::String(iname));
-        node.insert("x".to_string(), Value::Number((x.unwrap_or(0)).into()));
-        node.insert("y".to_string(), Value::Number((y.unwrap_or(0)).into()));
-        node.insert("version".to_string(), Value::Number(120000.into()));
-        node.insert("renamed".to_string(), Value::Bool(name.is_some()));
-        node.insert("data_length".to_string(), Value::Number(1.into()));
-        node.insert(
-            "input_fix_len".to_string(),
-            Value::Number((reg_node.inputs.len() as u64).into()),
-        );
-        node.insert("inputs".to_string(), Value::Array(inputs));
-        node.insert("outputs".to_string(), Value::Array(outputs));
-        node.insert("attri".to_string(), Value::Object(Map::new()));
-        node.insert("insp_col".to_string(), Value::Object(Map::new()));
-        node.insert("inspectInputs".to_string(), Value::Array(vec![]));
-
-        let nodes = self
-            .inner
-            .json
-            .get_mut("nodes")
-            .and_then(|v| v.as_array_mut())
-            .ok_or_else(|| PyRuntimeError::new_err("nodes array missing"))?;
-        nodes.push(Value::Object(node));
-        Ok(id)
+        let registry = self.registry();
+        add_node_to_pxc(
+            &mut self.inner,
+            node_type,
+            x.unwrap_or(0),
+            y.unwrap_or(0),
+            name,
+            &registry,
+        )
+        .map_err(py_err)
     }
 
     #[pyo3(signature = (from_node, to_node, from_output=None, to_input=None, to_input_name=None, from_output_name=None))]
@@ -372,7 +355,7 @@ impl Project {
         to_input_name: Option<&str>,
         from_output_name: Option<&str>,
     ) -> PyResult<()> {
-        let registry = embedded_registry_inner();
+        let registry = self.registry();
         let nodes = self
             .inner
             .json
@@ -425,6 +408,432 @@ impl Project {
         Ok(())
     }
 
+    fn build_graph(&mut self, path: &str) -> PyResult<Vec<(String, String)>> {
+        let registry = self.registry();
+        build_graph_into_pxc(&mut self.inner, Path::new(path), &registry).map_err(py_err)
+    }
+
+    #[pyo3(signature = (nodes, name=None))]
+    fn group_nodes(&mut self, nodes: Vec<String>, name: Option<&str>) -> PyResult<String> {
+        let members: Vec<&str> = nodes.iter().map(|s| s.as_str()).collect();
+        let registry = self.registry();
+        group_nodes_in_pxc(&mut self.inner, &members, name, Some(&registry)).map_err(py_err)
+    }
+
+    fn ungroup(&mut self, group: &str) -> PyResult<usize> {
+        ungroup_in_pxc(&mut self.inner, group).map_err(py_err)
+    }
+
+    fn add_input_group(&mut self, node: &str) -> PyResult<usize> {
+        let registry = self.registry();
+        add_input_group_in_pxc(&mut self.inner, node, &registry).map_err(py_err)
+    }
+
+    fn remove_input_group(&mut self, node: &str, index: usize) -> PyResult<()> {
+        let registry = self.registry();
+        remove_input_group_in_pxc(&mut self.inner, node, index, &registry).map_err(py_err)
+    }
+
+    #[pyo3(signature = (node, input=None, frame=0.0, input_name=None))]
+    fn get_input_value_at(
+        &self,
+        py: Python<'_>,
+        node: &str,
+        input: Option<usize>,
+        frame: f64,
+        input_name: Option<&str>,
+    ) -> PyResult<PyObject> {
+        let registry = self.registry();
+        let value =
+            get_input_value_at_in_pxc(&self.inner, node, input, input_name, frame, Some(&registry))
+                .map_err(py_err)?;
+        let json_str = serde_json::to_string(&value).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
+    #[pyo3(signature = (node, input=None, input_name=None))]
+    fn keyframes(
+        &self,
+        py: Python<'_>,
+        node: &str,
+        input: Option<usize>,
+        input_name: Option<&str>,
+    ) -> PyResult<PyObject> {
+        let registry = self.registry();
+        let keys = keyframes_in_pxc(&self.inner, node, input, input_name, Some(&registry))
+            .map_err(py_err)?;
+        let json_str = serde_json::to_string(&keys).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
+    #[pyo3(signature = (
+        node, frame, value=None, input=None, input_name=None, interp=None,
+        interp_in=None, interp_out=None, ease_in=None, ease_out=None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn set_keyframe(
+        &mut self,
+        py: Python<'_>,
+        node: &str,
+        frame: i64,
+        value: Option<&Bound<'_, PyAny>>,
+        input: Option<usize>,
+        input_name: Option<&str>,
+        interp: Option<&str>,
+        interp_in: Option<&str>,
+        interp_out: Option<&str>,
+        ease_in: Option<(f64, f64)>,
+        ease_out: Option<(f64, f64)>,
+    ) -> PyResult<()> {
+        let value = value.map(|v| py_any_to_value(py, v)).transpose()?;
+        let parse = |name: Option<&str>| name.map(Interpolation::parse).transpose().map_err(py_err);
+        let interp = parse(interp)?;
+        let ease = KeyframeEase {
+            ease_in: ease_in.map(|(x, y)| [x, y]),
+            ease_out: ease_out.map(|(x, y)| [x, y]),
+            interp_in: parse(interp_in)?.or(interp),
+            interp_out: parse(interp_out)?.or(interp),
+        };
+        let registry = self.registry();
+        set_keyframe_in_pxc(
+            &mut self.inner,
+            node,
+            input,
+            input_name,
+            frame,
+            value,
+            &ease,
+            Some(&registry),
+        )
+        .map_err(py_err)
+    }
+
+    #[pyo3(signature = (node, frame, to_frame, input=None, input_name=None))]
+    fn move_keyframe(
+        &mut self,
+        node: &str,
+        frame: i64,
+        to_frame: i64,
+        input: Option<usize>,
+        input_name: Option<&str>,
+    ) -> PyResult<()> {
+        let registry = self.registry();
+        move_keyframe_in_pxc(
+            &mut self.inner,
+            node,
+            input,
+            input_name,
+            frame,
+            to_frame,
+            Some(&registry),
+        )
+        .map_err(py_err)
+    }
+
+    #[pyo3(signature = (node, frame, input=None, input_name=None))]
+    fn delete_keyframe(
+        &mut self,
+        node: &str,
+        frame: i64,
+        input: Option<usize>,
+        input_name: Option<&str>,
+    ) -> PyResult<()> {
+        let registry = self.registry();
+        delete_keyframe_in_pxc(
+            &mut self.inner,
+            node,
+            input,
+            input_name,
+            frame,
+            Some(&registry),
+        )
+        .map_err(py_err)
+    }
+
+    #[pyo3(signature = (node, animated, input=None, input_name=None))]
+    fn set_animated(
+        &mut self,
+        node: &str,
+        animated: bool,
+        input: Option<usize>,
+        input_name: Option<&str>,
+    ) -> PyResult<()> {
+        let registry = self.registry();
+        set_animated_in_pxc(
+            &mut self.inner,
+            node,
+            input,
+            input_name,
+            animated,
+            Some(&registry),
+        )
+        .map_err(py_err)
+    }
+
+    fn animation(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let settings = animation_settings(&self.inner);
+        let json_str = serde_json::to_string(&settings).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
+    #[pyo3(signature = (frames_total=None, framerate=None, frame_range=None, clear_range=false))]
+    fn set_animation(
+        &mut self,
+        frames_total: Option<u32>,
+        framerate: Option<f64>,
+        frame_range: Option<(i64, i64)>,
+        clear_range: bool,
+    ) -> PyResult<()> {
+        let mut settings = animation_settings(&self.inner);
+        if let Some(frames) = frames_total {
+            settings.frames_total = frames;
+        }
+        if let Some(fps) = framerate {
+            settings.framerate = fps;
+        }
+        if let Some((start, end)) = frame_range {
+            settings.frame_range = Some([start, end]);
+        } else if clear_range {
+            settings.frame_range = None;
+        }
+        set_animation_settings(&mut self.inner, &settings).map_err(py_err)
+    }
+
+    #[pyo3(signature = (frames_total=None, framerate=None))]
+    fn retime(&mut self, frames_total: Option<u32>, framerate: Option<f64>) -> PyResult<usize> {
+        let report = retime_pxc(&mut self.inner, frames_total, framerate).map_err(py_err)?;
+        Ok(report.keys_moved)
+    }
+
+    fn globals(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let globals = globals_in_pxc(&self.inner);
+        let json_str = serde_json::to_string(&globals).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
+    fn get_global(&self, py: Python<'_>, name: &str) -> PyResult<PyObject> {
+        let global = get_global_in_pxc(&self.inner, name).map_err(py_err)?;
+        let json_str = serde_json::to_string(&global).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
+    #[pyo3(signature = (name, r#type="float", value=None))]
+    fn add_global(
+        &mut self,
+        py: Python<'_>,
+        name: &str,
+        r#type: &str,
+        value: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<()> {
+        let ty = GlobalType::parse(r#type).map_err(py_err)?;
+        let value = value.map(|v| py_any_to_value(py, v)).transpose()?;
+        add_global_to_pxc(&mut self.inner, name, ty, value).map_err(py_err)
+    }
+
+    #[pyo3(signature = (name, value=None, r#type=None, rename=None))]
+    fn set_global(
+        &mut self,
+        py: Python<'_>,
+        name: &str,
+        value: Option<&Bound<'_, PyAny>>,
+        r#type: Option<&str>,
+        rename: Option<String>,
+    ) -> PyResult<usize> {
+        let edit = GlobalEdit {
+            value: value.map(|v| py_any_to_value(py, v)).transpose()?,
+            ty: r#type.map(GlobalType::parse).transpose().map_err(py_err)?,
+            rename,
+        };
+        set_global_in_pxc(&mut self.inner, name, &edit).map_err(py_err)
+    }
+
+    fn remove_global(&mut self, name: &str) -> PyResult<usize> {
+        remove_global_from_pxc(&mut self.inner, name).map_err(py_err)
+    }
+
+    fn notes(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let notes = notes_in_pxc(&self.inner);
+        let json_str = serde_json::to_string(&notes).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
+    #[pyo3(signature = (text, x=None, y=None, width=None, height=None, color=None))]
+    fn add_note(
+        &mut self,
+        text: &str,
+        x: Option<f64>,
+        y: Option<f64>,
+        width: Option<f64>,
+        height: Option<f64>,
+        color: Option<&str>,
+    ) -> PyResult<usize> {
+        let edit = NoteEdit {
+            x,
+            y,
+            w: width,
+            h: height,
+            text: None,
+            color: color.map(parse_note_color).transpose().map_err(py_err)?,
+        };
+        add_note_to_pxc(&mut self.inner, text, &edit).map_err(py_err)
+    }
+
+    #[pyo3(signature = (index, text=None, x=None, y=None, width=None, height=None, color=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn edit_note(
+        &mut self,
+        index: usize,
+        text: Option<String>,
+        x: Option<f64>,
+        y: Option<f64>,
+        width: Option<f64>,
+        height: Option<f64>,
+        color: Option<&str>,
+    ) -> PyResult<()> {
+        let edit = NoteEdit {
+            x,
+            y,
+            w: width,
+            h: height,
+            text,
+            color: color.map(parse_note_color).transpose().map_err(py_err)?,
+        };
+        edit_note_in_pxc(&mut self.inner, index, &edit).map_err(py_err)
+    }
+
+    fn remove_note(&mut self, index: usize) -> PyResult<()> {
+        remove_note_from_pxc(&mut self.inner, index)
+            .map(|_| ())
+            .map_err(py_err)
+    }
+
+    fn metadata(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let meta = metadata_in_pxc(&self.inner);
+        let json_str = serde_json::to_string(&meta).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
+    #[pyo3(signature = (author=None, description=None, contact=None, alias=None, tags=None, add_tags=None, remove_tags=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn set_metadata(
+        &mut self,
+        author: Option<String>,
+        description: Option<String>,
+        contact: Option<String>,
+        alias: Option<String>,
+        tags: Option<Vec<String>>,
+        add_tags: Option<Vec<String>>,
+        remove_tags: Option<Vec<String>>,
+    ) -> PyResult<bool> {
+        let edit = MetaEdit {
+            author,
+            description,
+            contact,
+            alias,
+            tags,
+            add_tags: add_tags.unwrap_or_default(),
+            remove_tags: remove_tags.unwrap_or_default(),
+        };
+        set_metadata_in_pxc(&mut self.inner, &edit).map_err(py_err)
+    }
+
+    fn favorites(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let favorites = favorites_in_pxc(&self.inner, Some(&self.registry()));
+        let json_str = serde_json::to_string(&favorites).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
+    #[pyo3(signature = (node, input=None, input_name=None))]
+    fn add_favorite(
+        &mut self,
+        node: &str,
+        input: Option<usize>,
+        input_name: Option<&str>,
+    ) -> PyResult<bool> {
+        let registry = self.registry();
+        add_favorite_in_pxc(&mut self.inner, node, input, input_name, Some(&registry))
+            .map_err(py_err)
+    }
+
+    #[pyo3(signature = (node, input=None, input_name=None))]
+    fn remove_favorite(
+        &mut self,
+        node: &str,
+        input: Option<usize>,
+        input_name: Option<&str>,
+    ) -> PyResult<()> {
+        let registry = self.registry();
+        remove_favorite_in_pxc(&mut self.inner, node, input, input_name, Some(&registry))
+            .map_err(py_err)
+    }
+
+    fn panels(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let panels = panels_in_pxc(&self.inner, Some(&self.registry()));
+        let json_str = serde_json::to_string(&panels).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
+    /// `inputs` is a list of `(node, input)` pairs; `input` is a slot or a name.
+    fn add_panel(
+        &mut self,
+        name: &str,
+        inputs: Vec<(String, Bound<'_, PyAny>)>,
+    ) -> PyResult<usize> {
+        let registry = self.registry();
+        let mut refs = Vec::new();
+        for (node, input) in inputs.iter() {
+            let resolved = match input.extract::<usize>() {
+                Ok(slot) => resolve_input(&self.inner, node, Some(slot), None, Some(&registry)),
+                Err(_) => {
+                    let input_name: String = input.extract()?;
+                    resolve_input(&self.inner, node, None, Some(&input_name), Some(&registry))
+                }
+            };
+            refs.push(resolved.map_err(py_err)?);
+        }
+        add_panel_to_pxc(&mut self.inner, name, &refs).map_err(py_err)
+    }
+
+    fn remove_panel(&mut self, index: usize) -> PyResult<()> {
+        remove_panel_from_pxc(&mut self.inner, index).map_err(py_err)
+    }
+
+    #[pyo3(signature = (nodes=None, spacing_x=None, spacing_y=None, origin_only=false))]
+    fn auto_layout(
+        &mut self,
+        nodes: Option<Vec<String>>,
+        spacing_x: Option<f64>,
+        spacing_y: Option<f64>,
+        origin_only: bool,
+    ) -> PyResult<usize> {
+        let only: Option<Vec<&str>> = nodes
+            .as_ref()
+            .map(|list| list.iter().map(|s| s.as_str()).collect());
+        let spacing = (
+            spacing_x.unwrap_or(DEFAULT_SPACING.0),
+            spacing_y.unwrap_or(DEFAULT_SPACING.1),
+        );
+        auto_layout_pxc(&mut self.inner, only.as_deref(), spacing, origin_only).map_err(py_err)
+    }
+
     #[pyo3(signature = (node))]
     fn set_preview_node(&mut self, node: &str) -> PyResult<()> {
         let nodes = self
@@ -451,7 +860,12 @@ impl Project {
     }
 
     #[pyo3(signature = (keys, interp=0))]
-    fn add_gradient(&self, py: Python<'_>, keys: &Bound<'_, PyAny>, interp: i32) -> PyResult<String> {
+    fn add_gradient(
+        &self,
+        py: Python<'_>,
+        keys: &Bound<'_, PyAny>,
+        interp: i32,
+    ) -> PyResult<String> {
         let value = py_any_to_value(py, keys)?;
         let arr = value.as_array().ok_or_else(|| {
             PyRuntimeError::new_err("keys must be an array of [time, color] pairs")
@@ -480,7 +894,7 @@ impl Project {
 
     #[pyo3(signature = (node_type))]
     fn list_node_inputs_json(&self, node_type: &str) -> PyResult<String> {
-        let registry = embedded_registry_inner();
+        let registry = self.registry();
         let node = registry
             .nodes
             .get(node_type)
@@ -499,7 +913,7 @@ impl Project {
 
     #[pyo3(signature = (node_type))]
     fn list_node_outputs_json(&self, node_type: &str) -> PyResult<String> {
-        let registry = embedded_registry_inner();
+        let registry = self.registry();
         let node = registry
             .nodes
             .get(node_type)
@@ -517,7 +931,7 @@ impl Project {
     }
 
     fn list_node_types_json(&self) -> PyResult<String> {
-        let registry = embedded_registry_inner();
+        let registry = self.registry();
         let mut keys: Vec<String> = registry.nodes.keys().cloned().collect();
         keys.sort();
         serde_json::to_string(&keys).map_err(py_err)
@@ -530,6 +944,52 @@ impl Project {
         Ok(loaded.unbind())
     }
 
+    fn palette(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let colors = palette_in_pxc(&self.inner, Some(&self.registry()));
+        let json_str = serde_json::to_string(&colors).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
+    /// Writes the palette as `json`, `png`, `hex` or `gpl`, by default the format
+    /// of the file extension. Returns the number of colours.
+    #[pyo3(signature = (path, format=None, swatch=16))]
+    fn save_palette(&self, path: &str, format: Option<&str>, swatch: u32) -> PyResult<usize> {
+        let path = Path::new(path);
+        let format = format
+            .or_else(|| path.extension().and_then(|e| e.to_str()))
+            .unwrap_or("json");
+        let format = PaletteFormat::from_extension(format)
+            .ok_or_else(|| py_err(format!("unknown palette format: {}", format)))?;
+        let colors = palette_in_pxc(&self.inner, Some(&self.registry()));
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("palette");
+        let bytes = render_palette(&colors, format, name, swatch).map_err(py_err)?;
+        std::fs::write(path, bytes).map_err(py_err)?;
+        Ok(colors.len())
+    }
+
+    /// `mapping` is `{"#source": "#target", ...}` or `{"from": [...], "to": [...]}`.
+    /// Returns substitutions per node.
+    #[pyo3(signature = (mapping, nearest=false))]
+    fn remap_palette(
+        &mut self,
+        py: Python<'_>,
+        mapping: &Bound<'_, PyAny>,
+        nearest: bool,
+    ) -> PyResult<PyObject> {
+        let map = PaletteMap::from_json(&py_any_to_value(py, mapping)?).map_err(py_err)?;
+        let registry = self.registry();
+        let report = remap_palette_in_pxc(&mut self.inner, &map, nearest, Some(&registry));
+        let json_str = serde_json::to_string(&report).map_err(py_err)?;
+        let json_mod = py.import_bound("json")?;
+        let loaded = json_mod.call_method1("loads", (json_str,))?;
+        Ok(loaded.unbind())
+    }
+
     fn hue_set_all(&mut self, hue_deg: f64) -> PyResult<usize> {
         let changed = crate::color::hue_set_pxc(&mut self.inner, hue_deg);
         Ok(changed)
@@ -1,44 +1,305 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use anyhow::{Result, anyhow};
-use regex::Regex;
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Serialize;
 use serde_json::{Map, Value, json};
 use walkdir::WalkDir;
 
+use crate::gml::{self, Expr, PortCall};
+use crate::pxc::SAVE_VERSION;
+
 #[derive(Clone, Debug)]
 pub struct Registry {
     pub nodes: std::collections::HashMap<String, RegistryNode>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct RegistryNode {
     pub inputs: Vec<RegistryPort>,
     pub outputs: Vec<RegistryPort>,
+    /// Repeating input group of variable-length nodes (layer stacks, composite,
+    /// array builders). Copies of it follow the fixed `inputs`, one per group.
+    pub dynamic_inputs: Vec<RegistryPort>,
 }
 
-#[derive(Clone, Debug, serde::Serialize)]
+impl RegistryNode {
+    /// Port at `slot`, continuing into the repeated dynamic groups past the
+    /// fixed inputs.
+    pub fn input(&self, slot: usize) -> Option<&RegistryPort> {
+        if let Some(port) = self.inputs.get(slot) {
+            return Some(port);
+        }
+        let (_, offset) = self.dynamic_position(slot)?;
+        self.dynamic_inputs.get(offset)
+    }
+
+    /// Group index and position within the group of a dynamic input slot.
+    pub fn dynamic_position(&self, slot: usize) -> Option<(usize, usize)> {
+        let len = self.dynamic_inputs.len();
+        let rel = slot.checked_sub(self.inputs.len())?;
+        if len == 0 {
+            return None;
+        }
+        Some((rel / len, rel % len))
+    }
+
+    /// Label of the dynamic group, taken from its first port (`Layer`).
+    pub fn dynamic_group_name(&self) -> Option<&str> {
+        self.dynamic_inputs.first()?.name.as_deref()
+    }
+
+    /// Input name for display: fixed inputs by their name, dynamic ones as
+    /// `Layer[2]` (first port of group 2) or `Layer[2].Opacity`.
+    pub fn input_name(&self, slot: usize) -> Option<String> {
+        if let Some(port) = self.inputs.get(slot) {
+            return port.name.clone();
+        }
+        let (group, offset) = self.dynamic_position(slot)?;
+        let label = self.dynamic_group_name().unwrap_or("in");
+        match offset {
+            0 => Some(format!("{}[{}]", label, group)),
+            _ => {
+                let name = self.dynamic_inputs[offset].name.as_deref()?;
+                Some(format!("{}[{}].{}", label, group, name))
+            }
+        }
+    }
+
+    /// Slot for an input name as produced by [`RegistryNode::input_name`].
+    pub fn input_slot(&self, name: &str) -> Option<usize> {
+        if let Some(i) = self
+            .inputs
+            .iter()
+            .position(|p| p.name.as_deref() == Some(name))
+        {
+            return Some(i);
+        }
+        let (label, rest) = name.split_once('[')?;
+        let (group, port) = rest.split_once(']')?;
+        let group: usize = group.trim().parse().ok()?;
+        let label_matches = self
+            .dynamic_group_name()
+            .is_some_and(|l| l.eq_ignore_ascii_case(label.trim()));
+        if !label_matches {
+            return None;
+        }
+        let offset = match port.strip_prefix('.') {
+            Some(port) => self.dynamic_inputs.iter().position(|p| {
+                p.name
+                    .as_deref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(port.trim()))
+            })?,
+            None if port.trim().is_empty() => 0,
+            None => return None,
+        };
+        Some(self.inputs.len() + group * self.dynamic_inputs.len() + offset)
+    }
+}
+
+impl Registry {
+    pub fn input_port(&self, node_type: &str, slot: usize) -> Option<&RegistryPort> {
+        self.nodes.get(node_type)?.input(slot)
+    }
+
+    pub fn output_port(&self, node_type: &str, slot: usize) -> Option<&RegistryPort> {
+        self.nodes.get(node_type)?.outputs.get(slot)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct RegistryPort {
     pub name: Option<String>,
     pub ty: Option<String>,
     pub tooltip: Option<String>,
+    /// Default value from the `nodeValue_*` constructor, as stored in a project.
+    pub default: Option<Value>,
+    /// Labels of an enum input; the stored value is the option index.
+    pub options: Option<Vec<String>>,
+    /// Slider bounds `(min, max)`.
+    pub range: Option<(f64, f64)>,
+    pub step: Option<f64>,
+    /// Initial junction visibility from `setVisible`.
+    pub visible: Option<bool>,
+    /// `VALUE_DISPLAY` widget name from `setDisplay`.
+    pub display: Option<String>,
 }
 
-pub fn load_registry(path: Option<&Path>) -> Result<Option<Registry>> {
-    if let Some(p) = path {
-        return Ok(Some(load_registry_file(p)?));
+/// Serializes like a registry file entry: `type`, and `range` as
+/// `[min, max, step]`.
+impl Serialize for RegistryPort {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        port_to_json(self).serialize(serializer)
+    }
+}
+
+impl RegistryPort {
+    /// Short value type (`surface`, `float`, ...), whether `type` holds a
+    /// constructor name such as `nodeValue_Surface` or a `VALUE_TYPE` member.
+    pub fn value_type(&self) -> Option<String> {
+        self.ty.as_deref().and_then(infer_type_from_fn)
+    }
+
+    /// Slider range as `[min, max, step]`, or `[min, max]` without a step.
+    pub(crate) fn range_json(&self) -> Option<Value> {
+        let (min, max) = self.range?;
+        Some(match self.step {
+            Some(step) => json!([min, max, step]),
+            None => json!([min, max]),
+        })
+    }
+
+    /// Checks a value against the port's enum options. Slider ranges only
+    /// bound the widget, so values outside them are left to `lint`.
+    pub fn check_value(&self, value: &Value) -> Result<()> {
+        let label = self.name.as_deref().unwrap_or("input");
+        if let Some(options) = &self.options
+            && !options.is_empty()
+        {
+            let index = value.as_f64().filter(|f| f.fract() == 0.0);
+            if !index.is_some_and(|i| i >= 0.0 && (i as usize) < options.len()) {
+                bail!(
+                    "{} expects an option index 0..{} ({}), got {}",
+                    label,
+                    options.len() - 1,
+                    options.join(", "),
+                    value
+                );
+            }
+        }
+        Ok(())
     }
 
-    Ok(Some(embedded_registry_inner()))
+    /// Why a number falls outside the port's slider range, if it does.
+    pub fn range_issue(&self, value: &Value) -> Option<String> {
+        let (min, max) = self.range?;
+        let v = value.as_f64()?;
+        (v < min || v > max).then(|| format!("{} is outside the slider range {}..{}", v, min, max))
+    }
 }
 
+/// Registries compiled into the binary, keyed by the save version of the Pixel
+/// Composer source they were built from.
+const EMBEDDED_REGISTRIES: &[(u32, &str)] =
+    &[(SAVE_VERSION, include_str!("registry_embedded.json"))];
+
+enum RegistrySource {
+    Embedded(&'static str),
+    File(PathBuf),
+}
+
+/// The registry picked for a project, and what it was picked from.
+#[derive(Clone, Debug)]
+pub struct RegistryChoice {
+    /// `embedded` or the registry file's path.
+    pub source: String,
+    /// Save version the chosen registry is keyed by (`None` for a single file).
+    pub version: Option<u32>,
+    /// Save versions of every registry that was considered.
+    pub available: Vec<u32>,
+}
+
+/// Candidate registries: the embedded set, one file, or every `<version>.json`
+/// (any name ending in the save version) in a directory.
+fn registry_candidates(path: Option<&Path>) -> Result<Vec<(Option<u32>, RegistrySource)>> {
+    let Some(path) = path else {
+        return Ok(EMBEDDED_REGISTRIES
+            .iter()
+            .map(|(v, data)| (Some(*v), RegistrySource::Embedded(data)))
+            .collect());
+    };
+    if !path.is_dir() {
+        return Ok(vec![(None, RegistrySource::File(path.to_path_buf()))]);
+    }
+    let mut out = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let file = entry?.path();
+        if file.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        if let Ok(version) = stem[stem.len() - digits..].parse::<u32>() {
+            out.push((Some(version), RegistrySource::File(file)));
+        }
+    }
+    if out.is_empty() {
+        bail!(
+            "no versioned registries (e.g. 1180.json) in {}",
+            path.display()
+        );
+    }
+    Ok(out)
+}
+
+/// Index of the newest registry not newer than `version`, else the oldest one.
+/// Without a version the newest registry wins.
+fn pick_registry(candidates: &[(Option<u32>, RegistrySource)], version: Option<u32>) -> usize {
+    let key = |i: usize| candidates[i].0.unwrap_or(0);
+    let indices = 0..candidates.len();
+    let fitting = indices
+        .clone()
+        .filter(|&i| version.is_none_or(|v| key(i) <= v))
+        .max_by_key(|&i| key(i));
+    fitting.unwrap_or_else(|| indices.min_by_key(|&i| key(i)).unwrap_or(0))
+}
+
+fn choose_registry(
+    path: Option<&Path>,
+    version: Option<u32>,
+) -> Result<(RegistryChoice, RegistrySource)> {
+    let mut candidates = registry_candidates(path)?;
+    let index = pick_registry(&candidates, version);
+    let mut available: Vec<u32> = candidates.iter().filter_map(|(v, _)| *v).collect();
+    available.sort_unstable();
+    let (chosen_version, source) = candidates.swap_remove(index);
+    let choice = RegistryChoice {
+        source: match &source {
+            RegistrySource::Embedded(_) => "embedded".to_string(),
+            RegistrySource::File(p) => p.display().to_string(),
+        },
+        version: chosen_version,
+        available,
+    };
+    Ok((choice, source))
+}
+
+/// Which registry [`load_registry`] would use for a project of `version`.
+pub fn registry_choice(path: Option<&Path>, version: Option<u32>) -> Result<RegistryChoice> {
+    Ok(choose_registry(path, version)?.0)
+}
+
+/// Loads the registry for a project of save `version`. `path` may be a registry
+/// file, or a directory of registries keyed by save version; without it the
+/// embedded registries are used.
+pub fn load_registry(path: Option<&Path>, version: Option<u32>) -> Result<Option<Registry>> {
+    let (_, source) = choose_registry(path, version)?;
+    let registry = match source {
+        RegistrySource::Embedded(data) => load_registry_from_str(data)?,
+        RegistrySource::File(p) => load_registry_file(&p)
+            .with_context(|| format!("failed to load registry {}", p.display()))?,
+    };
+    Ok(Some(registry))
+}
+
+/// The newest embedded registry.
 pub fn embedded_registry() -> Registry {
     embedded_registry_inner()
 }
 
 pub(crate) fn embedded_registry_inner() -> Registry {
-    let data = include_str!("registry_embedded.json");
-    load_registry_from_str(data).expect("embedded registry JSON is invalid")
+    embedded_registry_for(None)
+}
+
+/// The embedded registry matching a project's save version.
+pub(crate) fn embedded_registry_for(version: Option<u32>) -> Registry {
+    load_registry(None, version)
+        .ok()
+        .flatten()
+        .expect("embedded registry JSON is invalid")
 }
 
 fn load_registry_file(path: &Path) -> Result<Registry> {
@@ -57,9 +318,12 @@ fn load_registry_from_str(data: &str) -> Result<Registry> {
             Some(v) => v,
             None => continue,
         };
-        let inputs = parse_registry_ports(node_obj.get("inputs"));
-        let outputs = parse_registry_ports(node_obj.get("outputs"));
-        nodes.insert(node_name.clone(), RegistryNode { inputs, outputs });
+        let node = RegistryNode {
+            inputs: parse_registry_ports(node_obj.get("inputs")),
+            outputs: parse_registry_ports(node_obj.get("outputs")),
+            dynamic_inputs: parse_registry_ports(node_obj.get("dynamic_inputs")),
+        };
+        nodes.insert(node_name.clone(), node);
     }
     Ok(Registry { nodes })
 }
@@ -72,26 +336,26 @@ fn parse_registry_ports(v: Option<&Value>) -> Vec<RegistryPort> {
     };
     for item in arr {
         if let Some(obj) = item.as_object() {
+            let text = |key: &str| obj.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+            let range = obj.get("range").and_then(|v| v.as_array());
+            let bound = |i: usize| range.and_then(|r| r.get(i)).and_then(|v| v.as_f64());
             out.push(RegistryPort {
-                name: obj
-                    .get("name")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string()),
-                ty: obj
-                    .get("type")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string()),
-                tooltip: obj
-                    .get("tooltip")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string()),
+                name: text("name"),
+                ty: text("type"),
+                tooltip: text("tooltip"),
+                default: obj.get("default").filter(|v| !v.is_null()).cloned(),
+                options: obj.get("options").and_then(|v| v.as_array()).map(|a| {
+                    a.iter()
+                        .map(|o| o.as_str().unwrap_or("").to_string())
+                        .collect()
+                }),
+                range: bound(0).zip(bound(1)),
+                step: bound(2),
+                visible: obj.get("visible").and_then(|v| v.as_bool()),
+                display: text("display"),
             });
         } else {
-            out.push(RegistryPort {
-                name: None,
-                ty: None,
-                tooltip: None,
-            });
+            out.push(RegistryPort::default());
         }
     }
     out
@@ -111,7 +375,14 @@ fn load_locale_registry(path: &Path) -> Result<Registry> {
         };
         let inputs = parse_registry_ports(node_obj.get("inputs"));
         let outputs = parse_registry_ports(node_obj.get("outputs"));
-        nodes.insert(node_name.clone(), RegistryNode { inputs, outputs });
+        nodes.insert(
+            node_name.clone(),
+            RegistryNode {
+                inputs,
+                outputs,
+                ..RegistryNode::default()
+            },
+        );
     }
     Ok(Registry { nodes })
 }
@@ -125,12 +396,6 @@ pub(crate) fn cmd_registry_build(scripts: &Path, locale: Option<&Path>, out: &Pa
         None
     };
 
-    let node_fn_re = Regex::new(r"function\\s+(Node_[A-Za-z0-9_]+)")?;
-    let new_input_re = Regex::new(r"newInput[^,]*,\\s*(new\\s+)?([A-Za-z_][A-Za-z0-9_]*)")?;
-    let new_output_re = Regex::new(r"newOutput[^,]*,\\s*(new\\s+)?([A-Za-z_][A-Za-z0-9_]*)")?;
-    let name_re = Regex::new(r#"\"([^\"]+)\""#)?;
-    let value_type_re = Regex::new(r"VALUE_TYPE\\.([A-Za-z0-9_]+)")?;
-
     for entry in WalkDir::new(scripts).into_iter().filter_map(Result::ok) {
         if !entry.file_type().is_file() {
             continue;
@@ -139,68 +404,33 @@ pub(crate) fn cmd_registry_build(scripts: &Path, locale: Option<&Path>, out: &Pa
             continue;
         }
         let text = fs::read_to_string(entry.path()).unwrap_or_default();
-        let node_name = node_fn_re
-            .captures(&text)
-            .and_then(|c| c.get(1).map(|m| m.as_str().to_string()));
-        let node_name = match node_name {
-            Some(n) => n,
-            None => continue,
-        };
-
-        let mut inputs: Vec<Option<RegistryPort>> = Vec::new();
-        for cap in new_input_re.captures_iter(&text) {
-            let whole = cap.get(0).map(|m| m.as_str()).unwrap_or("");
-            let func = cap.get(2).map(|m| m.as_str()).unwrap_or("");
-            let ty = infer_type_from_fn_with_value(func, whole, &value_type_re);
-            let name = name_re
-                .captures(whole)
-                .and_then(|c| c.get(1).map(|m| m.as_str().to_string()));
-            let slot = extract_slot(whole, "newInput").unwrap_or(inputs.len());
-            if inputs.len() <= slot {
-                inputs.resize_with(slot + 1, || None);
+        for def in gml::node_definitions(&text) {
+            let inputs = ports_from_calls(&def.inputs, true);
+            let outputs = ports_from_calls(&def.outputs, false);
+            let mut dynamic_inputs = dynamic_ports_from_calls(&def.inputs);
+            if let Some(len) = def.dynamic_len
+                && !dynamic_inputs.is_empty()
+            {
+                dynamic_inputs.resize_with(len, RegistryPort::default);
             }
-            inputs[slot] = Some(RegistryPort {
-                name,
-                ty,
-                tooltip: None,
-            });
-        }
-
-        let mut outputs: Vec<Option<RegistryPort>> = Vec::new();
-        for cap in new_output_re.captures_iter(&text) {
-            let whole = cap.get(0).map(|m| m.as_str()).unwrap_or("");
-            let func = cap.get(2).map(|m| m.as_str()).unwrap_or("");
-            let ty = infer_type_from_fn_with_value(func, whole, &value_type_re);
-            let name = name_re
-                .captures(whole)
-                .and_then(|c| c.get(1).map(|m| m.as_str().to_string()));
-            let slot = extract_slot(whole, "newOutput").unwrap_or(outputs.len());
-            if outputs.len() <= slot {
-                outputs.resize_with(slot + 1, || None);
-            }
-            outputs[slot] = Some(RegistryPort {
-                name,
-                ty,
-                tooltip: None,
-            });
+            nodes.insert(
+                def.name,
+                RegistryNode {
+                    inputs: compact_ports(inputs),
+                    outputs: compact_ports(outputs),
+                    dynamic_inputs,
+                },
+            );
         }
+    }
 
-        if let Some(locale_reg) = &locale_nodes {
-            if let Some(lr) = locale_reg.nodes.get(&node_name) {
-                let inputs_compact = compact_ports(inputs);
-                let outputs_compact = compact_ports(outputs);
-                inputs = expand_ports(merge_registry_ports(&inputs_compact, &lr.inputs));
-                outputs = expand_ports(merge_registry_ports(&outputs_compact, &lr.outputs));
-            }
+    // Locale entries name the ports and also cover nodes without a script of their own.
+    if let Some(locale_reg) = locale_nodes {
+        for (name, lr) in locale_reg.nodes {
+            let node = nodes.entry(name).or_default();
+            node.inputs = merge_registry_ports(&lr.inputs, &node.inputs);
+            node.outputs = merge_registry_ports(&lr.outputs, &node.outputs);
         }
-
-        nodes.insert(
-            node_name,
-            RegistryNode {
-                inputs: compact_ports(inputs),
-                outputs: compact_ports(outputs),
-            },
-        );
     }
 
     let json = registry_to_json(&Registry { nodes });
@@ -224,6 +454,22 @@ fn merge_registry_ports(a: &[RegistryPort], b: &[RegistryPort]) -> Vec<RegistryP
             tooltip: pa
                 .and_then(|p| p.tooltip.clone())
                 .or_else(|| pb.and_then(|p| p.tooltip.clone())),
+            default: pa
+                .and_then(|p| p.default.clone())
+                .or_else(|| pb.and_then(|p| p.default.clone())),
+            options: pa
+                .and_then(|p| p.options.clone())
+                .or_else(|| pb.and_then(|p| p.options.clone())),
+            range: pa
+                .and_then(|p| p.range)
+                .or_else(|| pb.and_then(|p| p.range)),
+            step: pa.and_then(|p| p.step).or_else(|| pb.and_then(|p| p.step)),
+            visible: pa
+                .and_then(|p| p.visible)
+                .or_else(|| pb.and_then(|p| p.visible)),
+            display: pa
+                .and_then(|p| p.display.clone())
+                .or_else(|| pb.and_then(|p| p.display.clone())),
         });
     }
     out
@@ -234,30 +480,44 @@ fn registry_to_json(reg: &Registry) -> Value {
     for (node_name, node) in reg.nodes.iter() {
         let inputs = registry_ports_to_json(&node.inputs);
         let outputs = registry_ports_to_json(&node.outputs);
-        obj.insert(
-            node_name.clone(),
-            json!({
-                "inputs": inputs,
-                "outputs": outputs
-            }),
-        );
+        let mut entry = json!({
+            "inputs": inputs,
+            "outputs": outputs
+        });
+        if !node.dynamic_inputs.is_empty() {
+            entry["dynamic_inputs"] = registry_ports_to_json(&node.dynamic_inputs);
+        }
+        obj.insert(node_name.clone(), entry);
     }
     Value::Object(obj)
 }
 
+fn port_to_json(p: &RegistryPort) -> Value {
+    let mut port = json!({
+        "name": p.name,
+        "type": p.ty,
+        "tooltip": p.tooltip
+    });
+    if let Some(d) = &p.default {
+        port["default"] = d.clone();
+    }
+    if let Some(options) = &p.options {
+        port["options"] = json!(options);
+    }
+    if let Some(range) = p.range_json() {
+        port["range"] = range;
+    }
+    if let Some(visible) = p.visible {
+        port["visible"] = Value::Bool(visible);
+    }
+    if let Some(display) = &p.display {
+        port["display"] = Value::String(display.clone());
+    }
+    port
+}
+
 fn registry_ports_to_json(ports: &[RegistryPort]) -> Value {
-    Value::Array(
-        ports
-            .iter()
-            .map(|p| {
-                json!({
-                    "name": p.name,
-                    "type": p.ty,
-                    "tooltip": p.tooltip
-                })
-            })
-            .collect(),
-    )
+    Value::Array(ports.iter().map(port_to_json).collect())
 }
 
 fn infer_type_from_fn(func: &str) -> Option<String> {
@@ -297,6 +557,12 @@ fn infer_type_from_fn(func: &str) -> Option<String> {
         "vec4"
     } else if f.contains("range") {
         "range"
+    } else if f.contains("slider") {
+        "float"
+    } else if f.contains("dimension") {
+        "vec2"
+    } else if f.contains("padding") {
+        "vec4"
     } else if f.contains("matrix") {
         "matrix"
     } else if f.contains("palette") {
@@ -327,53 +593,316 @@ fn infer_type_from_fn(func: &str) -> Option<String> {
     Some(ty.to_string())
 }
 
-fn infer_type_from_fn_with_value(func: &str, snippet: &str, value_re: &Regex) -> Option<String> {
+/// The `type` recorded for a port: the constructor name, as in the embedded
+/// registry, or the `VALUE_TYPE` member for the generic `nodeValue` constructors.
+fn port_type(func: &str, args: &[Expr]) -> Option<String> {
     let func_lower = func.to_lowercase();
-    if func_lower == "nodevalue"
-        || func_lower == "nodevalue_output"
-        || func_lower == "nodevalue_output".to_string()
-    {
-        if let Some(cap) = value_re.captures(snippet) {
-            if let Some(m) = cap.get(1) {
-                return Some(m.as_str().to_lowercase());
-            }
+    if func_lower == "nodevalue" || func_lower == "nodevalue_output" {
+        let value_type = args
+            .iter()
+            .filter_map(Expr::path)
+            .find_map(|p| p.strip_prefix("VALUE_TYPE.").map(str::to_lowercase));
+        if value_type.is_some() {
+            return value_type;
         }
     }
-    infer_type_from_fn(func)
+    (!func.is_empty()).then(|| func.to_string())
 }
 
-fn extract_slot(snippet: &str, key: &str) -> Option<usize> {
-    let idx = snippet.find(key)?;
-    let mut found = false;
-    let mut num = String::new();
-    for ch in snippet[idx + key.len()..].chars() {
-        if ch.is_ascii_digit() {
-            found = true;
-            num.push(ch);
-        } else if found {
-            break;
+/// Ports indexed by slot. Calls with a computed slot belong to the dynamic group.
+fn ports_from_calls(calls: &[PortCall], input: bool) -> Vec<Option<RegistryPort>> {
+    let mut ports: Vec<Option<RegistryPort>> = Vec::new();
+    for call in calls {
+        let Some(slot) = call.slot else {
+            continue;
+        };
+        if ports.len() <= slot {
+            ports.resize_with(slot + 1, || None);
         }
+        ports[slot] = Some(port_from_call(call, input));
     }
-    if num.is_empty() {
-        None
+    ports
+}
+
+/// The repeating group of a variable-length node: calls whose slot is computed
+/// as `index + N`, placed at `N`.
+fn dynamic_ports_from_calls(calls: &[PortCall]) -> Vec<RegistryPort> {
+    let mut ports: Vec<Option<RegistryPort>> = Vec::new();
+    for call in calls.iter().filter(|c| c.slot.is_none()) {
+        let Some(offset) = call.offset else {
+            continue;
+        };
+        if ports.len() <= offset {
+            ports.resize_with(offset + 1, || None);
+        }
+        ports[offset] = Some(port_from_call(call, true));
+    }
+    compact_ports(ports)
+}
+
+/// Reads a `nodeValue_*(...)` constructor call plus any chained
+/// `.setDisplay(...)`-style calls.
+fn port_from_call(call: &PortCall, input: bool) -> RegistryPort {
+    let func = call.ctor.as_deref().unwrap_or("");
+    let args = &call.args;
+    let mut port = RegistryPort {
+        name: args.first().and_then(gml_string),
+        ty: port_type(func, args),
+        ..RegistryPort::default()
+    };
+
+    // Arguments after the name: `self`, legacy `JUNCTION_CONNECT`/`VALUE_TYPE`
+    // markers, then the default value and constructor-specific extras.
+    let rest: Vec<&Expr> = args
+        .iter()
+        .skip(1)
+        .skip_while(|a| match a.path() {
+            Some(p) => {
+                p == "self" || p.starts_with("JUNCTION_CONNECT.") || p.starts_with("VALUE_TYPE.")
+            }
+            None => false,
+        })
+        .collect();
+    let func_lower = func.to_ascii_lowercase();
+    if input {
+        port.default = rest.first().and_then(|a| gml_literal(a));
+        if func_lower.contains("enum") {
+            port.options = rest.get(1).and_then(|a| gml_options(a));
+        }
+        if func_lower.contains("slider")
+            && !func_lower.contains("range")
+            && let Some((range, step)) = rest.get(1).and_then(|a| gml_range(a))
+        {
+            port.range = Some(range);
+            port.step = step;
+        }
+    }
+    for (method, margs) in &call.chain {
+        match method.as_str() {
+            "setDisplay" => {
+                let display = margs.first().and_then(Expr::path).unwrap_or_default();
+                let display = display.strip_prefix("VALUE_DISPLAY.").unwrap_or(&display);
+                if !display.is_empty() {
+                    port.display = Some(display.to_string());
+                }
+                let extra = margs.get(1);
+                if display.starts_with("enum") {
+                    port.options = extra.and_then(gml_options).or(port.options.take());
+                } else if display == "slider" {
+                    let range = extra.map(|e| e.field("range").unwrap_or(e));
+                    if let Some((r, step)) = range.and_then(gml_range) {
+                        port.range = Some(r);
+                        port.step = step;
+                    }
+                }
+            }
+            "setVisible" => {
+                port.visible = margs.first().and_then(|a| gml_literal(a)?.as_bool());
+            }
+            "setTooltip" => {
+                port.tooltip = margs.first().and_then(gml_string);
+            }
+            _ => {}
+        }
+    }
+    port
+}
+
+/// A string literal, optionally wrapped in `__txt(...)`.
+fn gml_string(e: &Expr) -> Option<String> {
+    match e.callee() {
+        Some((f, args)) if f == "__txt" => args.first()?.as_str().map(str::to_string),
+        _ => e.as_str().map(str::to_string),
+    }
+}
+
+/// GML color constants (BGR) with full alpha, as Pixel Composer stores them.
+fn gml_color(name: &str) -> Option<u32> {
+    let bgr = match name {
+        "c_aqua" => 0xFFFF00,
+        "c_black" => 0x000000,
+        "c_blue" => 0xFF0000,
+        "c_dkgray" | "c_dkgrey" => 0x404040,
+        "c_fuchsia" => 0xFF00FF,
+        "c_gray" | "c_grey" => 0x808080,
+        "c_green" => 0x008000,
+        "c_lime" => 0x00FF00,
+        "c_ltgray" | "c_ltgrey" => 0xC0C0C0,
+        "c_maroon" => 0x000080,
+        "c_navy" => 0x800000,
+        "c_olive" => 0x008080,
+        "c_orange" => 0x40A0FF,
+        "c_purple" => 0x800080,
+        "c_red" => 0x0000FF,
+        "c_silver" => 0xC0C0C0,
+        "c_teal" => 0x808000,
+        "c_white" => 0xFFFFFF,
+        "c_yellow" => 0x00FFFF,
+        _ => return None,
+    };
+    Some(0xFF00_0000 | bgr)
+}
+
+/// Converts a GML literal (number, string, bool, color constant, `noone`, or an
+/// array of those) to the JSON a project stores. Expressions yield `None`.
+fn gml_literal(e: &Expr) -> Option<Value> {
+    match e {
+        Expr::Ident(s) => match s.as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            "noone" => Some(json!(-4)),
+            c => gml_color(c).map(|c| json!(c)),
+        },
+        Expr::Array(items) => items
+            .iter()
+            .map(gml_literal)
+            .collect::<Option<Vec<_>>>()
+            .map(Value::Array),
+        Expr::Number(n) => gml_number(n, false),
+        Expr::Neg(inner) => match &**inner {
+            Expr::Number(n) => gml_number(n, true),
+            _ => None,
+        },
+        _ => gml_string(e).map(Value::String),
+    }
+}
+
+fn gml_number(digits: &str, neg: bool) -> Option<Value> {
+    let int = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix('$'))
+    {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()
     } else {
-        num.parse::<usize>().ok()
+        digits.parse::<i64>().ok()
+    };
+    if let Some(i) = int {
+        return Some(json!(if neg { -i } else { i }));
     }
+    let f: f64 = digits.parse().ok().filter(|f: &f64| f.is_finite())?;
+    Some(json!(if neg { -f } else { f }))
 }
 
-fn compact_ports(ports: Vec<Option<RegistryPort>>) -> Vec<RegistryPort> {
-    ports
-        .into_iter()
-        .map(|p| {
-            p.unwrap_or(RegistryPort {
-                name: None,
-                ty: None,
-                tooltip: None,
-            })
+/// Enum labels from `["A", "B"]`, `[new scrollItem("A", ...), ...]` or
+/// `__enum_array_gen(["A", "B"], ...)`.
+fn gml_options(e: &Expr) -> Option<Vec<String>> {
+    if let Some((f, args)) = e.callee()
+        && f == "__enum_array_gen"
+    {
+        return gml_options(args.first()?);
+    }
+    let Expr::Array(items) = e else {
+        return None;
+    };
+    let items: Vec<String> = items
+        .iter()
+        .map(|item| {
+            gml_string(item)
+                .or_else(|| gml_string(item.callee()?.1.first()?))
+                .unwrap_or_default()
         })
-        .collect()
+        .collect();
+    (!items.is_empty()).then_some(items)
 }
 
-fn expand_ports(ports: Vec<RegistryPort>) -> Vec<Option<RegistryPort>> {
-    ports.into_iter().map(Some).collect()
+/// `[min, max]` or `[min, max, step]`.
+fn gml_range(e: &Expr) -> Option<((f64, f64), Option<f64>)> {
+    let values = gml_literal(e)?;
+    let values = values.as_array()?;
+    let min = values.first()?.as_f64()?;
+    let max = values.get(1)?.as_f64()?;
+    Some(((min, max), values.get(2).and_then(|v| v.as_f64())))
+}
+
+fn compact_ports(ports: Vec<Option<RegistryPort>>) -> Vec<RegistryPort> {
+    ports.into_iter().map(Option::unwrap_or_default).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(versions: &[u32]) -> Vec<(Option<u32>, RegistrySource)> {
+        versions
+            .iter()
+            .map(|v| (Some(*v), RegistrySource::Embedded("{}")))
+            .collect()
+    }
+
+    #[test]
+    fn picks_newest_registry_not_newer_than_the_project() {
+        let regs = candidates(&[11900, 1180, 12000]);
+        assert_eq!(pick_registry(&regs, Some(11950)), 0);
+        assert_eq!(pick_registry(&regs, Some(12000)), 2);
+        assert_eq!(pick_registry(&regs, Some(99999)), 2);
+        // Older than every registry: the oldest one.
+        assert_eq!(pick_registry(&regs, Some(1000)), 1);
+        // No version: the newest one.
+        assert_eq!(pick_registry(&regs, None), 2);
+    }
+
+    #[test]
+    fn directory_scan_keys_files_by_trailing_digits() {
+        let dir = std::env::temp_dir().join(format!("pxc-registries-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "1180.json",
+            "registry-11900.json",
+            "latest.json",
+            "12000.txt",
+        ] {
+            fs::write(dir.join(name), "{}").unwrap();
+        }
+        let mut versions: Vec<Option<u32>> = registry_candidates(Some(&dir))
+            .unwrap()
+            .into_iter()
+            .map(|(v, _)| v)
+            .collect();
+        versions.sort();
+        assert_eq!(versions, [Some(1180), Some(11900)]);
+        let choice = registry_choice(Some(&dir), Some(11950)).unwrap();
+        assert_eq!(choice.version, Some(11900));
+        assert!(choice.source.ends_with("registry-11900.json"));
+        assert_eq!(choice.available, [1180, 11900]);
+
+        for name in ["1180.json", "registry-11900.json"] {
+            fs::remove_file(dir.join(name)).unwrap();
+        }
+        let err = registry_candidates(Some(&dir)).err().unwrap();
+        assert!(err.to_string().starts_with("no versioned registries"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn ports_serialize_in_the_registry_file_layout() {
+        let port = RegistryPort {
+            name: Some("Size".to_string()),
+            ty: Some("nodeValue_Slider".to_string()),
+            range: Some((1.0, 32.0)),
+            step: Some(0.5),
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&port).unwrap();
+        assert_eq!(
+            value,
+            json!({"name": "Size", "type": "nodeValue_Slider", "tooltip": null,
+                   "range": [1.0, 32.0, 0.5]})
+        );
+        let back = &parse_registry_ports(Some(&json!([value])))[0];
+        assert_eq!((back.range, back.step), (port.range, port.step));
+    }
+
+    #[test]
+    #[ignore = "registry_embedded.json predates dynamic inputs; regenerate it with scripts/embed_registry.py"]
+    fn embedded_composite_has_dynamic_inputs() {
+        let registry = load_registry(None, None).unwrap().unwrap();
+        let node = &registry.nodes["Node_Composite"];
+        assert!(!node.dynamic_inputs.is_empty());
+        assert_eq!(
+            node.dynamic_inputs[0].ty.as_deref(),
+            Some("nodeValue_Surface")
+        );
+    }
 }
@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use serde_json::Value;
+
+use crate::graph::GraphData;
+use crate::notes::Note;
+use crate::registry::Registry;
+
+const HEADER_H: f64 = 40.0;
+const ROW_H: f64 = 18.0;
+const MIN_NODE_W: f64 = 140.0;
+const CHAR_W: f64 = 7.0;
+const FRAME_PAD: f64 = 24.0;
+const FRAME_TITLE_H: f64 = 22.0;
+const MARGIN: f64 = 40.0;
+
+struct Port {
+    slot: usize,
+    label: String,
+    ty: Option<String>,
+}
+
+struct NodeBox {
+    key: String,
+    name: String,
+    typ: String,
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    inputs: Vec<Port>,
+    outputs: Vec<Port>,
+}
+
+impl NodeBox {
+    fn input_anchor(&self, slot: usize) -> (f64, f64) {
+        let row = self.inputs.iter().position(|p| p.slot == slot);
+        (self.x, self.row_y(row))
+    }
+
+    fn output_anchor(&self, slot: usize) -> (f64, f64) {
+        let row = self.outputs.iter().position(|p| p.slot == slot);
+        (self.x + self.w, self.row_y(row))
+    }
+
+    fn row_y(&self, row: Option<usize>) -> f64 {
+        match row {
+            Some(r) => self.y + HEADER_H + ROW_H * r as f64 + ROW_H / 2.0,
+            None => self.y + HEADER_H / 2.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Rect {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+}
+
+impl Rect {
+    fn union(self, other: Rect) -> Rect {
+        Rect {
+            x0: self.x0.min(other.x0),
+            y0: self.y0.min(other.y0),
+            x1: self.x1.max(other.x1),
+            y1: self.y1.max(other.y1),
+        }
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn port_color(ty: Option<&str>) -> &'static str {
+    match ty.map(|t| t.to_ascii_lowercase()).as_deref() {
+        Some("surface") => "#8fb8ff",
+        Some("float") | Some("integer") | Some("rotation") | Some("range") => "#9be39b",
+        Some("boolean") | Some("trigger") => "#f2a2a2",
+        Some("color") | Some("palette") | Some("gradient") => "#f5c46b",
+        Some("vec2") | Some("vec3") | Some("vec4") | Some("matrix") => "#7fd6c9",
+        Some("text") | Some("path") | Some("pathnode") => "#d0a7f0",
+        Some("enum") => "#c9c9c9",
+        _ => "#b0b0b0",
+    }
+}
+
+fn build_box(key: String, node: &Value, registry: Option<&Registry>) -> NodeBox {
+    let name = node
+        .get("name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    let typ = node
+        .get("type")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    let x = node.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0);
+    let y = node.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0);
+    let reg_node = registry.and_then(|r| r.nodes.get(&typ));
+
+    let mut inputs = Vec::new();
+    if let Some(arr) = node.get("inputs").and_then(|v| v.as_array()) {
+        for (i, input) in arr.iter().enumerate() {
+            let connected = input.get("from_node").is_some();
+            let hidden = input.get("visible").and_then(|v| v.as_bool()) == Some(false);
+            if hidden && !connected {
+                continue;
+            }
+            let rp = reg_node.and_then(|n| n.input(i));
+            inputs.push(Port {
+                slot: i,
+                label: reg_node
+                    .and_then(|n| n.input_name(i))
+                    .unwrap_or_else(|| format!("in{}", i)),
+                ty: rp.and_then(|p| p.value_type()),
+            });
+        }
+    }
+    let mut outputs = Vec::new();
+    if let Some(arr) = node.get("outputs").and_then(|v| v.as_array()) {
+        for i in 0..arr.len() {
+            let rp = reg_node.and_then(|n| n.outputs.get(i));
+            outputs.push(Port {
+                slot: i,
+                label: rp
+                    .and_then(|p| p.name.clone())
+                    .unwrap_or_else(|| format!("out{}", i)),
+                ty: rp.and_then(|p| p.value_type()),
+            });
+        }
+    }
+
+    let widest_in = inputs.iter().map(|p| p.label.len()).max().unwrap_or(0);
+    let widest_out = outputs.iter().map(|p| p.label.len()).max().unwrap_or(0);
+    let title = name.len().max(typ.len());
+    let w = MIN_NODE_W
+        .max(title as f64 * CHAR_W + 20.0)
+        .max((widest_in + widest_out) as f64 * CHAR_W + 40.0);
+    let rows = inputs.len().max(outputs.len());
+    let h = HEADER_H + ROW_H * rows as f64 + 8.0;
+
+    NodeBox {
+        key,
+        name,
+        typ,
+        x,
+        y,
+        w,
+        h,
+        inputs,
+        outputs,
+    }
+}
+
+/// Pushes a frame for `group_id` and each nested group into `out`; returns its bounds.
+fn collect_frames(
+    graph: &GraphData,
+    boxes: &HashMap<String, NodeBox>,
+    group_id: &str,
+    depth: usize,
+    out: &mut Vec<(usize, String, Rect)>,
+) -> Option<Rect> {
+    let mut bounds: Option<Rect> = None;
+    for child in graph.children(group_id) {
+        let rect = if graph.is_group(&child) {
+            collect_frames(graph, boxes, &child, depth + 1, out)
+        } else {
+            None
+        };
+        let own = boxes.get(&child).map(|b| Rect {
+            x0: b.x,
+            y0: b.y,
+            x1: b.x + b.w,
+            y1: b.y + b.h,
+        });
+        for r in [rect, own].into_iter().flatten() {
+            bounds = Some(match bounds {
+                Some(acc) => acc.union(r),
+                None => r,
+            });
+        }
+    }
+    let bounds = bounds?;
+    let frame = Rect {
+        x0: bounds.x0 - FRAME_PAD,
+        y0: bounds.y0 - FRAME_PAD - FRAME_TITLE_H,
+        x1: bounds.x1 + FRAME_PAD,
+        y1: bounds.y1 + FRAME_PAD,
+    };
+    out.push((depth, group_id.to_string(), frame));
+    Some(frame)
+}
+
+/// Draws the graph at the positions stored in the project, as a standalone SVG.
+pub(crate) fn render_svg(
+    graph: &GraphData,
+    nodes: &[Value],
+    notes: &[Note],
+    registry: Option<&Registry>,
+) -> String {
+    let by_id: HashMap<&str, &Value> = nodes
+        .iter()
+        .filter_map(|n| n.get("id").and_then(|v| v.as_str()).map(|id| (id, n)))
+        .collect();
+
+    let mut boxes: HashMap<String, NodeBox> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+    for id in graph.node_map.keys() {
+        if let Some(node) = by_id.get(id.as_str()) {
+            boxes.insert(id.clone(), build_box(graph.key(id), node, registry));
+            order.push(id.clone());
+        }
+    }
+
+    let mut frames = Vec::new();
+    for id in graph.node_map.keys() {
+        if graph.parent(id).is_none() && graph.is_group(id) {
+            collect_frames(graph, &boxes, id, 0, &mut frames);
+        }
+    }
+    frames.sort_by_key(|(depth, _, _)| *depth);
+
+    let mut bounds: Option<Rect> = None;
+    let rects = boxes
+        .values()
+        .map(|b| Rect {
+            x0: b.x,
+            y0: b.y,
+            x1: b.x + b.w,
+            y1: b.y + b.h,
+        })
+        .chain(frames.iter().map(|(_, _, r)| *r))
+        .chain(notes.iter().map(|n| Rect {
+            x0: n.x,
+            y0: n.y,
+            x1: n.x + n.w,
+            y1: n.y + n.h,
+        }));
+    for r in rects {
+        bounds = Some(match bounds {
+            Some(acc) => acc.union(r),
+            None => r,
+        });
+    }
+    let bounds = bounds.unwrap_or(Rect {
+        x0: 0.0,
+        y0: 0.0,
+        x1: 0.0,
+        y1: 0.0,
+    });
+    let vx = bounds.x0 - MARGIN;
+    let vy = bounds.y0 - MARGIN;
+    let vw = bounds.x1 - bounds.x0 + MARGIN * 2.0;
+    let vh = bounds.y1 - bounds.y0 + MARGIN * 2.0;
+
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" width=\"{}\" height=\"{}\">",
+        vx, vy, vw, vh, vw, vh
+    );
+    s.push_str(
+        "<style>\
+         text{font-family:sans-serif;font-size:11px;fill:#ddd}\
+         .frame{fill:#ffffff0d;stroke:#888;stroke-dasharray:6 4}\
+         .frame-title{font-size:12px;font-weight:bold;fill:#bbb}\
+         .node{fill:#2b2b33;stroke:#555}\
+         .name{font-size:12px;font-weight:bold;fill:#fff}\
+         .type{fill:#999}\
+         .edge{fill:none;stroke:#9aa;stroke-width:2}\
+         .edge.tag{stroke-dasharray:4 3}\
+         .note{fill-opacity:0.85;stroke:#0004}\
+         .note-text{fill:#222}\
+         </style>\n",
+    );
+    let _ = writeln!(
+        s,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#1e1e24\"/>",
+        vx, vy, vw, vh
+    );
+
+    for note in notes {
+        let _ = writeln!(
+            s,
+            "<g data-note=\"{}\"><rect class=\"note\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"{}\"/>",
+            note.index,
+            note.x,
+            note.y,
+            note.w,
+            note.h,
+            note.color_hex()
+        );
+        // Lines that do not fit the note's height are left out, as in the editor.
+        let lines = ((note.h - 8.0) / ROW_H).floor().max(0.0) as usize;
+        for (row, line) in note.text.lines().take(lines).enumerate() {
+            let _ = writeln!(
+                s,
+                "  <text class=\"note-text\" x=\"{}\" y=\"{}\">{}</text>",
+                note.x + 8.0,
+                note.y + ROW_H * (row + 1) as f64,
+                escape_xml(line)
+            );
+        }
+        s.push_str("</g>\n");
+    }
+
+    for (_, group_id, r) in frames.iter() {
+        let title = by_id
+            .get(group_id.as_str())
+            .and_then(|n| n.get("name"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let _ = writeln!(
+            s,
+            "<g class=\"group\" data-id=\"{}\"><rect class=\"frame\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"8\"/><text class=\"frame-title\" x=\"{}\" y=\"{}\">{}</text></g>",
+            escape_xml(&graph.key(group_id)),
+            r.x0,
+            r.y0,
+            r.x1 - r.x0,
+            r.y1 - r.y0,
+            r.x0 + 8.0,
+            r.y0 + 16.0,
+            escape_xml(title)
+        );
+    }
+
+    for edge in graph.edges.iter() {
+        let from = edge.get("f").and_then(|v| v.as_str()).unwrap_or("");
+        let to = edge.get("t").and_then(|v| v.as_str()).unwrap_or("");
+        let (Some(fb), Some(tb)) = (boxes.get(from), boxes.get(to)) else {
+            continue;
+        };
+        let from_index = edge.get("fo").and_then(|v| v.as_i64()).unwrap_or(0);
+        let to_input = edge.get("ti").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+        let (x1, y1) = fb.output_anchor(usize::try_from(from_index).unwrap_or(0));
+        let (x2, y2) = tb.input_anchor(to_input);
+        let dx = ((x2 - x1).abs() / 2.0).max(40.0);
+        let class = if edge.get("tg").is_some() {
+            "edge tag"
+        } else {
+            "edge"
+        };
+        let _ = writeln!(
+            s,
+            "<path class=\"{}\" d=\"M {} {} C {} {}, {} {}, {} {}\"><title>{} out{} -&gt; {} in{}</title></path>",
+            class,
+            x1,
+            y1,
+            x1 + dx,
+            y1,
+            x2 - dx,
+            y2,
+            x2,
+            y2,
+            escape_xml(&fb.key),
+            from_index,
+            escape_xml(&tb.key),
+            to_input
+        );
+    }
+
+    for id in order.iter() {
+        let b = &boxes[id];
+        let _ = writeln!(s, "<g data-id=\"{}\">", escape_xml(&b.key));
+        let _ = writeln!(
+            s,
+            "  <rect class=\"node\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"6\"/>",
+            b.x, b.y, b.w, b.h
+        );
+        let _ = writeln!(
+            s,
+            "  <text class=\"name\" x=\"{}\" y=\"{}\">{}</text>",
+            b.x + 8.0,
+            b.y + 16.0,
+            escape_xml(&b.name)
+        );
+        let _ = writeln!(
+            s,
+            "  <text class=\"type\" x=\"{}\" y=\"{}\">{}</text>",
+            b.x + 8.0,
+            b.y + 31.0,
+            escape_xml(&b.typ)
+        );
+        for (row, port) in b.inputs.iter().enumerate() {
+            let py = b.row_y(Some(row));
+            let _ = writeln!(
+                s,
+                "  <circle cx=\"{}\" cy=\"{}\" r=\"4\" fill=\"{}\"/><text x=\"{}\" y=\"{}\">{}</text>",
+                b.x,
+                py,
+                port_color(port.ty.as_deref()),
+                b.x + 8.0,
+                py + 4.0,
+                escape_xml(&port.label)
+            );
+        }
+        for (row, port) in b.outputs.iter().enumerate() {
+            let py = b.row_y(Some(row));
+            let _ = writeln!(
+                s,
+                "  <circle cx=\"{}\" cy=\"{}\" r=\"4\" fill=\"{}\"/><text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+                b.x + b.w,
+                py,
+                port_color(port.ty.as_deref()),
+                b.x + b.w - 8.0,
+                py + 4.0,
+                escape_xml(&port.label)
+            );
+        }
+        s.push_str("</g>\n");
+    }
+
+    s.push_str("</svg>\n");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::collect_graph;
+    use serde_json::json;
+
+    #[test]
+    fn draws_edges_and_group_frames() {
+        // `g` (A) holds `a` (B), which feeds `b` (C) outside the group.
+        let nodes = json!([
+            {"id": "g", "type": "Node_Group", "name": "Grp", "x": 0, "y": 0},
+            {"id": "a", "type": "Node_T", "group": "g", "x": 0, "y": 0, "outputs": [{}]},
+            {"id": "b", "type": "Node_T", "x": 300, "y": 0,
+             "inputs": [{"from_node": "a", "from_index": 0}]},
+        ]);
+        let nodes = nodes.as_array().unwrap();
+        let graph = collect_graph(nodes, false, false, None).unwrap();
+        let svg = render_svg(&graph, nodes, &[], None);
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains("<g class=\"group\" data-id=\"A\"><rect class=\"frame\""));
+        assert!(svg.contains("<text class=\"frame-title\""));
+        assert!(svg.contains(">Grp</text></g>"));
+        assert!(svg.contains("<title>B out0 -&gt; C in0</title>"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,50 @@
+function Node_create_Blend(_x, _y, _group = noone, _param = {}) {
+	var node = new Node_Blend(_x, _y, _group);
+	node.skipDefault();
+	return node;
+}
+
+#region blend modes
+#macro BLEND_TYPES [ "Normal", "Add", "Subtract", "Multiply" ]
+#endregion
+
+function Node_Blend(_x, _y, _group = noone) : Node_Processor(_x, _y, _group) constructor {
+	name = "Blend";
+	
+	newInput(0, nodeValue_Surface("Background", self));
+	newInput(1, nodeValue_Surface("Foreground", self));
+	
+	newInput(2, nodeValue_Enum_Scroll("Blend mode", self, 0, [ "Normal", "Add", "Subtract", "Multiply" ]))
+		.rejectArray();
+	
+	newInput(3, nodeValue_Slider("Opacity", self, 1));
+	
+	// newInput(4, nodeValue_Surface("Mask", self)); // moved to slot 5
+	
+	newInput(5, nodeValue_Surface("Mask", self));
+	
+	newInput(6, nodeValue_Enum_Scroll("Fill mode", self, 0, [ "None", "Stretch", "Tile" ]))
+		.setTooltip("How the foreground covers the background, e.g. \"Tile\" repeats it (no scaling).");
+	
+	newInput(7, nodeValue_Vec2("Position", self, [ 0.5, 0.5 ]))
+		.setUnitRef(function(index) { return getDimension(index); }, VALUE_UNIT.reference);
+	
+	newOutput(0, nodeValue_Output("Surface Out", self, VALUE_TYPE.surface, noone));
+	
+	newOutput(1, nodeValue_Output("Mask Out", self, VALUE_TYPE.surface, noone))
+		.setVisible(false);
+}
+
+function Node_Blend_Stack(_x, _y, _group = noone) : Node_Blend(_x, _y, _group) constructor {
+	name = "Blend Stack";
+	
+	newInput(0, nodeValue_Dimension(self));
+	
+	static createNewInput = function(index = array_length(inputs)) {
+		newInput(index + 0, nodeValue_Surface("Layer", self));
+		newInput(index + 1, nodeValue_Enum_Scroll("Layer mode", self, 0, BLEND_TYPES));
+		return inputs[index];
+	}
+	
+	newOutput(0, nodeValue_Output("Surface Out", self, VALUE_TYPE.surface, noone));
+}
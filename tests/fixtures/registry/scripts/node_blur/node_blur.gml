@@ -0,0 +1,29 @@
+function Node_Blur(_x, _y, _group = noone) : Node_Processor(_x, _y, _group) constructor {
+	name = "Blur";
+	
+	newInput(0, nodeValue_Surface("Surface In", self));
+	
+	newInput(1, nodeValue_Int("Size", self, 3))
+		.setValidator(VV_min(0))
+		.setDisplay(VALUE_DISPLAY.slider, { range: [1, 32, 0.1] });
+	
+	newInput(2, nodeValue_Enum_Scroll("Oversample mode", self,  0, [ "Empty", "Clamp", "Repeat" ]))
+		.setTooltip("How to deal with pixel outside the surface.");
+	
+	newInput(3, nodeValue_Bool("Override color", self, false, "Replace all color"));
+	
+	newInput(4, nodeValue_Color("Color", self, c_black));
+	
+	newInput(6, nodeValue_Slider("Mix", self, 1));
+	
+	newInput(7, nodeValue_Bool("Active", self, true));
+	
+	newInput(8, nodeValue_Vec2("Position", self, [ 0, -0.5 ]))
+		.setVisible(false);
+
+	newInput(9, nodeValue_Enum_Button("Mode", self, 1, [ new scrollItem("Linear", s_a), new scrollItem("Radial", s_b) ]));
+
+	newInput(10, nodeValue_Slider("Feather", self, 0, [1, 16, 0.1]));
+	
+	newOutput(0, nodeValue_Output("Surface Out", self, VALUE_TYPE.surface, noone));
+}
@@ -0,0 +1,95 @@
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::Value;
+
+fn build_fixture_registry(tag: &str) -> Value {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/registry");
+    let out =
+        std::env::temp_dir().join(format!("pxc-registry-{}-{}.json", std::process::id(), tag));
+    let status = Command::new(env!("CARGO_BIN_EXE_pxc-tools"))
+        .arg("registry-build")
+        .arg("--scripts")
+        .arg(fixtures.join("scripts"))
+        .arg("--locale")
+        .arg(fixtures.join("nodes.json"))
+        .arg("--out")
+        .arg(&out)
+        .status()
+        .expect("run pxc-tools");
+    assert!(status.success());
+    let text = std::fs::read_to_string(&out).expect("read built registry");
+    let _ = std::fs::remove_file(&out);
+    serde_json::from_str(&text).expect("registry is JSON")
+}
+
+/// Checks the layout `registry_embedded.json` uses: node name -> `inputs` and
+/// `outputs` arrays indexed by slot, each port carrying `name`, `type` and
+/// `tooltip` (null when unknown).
+fn assert_embedded_layout(registry: &Value) {
+    let nodes = registry.as_object().expect("registry is an object");
+    assert!(!nodes.is_empty());
+    for (name, node) in nodes {
+        let node = node.as_object().expect("node is an object");
+        for key in node.keys() {
+            assert!(
+                matches!(key.as_str(), "inputs" | "outputs" | "dynamic_inputs"),
+                "{name}: unexpected key {key}"
+            );
+        }
+        assert!(
+            node.contains_key("inputs") && node.contains_key("outputs"),
+            "{}",
+            name
+        );
+        for port in node.values().flat_map(|ports| ports.as_array().unwrap()) {
+            for key in ["name", "type", "tooltip"] {
+                let value = port
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{name}: port lacks {key}"));
+                // Tooltips are copied from the locale file as-is.
+                assert!(
+                    key == "tooltip" || value.is_null() || value.is_string(),
+                    "{name}: {key} = {value}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn registry_build_matches_fixture() {
+    let built = build_fixture_registry("fixture");
+    let expected: Value =
+        serde_json::from_str(include_str!("fixtures/registry/expected.json")).unwrap();
+    assert_eq!(built, expected);
+}
+
+#[test]
+fn registry_build_uses_embedded_format() {
+    let embedded: Value =
+        serde_json::from_str(include_str!("../src/registry_embedded.json")).unwrap();
+    assert_embedded_layout(&embedded);
+
+    let built = build_fixture_registry("format");
+    assert_embedded_layout(&built);
+    // Input types are constructor names, as in the embedded registry.
+    let blur = &built["Node_Blur"]["inputs"];
+    assert_eq!(blur[0]["type"], embedded["Node_Blur"]["inputs"][0]["type"]);
+    assert_eq!(blur[1]["type"], "nodeValue_Int");
+    // Gaps in slot numbering are kept as empty ports.
+    assert_eq!(built["Node_Blur"]["inputs"][5]["name"], Value::Null);
+    // Locale-only nodes are included, like the Python builder did.
+    assert_eq!(built["Node_Number"]["inputs"][0]["name"], "Value");
+}
+
+#[test]
+fn registry_build_records_dynamic_inputs() {
+    let built = build_fixture_registry("dynamic");
+    // Computed `index + N` slots form the repeating group of dynamic nodes.
+    let stack = &built["Node_Blend_Stack"];
+    assert_eq!(stack["inputs"].as_array().unwrap().len(), 1);
+    assert_eq!(stack["dynamic_inputs"][0]["name"], "Layer");
+    assert_eq!(stack["dynamic_inputs"][1]["name"], "Layer mode");
+    assert!(built["Node_Blend"].get("dynamic_inputs").is_none());
+}